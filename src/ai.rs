@@ -0,0 +1,299 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+mod alphabeta;
+mod mcts;
+
+pub use alphabeta::AlphaBetaEngine;
+pub use mcts::MctsEngine;
+
+use crate::board::PosType;
+use crate::game_state::{FieldState, GameState, Move, MoveState, Turn, WinState};
+use crate::print::Print;
+use anyhow as ah;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Score of a won game.
+const SCORE_WIN: i32 = 100_000;
+
+/// A computer player engine.
+/// The engine searches on a worker thread.
+pub trait Engine: Send {
+    /// Get the name of the engine.
+    fn name(&self) -> &str;
+
+    /// Select a move for the side to move.
+    /// Returns None, if there is no legal move.
+    fn select_move(&mut self, game: &GameState) -> Option<Move>;
+}
+
+/// Evaluate the position from the point of view of the wolves.
+/// Positive values are good for the wolves and negative values are good for the sheep.
+fn evaluate_wolf(game: &GameState) -> i32 {
+    match game.get_win_state() {
+        WinState::Wolf => return SCORE_WIN,
+        WinState::Sheep => return -SCORE_WIN,
+        WinState::Draw => return 0,
+        WinState::Undecided => (),
+    }
+
+    let stats = game.get_stats();
+    let mut score = stats.sheep_captured as i32 * 100;
+
+    for (coord, pos_type) in game.board().iter_pos() {
+        if game.get_field_state(coord) == FieldState::Sheep {
+            match pos_type {
+                PosType::Barn => score -= 30,
+                // Reward the sheep for advancing towards the barn.
+                PosType::Field => score -= 6 - coord.y as i32,
+                PosType::Invalid => (),
+            }
+        }
+    }
+
+    score += game.possible_moves(Turn::Wolf).len() as i32 * 5;
+    score
+}
+
+/// Evaluate the position from the point of view of the given side.
+fn evaluate(game: &GameState, turn: Turn) -> i32 {
+    match turn {
+        Turn::Wolf => evaluate_wolf(game),
+        Turn::Sheep => -evaluate_wolf(game),
+    }
+}
+
+/// Computer player configuration.
+#[derive(Clone, Debug)]
+pub struct ComputerConfig {
+    /// Engine name. May be "alphabeta" or "mcts".
+    pub engine: String,
+    /// Maximum search depth of the alpha-beta engine.
+    pub depth: u32,
+    /// Maximum thinking time per move.
+    pub time_limit: Option<Duration>,
+    /// Number of search iterations per move of the MCTS engine.
+    pub iterations: u32,
+}
+
+impl ComputerConfig {
+    /// Create the configured engine.
+    pub fn new_engine(&self) -> ah::Result<Box<dyn Engine>> {
+        match self.engine.to_lowercase().trim() {
+            "alphabeta" => Ok(Box::new(AlphaBetaEngine::new(self.depth, self.time_limit))),
+            "mcts" => Ok(Box::new(MctsEngine::new(self.iterations, self.time_limit))),
+            other => Err(ah::format_err!("Unknown computer engine: {}", other)),
+        }
+    }
+
+    /// Create a computer player for one side.
+    pub fn new_computer(&self, side: Turn) -> ah::Result<Computer> {
+        Ok(Computer::new(side, self.new_engine()?))
+    }
+}
+
+/// A search for a computer move running on a worker thread.
+struct Search {
+    /// The hash of the searched position.
+    position: u64,
+    handle: JoinHandle<(Box<dyn Engine>, Option<Move>)>,
+}
+
+/// A computer player controlling one side of a local game.
+pub struct Computer {
+    side: Turn,
+    /// The engine. None, while the engine is searching on the worker thread.
+    engine: Option<Box<dyn Engine>>,
+    search: Option<Search>,
+}
+
+impl Computer {
+    pub fn new(side: Turn, engine: Box<dyn Engine>) -> Computer {
+        Computer {
+            side,
+            engine: Some(engine),
+            search: None,
+        }
+    }
+
+    /// Get the side played by the computer.
+    pub fn get_side(&self) -> Turn {
+        self.side
+    }
+
+    /// Check if it is the computer's turn.
+    fn may_move(&self, game: &GameState) -> bool {
+        game.get_turn() == self.side
+            && game.get_move_state() == MoveState::NoMove
+            && game.get_win_state() == WinState::Undecided
+            && !game.setup_is_active()
+    }
+
+    /// Do the computer move, if it is the computer's turn.
+    /// The move is searched on a worker thread. This function does not block.
+    /// It starts the search and does the move in a later call, once the search has finished.
+    /// The move is dropped, if the position has been changed during the search.
+    /// Returns true, if a move has been done.
+    /// Returns an error, if the search thread has crashed. The computer can't move anymore then.
+    pub fn poll(&mut self, game: &mut GameState) -> ah::Result<bool> {
+        if let Some(search) = self.search.take() {
+            if !search.handle.is_finished() {
+                self.search = Some(search);
+                return Ok(false);
+            }
+            let Ok((engine, mov)) = search.handle.join() else {
+                return Err(ah::format_err!(
+                    "The search thread of the computer player has crashed."
+                ));
+            };
+            let name = engine.name().to_string();
+            self.engine = Some(engine);
+            return Ok(match mov {
+                Some(mov) if self.may_move(game) && game.position_hash() == search.position => {
+                    match game.make_move(&mov) {
+                        Ok(_) => true,
+                        Err(e) => {
+                            Print::error(&format!("Computer ({}): Move failed: {}", name, e));
+                            false
+                        }
+                    }
+                }
+                Some(_) | None => false,
+            });
+        }
+
+        if !self.may_move(game) {
+            return Ok(false);
+        }
+        if let Some(mut engine) = self.engine.take() {
+            let position = game.position_hash();
+            let game = game.clone_search();
+            let handle = thread::spawn(move || {
+                let mov = engine.select_move(&game);
+                (engine, mov)
+            });
+            self.search = Some(Search { position, handle });
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::new_test_game;
+    use std::time::Instant;
+
+    fn config(engine: &str) -> ComputerConfig {
+        ComputerConfig {
+            engine: engine.to_string(),
+            depth: 2,
+            time_limit: Some(Duration::from_secs(5)),
+            iterations: 200,
+        }
+    }
+
+    /// Poll the computer until it has moved.
+    fn wait_for_move(computer: &mut Computer, game: &mut GameState) {
+        let start = Instant::now();
+        while !computer.poll(game).unwrap() {
+            assert!(start.elapsed() < Duration::from_secs(30));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_new_engine() {
+        assert_eq!(
+            config("alphabeta").new_engine().unwrap().name(),
+            "alphabeta"
+        );
+        assert_eq!(config(" MCTS ").new_engine().unwrap().name(), "mcts");
+        assert!(config("random").new_engine().is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let game = new_test_game("standard", None);
+        assert_eq!(evaluate(&game, Turn::Wolf), -evaluate(&game, Turn::Sheep));
+    }
+
+    #[test]
+    fn test_poll() {
+        for engine in ["alphabeta", "mcts"] {
+            let mut game = new_test_game("standard", None);
+            let mut sheep = config(engine).new_computer(Turn::Sheep).unwrap();
+            let mut wolf = config(engine).new_computer(Turn::Wolf).unwrap();
+            assert_eq!(sheep.get_side(), Turn::Sheep);
+
+            // It's not the turn of the wolves.
+            assert!(!wolf.poll(&mut game).unwrap());
+            assert!(wolf.search.is_none());
+
+            wait_for_move(&mut sheep, &mut game);
+            assert_eq!(game.get_turn(), Turn::Wolf);
+            wait_for_move(&mut wolf, &mut game);
+            assert_eq!(game.get_turn(), Turn::Sheep);
+            assert_eq!(game.get_recorder().get_moves().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_poll_changed_position() {
+        let mut game = new_test_game("standard", None);
+        let mut sheep = config("alphabeta").new_computer(Turn::Sheep).unwrap();
+        assert!(!sheep.poll(&mut game).unwrap());
+        assert!(sheep.search.is_some());
+
+        // The position changes during the search.
+        game.set_position_notation("1/3/1W1W1/5/SSSS1/SSSSS/SSSSS s")
+            .unwrap();
+
+        while sheep.search.is_some() {
+            assert!(!sheep.poll(&mut game).unwrap());
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(sheep.engine.is_some());
+        assert!(game.get_recorder().get_moves().is_empty());
+    }
+
+    struct PanicEngine;
+
+    impl Engine for PanicEngine {
+        fn name(&self) -> &str {
+            "panic"
+        }
+
+        fn select_move(&mut self, _game: &GameState) -> Option<Move> {
+            panic!("PanicEngine: select_move");
+        }
+    }
+
+    #[test]
+    fn test_poll_crash() {
+        let mut game = new_test_game("standard", None);
+        let mut sheep = Computer::new(Turn::Sheep, Box::new(PanicEngine));
+        assert!(!sheep.poll(&mut game).unwrap());
+        while sheep
+            .search
+            .as_ref()
+            .is_some_and(|s| !s.handle.is_finished())
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(sheep.poll(&mut game).is_err());
+        assert!(sheep.search.is_none());
+        assert!(sheep.engine.is_none());
+
+        // The crashed computer does not search anymore.
+        assert!(!sheep.poll(&mut game).unwrap());
+        assert!(sheep.search.is_none());
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
@@ -0,0 +1,198 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use super::{Engine, SCORE_WIN, evaluate};
+use crate::game_state::{GameState, Move, WinState};
+use crate::print::Print;
+use std::time::{Duration, Instant};
+
+/// Alpha-beta search engine with iterative deepening.
+pub struct AlphaBetaEngine {
+    max_depth: u32,
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+    nodes: u64,
+}
+
+impl AlphaBetaEngine {
+    /// Create a new engine.
+    /// The search stops at max_depth or after time_limit, whatever happens first.
+    pub fn new(max_depth: u32, time_limit: Option<Duration>) -> AlphaBetaEngine {
+        AlphaBetaEngine {
+            max_depth: max_depth.max(1),
+            time_limit,
+            deadline: None,
+            nodes: 0,
+        }
+    }
+
+    fn timed_out(&self) -> bool {
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+
+    /// Get the moves in search order. Captures are searched first.
+    fn ordered_moves(game: &GameState) -> Vec<Move> {
+        let mut moves = game.legal_moves();
+        moves.sort_by_key(|m| m.capture_pos.is_none());
+        moves
+    }
+
+    /// Negamax search.
+    /// Returns the score from the point of view of the side to move,
+    /// or None if the search timed out.
+    fn search(&mut self, game: &GameState, depth: u32, mut alpha: i32, beta: i32) -> Option<i32> {
+        self.nodes += 1;
+        if self.timed_out() {
+            return None;
+        }
+
+        let turn = game.get_turn();
+        if depth == 0 || game.get_win_state() != WinState::Undecided {
+            // Prefer quick wins and slow losses.
+            let score = evaluate(game, turn);
+            return Some(if score >= SCORE_WIN {
+                score + depth as i32
+            } else if score <= -SCORE_WIN {
+                score - depth as i32
+            } else {
+                score
+            });
+        }
+
+        let moves = Self::ordered_moves(game);
+        if moves.is_empty() {
+            return Some(evaluate(game, turn));
+        }
+
+        let mut best = i32::MIN + 1;
+        for mov in &moves {
+            let mut child = game.clone_search();
+            if child.make_move(mov).is_err() {
+                continue;
+            }
+            // A wolf capture chain or a blocked opponent keeps the turn.
+            let score = if child.get_turn() == turn {
+                self.search(&child, depth - 1, alpha, beta)?
+            } else {
+                -self.search(&child, depth - 1, -beta, -alpha)?
+            };
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Some(best)
+    }
+
+    /// Search all root moves to the given depth.
+    fn search_root(&mut self, game: &GameState, moves: &[Move], depth: u32) -> Option<(Move, i32)> {
+        let turn = game.get_turn();
+        let mut alpha = i32::MIN + 1;
+        let beta = i32::MAX;
+        let mut best = None;
+        for mov in moves {
+            let mut child = game.clone_search();
+            if child.make_move(mov).is_err() {
+                continue;
+            }
+            let score = if child.get_turn() == turn {
+                self.search(&child, depth - 1, alpha, beta)?
+            } else {
+                -self.search(&child, depth - 1, -beta, -alpha)?
+            };
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((*mov, score));
+            }
+        }
+        best
+    }
+}
+
+impl Engine for AlphaBetaEngine {
+    fn name(&self) -> &str {
+        "alphabeta"
+    }
+
+    fn select_move(&mut self, game: &GameState) -> Option<Move> {
+        let game = game.clone_search();
+        let mut moves = Self::ordered_moves(&game);
+        if moves.len() <= 1 {
+            return moves.pop();
+        }
+
+        self.deadline = self.time_limit.map(|t| Instant::now() + t);
+        self.nodes = 0;
+
+        let mut best = None;
+        for depth in 1..=self.max_depth {
+            match self.search_root(&game, &moves, depth) {
+                Some((mov, score)) => {
+                    Print::debug(&format!(
+                        "alphabeta: depth={} score={} nodes={} best={:?}",
+                        depth, score, self.nodes, mov
+                    ));
+                    // Search the best move first in the next iteration.
+                    if let Some(i) = moves.iter().position(|m| *m == mov) {
+                        let m = moves.remove(i);
+                        moves.insert(0, m);
+                    }
+                    best = Some(mov);
+                    if score.abs() >= SCORE_WIN {
+                        break;
+                    }
+                }
+                None => break, // Timeout.
+            }
+        }
+        self.deadline = None;
+
+        best.or_else(|| moves.first().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::coord::Coord;
+    use crate::game_state::new_test_game;
+
+    #[test]
+    fn test_wolf_win() {
+        // Capturing the sheep on b4 and d4 wins the game.
+        let game = new_test_game("standard", Some("W/3/5/WS1S1/SSS2/5/SSSSS w"));
+        let mut engine = AlphaBetaEngine::new(3, None);
+        let mov = engine.select_move(&game).unwrap();
+        assert_eq!(mov.from_pos, coord!(0, 3));
+        assert_eq!(mov.capture_pos, Some(coord!(1, 3)));
+    }
+
+    #[test]
+    fn test_sheep_win() {
+        // The sheep on a4 fills the barn.
+        let game = new_test_game("standard", Some("S/SSS/1SSSS/S4/5/5/W3W s"));
+        let mut engine = AlphaBetaEngine::new(2, Some(Duration::from_secs(10)));
+        let mov = engine.select_move(&game).unwrap();
+        assert_eq!(mov.from_pos, coord!(0, 3));
+        assert_eq!(mov.to_pos, coord!(0, 2));
+    }
+
+    #[test]
+    fn test_no_move() {
+        // The game has been won by the wolves.
+        let game = new_test_game("standard", Some("W/3/5/W4/5/5/SSSSS s"));
+        let mut engine = AlphaBetaEngine::new(3, None);
+        assert!(engine.select_move(&game).is_none());
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
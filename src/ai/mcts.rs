@@ -0,0 +1,253 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use super::{Engine, SCORE_WIN, evaluate};
+use crate::game_state::{GameState, Move, Turn, WinState};
+use crate::print::Print;
+use rand::{RngExt, rng};
+use std::time::{Duration, Instant};
+
+/// Exploration constant of the UCT formula.
+const UCT_C: f64 = 1.4;
+/// Maximum number of random moves in one playout.
+const PLAYOUT_MAX_MOVES: usize = 60;
+/// Evaluation score that maps to an almost certain playout win.
+const PLAYOUT_SCORE_SCALE: f64 = 300.0;
+
+struct Node {
+    /// The move leading to this node.
+    mov: Option<Move>,
+    /// The side that did the move leading to this node.
+    mover: Turn,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    /// Accumulated playout results from the point of view of the mover.
+    reward: f64,
+}
+
+/// Monte Carlo tree search engine.
+pub struct MctsEngine {
+    iterations: u32,
+    time_limit: Option<Duration>,
+    nodes: Vec<Node>,
+}
+
+impl MctsEngine {
+    /// Create a new engine.
+    /// The search stops after the given number of iterations or after time_limit,
+    /// whatever happens first.
+    pub fn new(iterations: u32, time_limit: Option<Duration>) -> MctsEngine {
+        MctsEngine {
+            iterations: iterations.max(1),
+            time_limit,
+            nodes: vec![],
+        }
+    }
+
+    fn new_node(
+        &mut self,
+        mov: Option<Move>,
+        mover: Turn,
+        parent: Option<usize>,
+        game: &GameState,
+    ) {
+        self.nodes.push(Node {
+            mov,
+            mover,
+            parent,
+            children: vec![],
+            untried: game.legal_moves(),
+            visits: 0,
+            reward: 0.0,
+        });
+    }
+
+    /// Select the child with the best UCT value.
+    fn select_child(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        let ln_visits = (node.visits.max(1) as f64).ln();
+        let uct = |child: &Node| {
+            let visits = child.visits.max(1) as f64;
+            (child.reward / visits) + UCT_C * (ln_visits / visits).sqrt()
+        };
+        *node
+            .children
+            .iter()
+            .max_by(|a, b| uct(&self.nodes[**a]).total_cmp(&uct(&self.nodes[**b])))
+            .expect("MCTS: Node without children")
+    }
+
+    /// Play random moves until the game is decided or the playout limit is reached.
+    /// Returns the result from the point of view of the wolves in the range 0.0 to 1.0.
+    fn playout(game: &mut GameState) -> f64 {
+        let mut rng = rng();
+        for _ in 0..PLAYOUT_MAX_MOVES {
+            if game.get_win_state() != WinState::Undecided {
+                break;
+            }
+            let moves = game.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            // Wolves never miss a capture during playouts.
+            let captures: Vec<&Move> = moves.iter().filter(|m| m.capture_pos.is_some()).collect();
+            let mov = if captures.is_empty() {
+                moves[rng.random_range(0..moves.len())]
+            } else {
+                *captures[rng.random_range(0..captures.len())]
+            };
+            if game.make_move(&mov).is_err() {
+                break;
+            }
+        }
+        let score = evaluate(game, Turn::Wolf);
+        if score >= SCORE_WIN {
+            1.0
+        } else if score <= -SCORE_WIN {
+            0.0
+        } else {
+            1.0 / (1.0 + (-(score as f64) / PLAYOUT_SCORE_SCALE).exp())
+        }
+    }
+
+    /// Run one search iteration: selection, expansion, playout and backpropagation.
+    fn iterate(&mut self, root_game: &GameState) {
+        let mut game = root_game.clone_search();
+        let mut index = 0;
+
+        // Selection.
+        while self.nodes[index].untried.is_empty() && !self.nodes[index].children.is_empty() {
+            index = self.select_child(index);
+            let mov = self.nodes[index].mov.expect("MCTS: Child without move");
+            if game.make_move(&mov).is_err() {
+                return;
+            }
+        }
+
+        // Expansion.
+        if !self.nodes[index].untried.is_empty() {
+            let untried = &mut self.nodes[index].untried;
+            let mov = untried.swap_remove(rng().random_range(0..untried.len()));
+            let mover = game.get_turn();
+            if game.make_move(&mov).is_err() {
+                return;
+            }
+            self.new_node(Some(mov), mover, Some(index), &game);
+            let child = self.nodes.len() - 1;
+            self.nodes[index].children.push(child);
+            index = child;
+        }
+
+        // Playout.
+        let wolf_result = Self::playout(&mut game);
+
+        // Backpropagation.
+        let mut node = Some(index);
+        while let Some(i) = node {
+            let n = &mut self.nodes[i];
+            n.visits += 1;
+            n.reward += match n.mover {
+                Turn::Wolf => wolf_result,
+                Turn::Sheep => 1.0 - wolf_result,
+            };
+            node = n.parent;
+        }
+    }
+}
+
+impl Engine for MctsEngine {
+    fn name(&self) -> &str {
+        "mcts"
+    }
+
+    fn select_move(&mut self, game: &GameState) -> Option<Move> {
+        let game = game.clone_search();
+        let mut moves = game.legal_moves();
+        if moves.len() <= 1 {
+            return moves.pop();
+        }
+
+        self.nodes.clear();
+        // The root's mover is the opponent of the side to move.
+        let root_mover = match game.get_turn() {
+            Turn::Wolf => Turn::Sheep,
+            Turn::Sheep => Turn::Wolf,
+        };
+        self.new_node(None, root_mover, None, &game);
+
+        let deadline = self.time_limit.map(|t| Instant::now() + t);
+        let mut iterations = 0;
+        while iterations < self.iterations {
+            if let Some(deadline) = deadline
+                && Instant::now() >= deadline
+            {
+                break;
+            }
+            self.iterate(&game);
+            iterations += 1;
+        }
+
+        // Select the most visited move.
+        let best = self.nodes[0]
+            .children
+            .iter()
+            .map(|i| &self.nodes[*i])
+            .max_by_key(|n| n.visits)?;
+        Print::debug(&format!(
+            "mcts: iterations={} visits={} reward={:.3} best={:?}",
+            iterations,
+            best.visits,
+            best.reward / best.visits.max(1) as f64,
+            best.mov
+        ));
+        let best = best.mov;
+        self.nodes.clear();
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::coord::Coord;
+    use crate::game_state::new_test_game;
+
+    #[test]
+    fn test_sheep_win() {
+        // The sheep on a4 fills the barn.
+        let game = new_test_game("standard", Some("S/SSS/1SSSS/S4/5/5/W3W s"));
+        let mut engine = MctsEngine::new(2000, None);
+        let mov = engine.select_move(&game).unwrap();
+        assert_eq!(mov.from_pos, coord!(0, 3));
+        assert_eq!(mov.to_pos, coord!(0, 2));
+    }
+
+    #[test]
+    fn test_single_move() {
+        // The wolf on c7 is enclosed and the wolf on a1 can only move to a2.
+        let game = new_test_game("standard", Some("W/SSS/1SSS1/5/2S2/1S3/WSS2 w"));
+        assert_eq!(game.legal_moves().len(), 1);
+        let mut engine = MctsEngine::new(10, None);
+        let mov = engine.select_move(&game).unwrap();
+        assert_eq!(mov.from_pos, coord!(0, 6));
+        assert_eq!(mov.to_pos, coord!(0, 5));
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let game = new_test_game("standard", Some("1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s"));
+        let mut engine = MctsEngine::new(u32::MAX, Some(Duration::from_millis(100)));
+        let start = Instant::now();
+        assert!(engine.select_move(&game).is_some());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
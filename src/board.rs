@@ -7,12 +7,16 @@
 
 use crate::coord;
 use crate::coord::{Coord, CoordAxis};
+use lazy_static::lazy_static;
 
-pub const BOARD_WIDTH: CoordAxis = 5;
+/// The maximum width of all boards.
+pub const BOARD_WIDTH: CoordAxis = 7;
+/// The maximum height of all boards.
 pub const BOARD_HEIGHT: CoordAxis = 7;
 
+/// The lines of the Wolfsmühle board.
 #[rustfmt::skip]
-pub const BOARD_LINES: [(Coord, Coord); 15] = [
+const MUEHLE_LINES: [(Coord, Coord); 15] = [
     // Vertical lines
     (coord!(0, 2), coord!(0, 6)),
     (coord!(1, 1), coord!(1, 6)),
@@ -34,6 +38,56 @@ pub const BOARD_LINES: [(Coord, Coord); 15] = [
     (coord!(2, 0), coord!(3, 1)),
 ];
 
+/// The lines of the 33-point cross board.
+#[rustfmt::skip]
+const CROSS_LINES: [(Coord, Coord); 14] = [
+    // Vertical lines
+    (coord!(0, 2), coord!(0, 4)),
+    (coord!(1, 2), coord!(1, 4)),
+    (coord!(2, 0), coord!(2, 6)),
+    (coord!(3, 0), coord!(3, 6)),
+    (coord!(4, 0), coord!(4, 6)),
+    (coord!(5, 2), coord!(5, 4)),
+    (coord!(6, 2), coord!(6, 4)),
+    // Horizontal lines
+    (coord!(2, 0), coord!(4, 0)),
+    (coord!(2, 1), coord!(4, 1)),
+    (coord!(0, 2), coord!(6, 2)),
+    (coord!(0, 3), coord!(6, 3)),
+    (coord!(0, 4), coord!(6, 4)),
+    (coord!(2, 5), coord!(4, 5)),
+    (coord!(2, 6), coord!(4, 6)),
+];
+
+/// The lines of the 33-point cross board with diagonals and a fortress.
+#[rustfmt::skip]
+const FORTRESS_LINES: [(Coord, Coord); 20] = [
+    // Vertical lines
+    (coord!(0, 2), coord!(0, 4)),
+    (coord!(1, 2), coord!(1, 4)),
+    (coord!(2, 0), coord!(2, 6)),
+    (coord!(3, 0), coord!(3, 6)),
+    (coord!(4, 0), coord!(4, 6)),
+    (coord!(5, 2), coord!(5, 4)),
+    (coord!(6, 2), coord!(6, 4)),
+    // Horizontal lines
+    (coord!(2, 0), coord!(4, 0)),
+    (coord!(2, 1), coord!(4, 1)),
+    (coord!(0, 2), coord!(6, 2)),
+    (coord!(0, 3), coord!(6, 3)),
+    (coord!(0, 4), coord!(6, 4)),
+    (coord!(2, 5), coord!(4, 5)),
+    (coord!(2, 6), coord!(4, 6)),
+    // Diagonal lines (arms)
+    (coord!(2, 0), coord!(6, 4)),
+    (coord!(4, 0), coord!(0, 4)),
+    (coord!(0, 2), coord!(4, 6)),
+    (coord!(6, 2), coord!(2, 6)),
+    // Diagonal lines (center)
+    (coord!(2, 2), coord!(4, 4)),
+    (coord!(4, 2), coord!(2, 4)),
+];
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum PosType {
     Invalid,
@@ -57,53 +111,339 @@ macro_rules! field {
     };
 }
 
+type PosArray = [[PosType; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+
+#[rustfmt::skip]
+const MUEHLE_POSITIONS: PosArray = [
+    [ invalid!(), invalid!(), barn!(),  invalid!(), invalid!(), invalid!(), invalid!(), ],
+    [ invalid!(), barn!(),    barn!(),  barn!(),    invalid!(), invalid!(), invalid!(), ],
+    [ barn!(),    barn!(),    barn!(),  barn!(),    barn!(),    invalid!(), invalid!(), ],
+    [ field!(),   field!(),   field!(), field!(),   field!(),   invalid!(), invalid!(), ],
+    [ field!(),   field!(),   field!(), field!(),   field!(),   invalid!(), invalid!(), ],
+    [ field!(),   field!(),   field!(), field!(),   field!(),   invalid!(), invalid!(), ],
+    [ field!(),   field!(),   field!(), field!(),   field!(),   invalid!(), invalid!(), ],
+];
+
+#[rustfmt::skip]
+const CROSS_POSITIONS: PosArray = [
+    [ invalid!(), invalid!(), field!(), field!(), field!(), invalid!(), invalid!(), ],
+    [ invalid!(), invalid!(), field!(), field!(), field!(), invalid!(), invalid!(), ],
+    [ field!(),   field!(),   field!(), field!(), field!(), field!(),   field!(),   ],
+    [ field!(),   field!(),   field!(), field!(), field!(), field!(),   field!(),   ],
+    [ field!(),   field!(),   field!(), field!(), field!(), field!(),   field!(),   ],
+    [ invalid!(), invalid!(), field!(), field!(), field!(), invalid!(), invalid!(), ],
+    [ invalid!(), invalid!(), field!(), field!(), field!(), invalid!(), invalid!(), ],
+];
+
 #[rustfmt::skip]
-pub const BOARD_POSITIONS: [[PosType; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize] = [
-    [ invalid!(), invalid!(), barn!(),  invalid!(), invalid!(), ],
-    [ invalid!(), barn!(),    barn!(),  barn!(),    invalid!(), ],
-    [ barn!(),    barn!(),    barn!(),  barn!(),    barn!(),    ],
-    [ field!(),   field!(),   field!(), field!(),   field!(),   ],
-    [ field!(),   field!(),   field!(), field!(),   field!(),   ],
-    [ field!(),   field!(),   field!(), field!(),   field!(),   ],
-    [ field!(),   field!(),   field!(), field!(),   field!(),   ],
+const FORTRESS_POSITIONS: PosArray = [
+    [ invalid!(), invalid!(), barn!(),  barn!(),  barn!(),  invalid!(), invalid!(), ],
+    [ invalid!(), invalid!(), barn!(),  barn!(),  barn!(),  invalid!(), invalid!(), ],
+    [ field!(),   field!(),   barn!(),  barn!(),  barn!(),  field!(),   field!(),   ],
+    [ field!(),   field!(),   field!(), field!(), field!(), field!(),   field!(),   ],
+    [ field!(),   field!(),   field!(), field!(), field!(), field!(),   field!(),   ],
+    [ invalid!(), invalid!(), field!(), field!(), field!(), invalid!(), invalid!(), ],
+    [ invalid!(), invalid!(), field!(), field!(), field!(), invalid!(), invalid!(), ],
 ];
 
-/// Check if a position is on the board.
-pub fn coord_is_on_board(pos: Coord) -> bool {
-    pos.x >= 0
-        && pos.x < BOARD_WIDTH
-        && pos.y >= 0
-        && pos.y < BOARD_HEIGHT
-        && BOARD_POSITIONS[pos.y as usize][pos.x as usize] != PosType::Invalid
-}
-
-/// Check if a position is on the main diagonal lines.
-pub fn is_on_main_diag(pos: Coord) -> bool {
-    let mut y = 2;
-    for x in 0..5 {
-        if pos == coord!(x, y) {
-            return true;
+/// One connection between two neighboring board positions.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BoardEdge {
+    /// The neighbor position.
+    pub to: Coord,
+    /// The one-step direction from the position to the neighbor.
+    pub dir: Coord,
+    /// The index of the board line this edge belongs to.
+    line: usize,
+    /// The board line starts or ends at the position.
+    line_ends_at_from: bool,
+    /// The board line starts or ends at the neighbor.
+    line_ends_at_to: bool,
+}
+
+/// A possible capture jump.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BoardJump {
+    /// The position of the captured token.
+    pub over: Coord,
+    /// The target position of the jump.
+    pub to: Coord,
+}
+
+/// The board topology as a graph of neighboring positions.
+/// The graph is derived from the board lines.
+pub struct BoardGraph {
+    width: CoordAxis,
+    height: CoordAxis,
+    edges: Vec<Vec<BoardEdge>>,
+    jumps: Vec<Vec<BoardJump>>,
+}
+
+impl BoardGraph {
+    /// Build the graph from a list of straight board lines.
+    pub fn new(width: CoordAxis, height: CoordAxis, lines: &[(Coord, Coord)]) -> BoardGraph {
+        let size = width as usize * height as usize;
+        let mut graph = BoardGraph {
+            width,
+            height,
+            edges: vec![vec![]; size],
+            jumps: vec![vec![]; size],
+        };
+
+        // Split the lines into edges between neighboring positions.
+        for (line, (start, end)) in lines.iter().enumerate() {
+            let dir = coord!((end.x - start.x).signum(), (end.y - start.y).signum());
+            let mut pos = *start;
+            while pos != *end {
+                let next = pos + dir;
+                graph.add_edge(pos, next, dir, line, pos == *start, next == *end);
+                graph.add_edge(
+                    next,
+                    pos,
+                    coord!(-dir.x, -dir.y),
+                    line,
+                    next == *end,
+                    pos == *start,
+                );
+                pos = next;
+            }
+        }
+
+        // A wolf can jump along two consecutive edges, if both edges are on the same line.
+        // It can also jump across a 135 degree corner, if both lines end at the corner.
+        for index in 0..size {
+            let mut jumps = vec![];
+            for first in &graph.edges[index] {
+                for second in &graph.edges[graph.index(first.to)] {
+                    let straight = second.line == first.line && second.dir == first.dir;
+                    let dot = first.dir.x * second.dir.x + first.dir.y * second.dir.y;
+                    let bend = second.line != first.line
+                        && first.line_ends_at_to
+                        && second.line_ends_at_from
+                        && dot > 0;
+                    if straight || bend {
+                        jumps.push(BoardJump {
+                            over: first.to,
+                            to: second.to,
+                        });
+                    }
+                }
+            }
+            graph.jumps[index] = jumps;
         }
-        y += 1;
+
+        graph
+    }
+
+    fn index(&self, pos: Coord) -> usize {
+        pos.y as usize * self.width as usize + pos.x as usize
     }
-    let mut y = 6;
-    for x in 0..5 {
-        if pos == coord!(x, y) {
-            return true;
+
+    fn contains(&self, pos: Coord) -> bool {
+        pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height
+    }
+
+    fn add_edge(
+        &mut self,
+        from: Coord,
+        to: Coord,
+        dir: Coord,
+        line: usize,
+        line_ends_at_from: bool,
+        line_ends_at_to: bool,
+    ) {
+        let index = self.index(from);
+        if !self.edges[index].iter().any(|e| e.to == to) {
+            self.edges[index].push(BoardEdge {
+                to,
+                dir,
+                line,
+                line_ends_at_from,
+                line_ends_at_to,
+            });
         }
-        y -= 1;
     }
-    false
+
+    /// Get all edges from a position to its neighbors.
+    pub fn edges(&self, pos: Coord) -> &[BoardEdge] {
+        if self.contains(pos) {
+            &self.edges[self.index(pos)]
+        } else {
+            &[]
+        }
+    }
+
+    /// Get the edge between two neighboring positions.
+    pub fn edge(&self, from: Coord, to: Coord) -> Option<&BoardEdge> {
+        self.edges(from).iter().find(|e| e.to == to)
+    }
+
+    /// Get all capture jumps starting at a position.
+    pub fn jumps(&self, pos: Coord) -> &[BoardJump] {
+        if self.contains(pos) {
+            &self.jumps[self.index(pos)]
+        } else {
+            &[]
+        }
+    }
+
+    /// Get the capture jump between two positions.
+    pub fn jump(&self, from: Coord, to: Coord) -> Option<&BoardJump> {
+        self.jumps(from).iter().find(|j| j.to == to)
+    }
+}
+
+/// The available board layouts.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BoardType {
+    /// The Wolfsmühle board with the barn at the top.
+    Muehle,
+    /// The 33-point cross board without diagonals (Fox and Geese).
+    Cross,
+    /// The 33-point cross board with diagonals and a fortress at the top (Asalto).
+    Fortress,
+}
+
+impl BoardType {
+    /// Get the board definition of this layout.
+    pub fn board(&self) -> &'static Board {
+        match self {
+            BoardType::Muehle => &MUEHLE_BOARD,
+            BoardType::Cross => &CROSS_BOARD,
+            BoardType::Fortress => &FORTRESS_BOARD,
+        }
+    }
+}
+
+/// The definition of one board layout.
+pub struct Board {
+    board_type: BoardType,
+    width: CoordAxis,
+    height: CoordAxis,
+    positions: PosArray,
+    lines: &'static [(Coord, Coord)],
+    wolf_start: [&'static [Coord]; 2],
+    graph: BoardGraph,
+}
+
+impl Board {
+    fn new(
+        board_type: BoardType,
+        width: CoordAxis,
+        height: CoordAxis,
+        positions: PosArray,
+        lines: &'static [(Coord, Coord)],
+        wolf_start: [&'static [Coord]; 2],
+    ) -> Board {
+        Board {
+            board_type,
+            width,
+            height,
+            positions,
+            lines,
+            wolf_start,
+            graph: BoardGraph::new(width, height, lines),
+        }
+    }
+
+    pub fn board_type(&self) -> BoardType {
+        self.board_type
+    }
+
+    /// Get the number of columns.
+    pub fn width(&self) -> CoordAxis {
+        self.width
+    }
+
+    /// Get the number of rows.
+    pub fn height(&self) -> CoordAxis {
+        self.height
+    }
+
+    /// Get the lines drawn on the board.
+    pub fn lines(&self) -> &'static [(Coord, Coord)] {
+        self.lines
+    }
+
+    /// Get the start positions of the wolves.
+    pub fn wolf_start(&self, wolves: u8) -> &'static [Coord] {
+        self.wolf_start[if wolves > 1 { 1 } else { 0 }]
+    }
+
+    /// Get the graph of neighboring positions.
+    pub fn graph(&self) -> &BoardGraph {
+        &self.graph
+    }
+
+    /// Get the type of a position.
+    pub fn pos_type(&self, pos: Coord) -> PosType {
+        if pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height {
+            self.positions[pos.y as usize][pos.x as usize]
+        } else {
+            PosType::Invalid
+        }
+    }
+
+    /// Check if a position is on the board.
+    pub fn contains(&self, pos: Coord) -> bool {
+        self.pos_type(pos) != PosType::Invalid
+    }
+
+    /// Check if the board has a barn.
+    pub fn has_barn(&self) -> bool {
+        self.positions
+            .iter()
+            .flatten()
+            .any(|pos_type| *pos_type == PosType::Barn)
+    }
+
+    /// Iterate over all positions on the board.
+    pub fn iter(&'static self) -> BoardIterator {
+        BoardIterator::new(self)
+    }
+
+    /// Iterate over all positions on the board and their types.
+    pub fn iter_pos(&'static self) -> BoardPosIterator {
+        BoardPosIterator::new(self)
+    }
+}
+
+lazy_static! {
+    static ref MUEHLE_BOARD: Board = Board::new(
+        BoardType::Muehle,
+        5,
+        7,
+        MUEHLE_POSITIONS,
+        &MUEHLE_LINES,
+        [&[coord!(2, 2)], &[coord!(1, 2), coord!(3, 2)]],
+    );
+    static ref CROSS_BOARD: Board = Board::new(
+        BoardType::Cross,
+        7,
+        7,
+        CROSS_POSITIONS,
+        &CROSS_LINES,
+        [&[coord!(3, 3)], &[coord!(2, 2), coord!(4, 2)]],
+    );
+    static ref FORTRESS_BOARD: Board = Board::new(
+        BoardType::Fortress,
+        7,
+        7,
+        FORTRESS_POSITIONS,
+        &FORTRESS_LINES,
+        [&[coord!(3, 2)], &[coord!(2, 2), coord!(4, 2)]],
+    );
 }
 
 pub struct BoardIterator {
+    board: &'static Board,
     x: CoordAxis,
     y: CoordAxis,
 }
 
 impl BoardIterator {
-    pub fn new() -> BoardIterator {
-        BoardIterator { x: 0, y: 0 }
+    pub fn new(board: &'static Board) -> BoardIterator {
+        BoardIterator { board, x: 0, y: 0 }
     }
 }
 
@@ -113,9 +453,9 @@ impl Iterator for BoardIterator {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             let mut coord = Some(coord!(self.x, self.y));
-            if self.x >= BOARD_WIDTH - 1 {
+            if self.x >= self.board.width - 1 {
                 self.x = 0;
-                if self.y >= BOARD_HEIGHT {
+                if self.y >= self.board.height {
                     self.y = 0;
                     coord = None;
                 } else {
@@ -128,7 +468,7 @@ impl Iterator for BoardIterator {
             match coord {
                 None => break None,
                 Some(coord) => {
-                    if coord_is_on_board(coord) {
+                    if self.board.contains(coord) {
                         break Some(coord);
                     }
                 }
@@ -142,9 +482,9 @@ pub struct BoardPosIterator {
 }
 
 impl BoardPosIterator {
-    pub fn new() -> BoardPosIterator {
+    pub fn new(board: &'static Board) -> BoardPosIterator {
         BoardPosIterator {
-            iter: BoardIterator::new(),
+            iter: BoardIterator::new(board),
         }
     }
 }
@@ -153,9 +493,123 @@ impl Iterator for BoardPosIterator {
     type Item = (Coord, PosType);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter
-            .next()
-            .map(|coord| (coord, BOARD_POSITIONS[coord.y as usize][coord.x as usize]))
+        let board = self.iter.board;
+        self.iter.next().map(|coord| (coord, board.pos_type(coord)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD_TYPES: [BoardType; 3] = [BoardType::Muehle, BoardType::Cross, BoardType::Fortress];
+
+    fn jump_over(board_type: BoardType, from: Coord, to: Coord) -> Option<Coord> {
+        board_type
+            .board()
+            .graph()
+            .jump(from, to)
+            .map(|jump| jump.over)
+    }
+
+    #[test]
+    fn test_iter() {
+        assert_eq!(BoardType::Muehle.board().iter().count(), 29);
+        assert_eq!(BoardType::Cross.board().iter().count(), 33);
+        assert_eq!(BoardType::Fortress.board().iter().count(), 33);
+    }
+
+    #[test]
+    fn test_graph_symmetric() {
+        for board_type in BOARD_TYPES {
+            let board = board_type.board();
+            let graph = board.graph();
+            for pos in board.iter() {
+                assert!(!graph.edges(pos).is_empty(), "{:?} {}", board_type, pos);
+                for edge in graph.edges(pos) {
+                    assert!(board.contains(edge.to), "{:?} {}", board_type, edge.to);
+                    assert!(graph.edge(edge.to, pos).is_some());
+                }
+                for jump in graph.jumps(pos) {
+                    assert!(board.contains(jump.over), "{:?} {}", board_type, jump.over);
+                    assert!(board.contains(jump.to), "{:?} {}", board_type, jump.to);
+                    assert_eq!(jump_over(board_type, jump.to, pos), Some(jump.over));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_muehle_jumps() {
+        let board_type = BoardType::Muehle;
+        assert_eq!(
+            jump_over(board_type, coord!(0, 3), coord!(2, 3)),
+            Some(coord!(1, 3))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(0, 2), coord!(2, 4)),
+            Some(coord!(1, 3))
+        );
+        // Around the corner of the barn.
+        assert_eq!(
+            jump_over(board_type, coord!(2, 0), coord!(1, 2)),
+            Some(coord!(1, 1))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(1, 1), coord!(3, 1)),
+            Some(coord!(2, 1))
+        );
+        // There are no positions right of the 5 columns.
+        assert_eq!(jump_over(board_type, coord!(3, 3), coord!(5, 3)), None);
+        assert!(board_type.board().graph().jumps(coord!(5, 3)).is_empty());
+    }
+
+    #[test]
+    fn test_cross_jumps() {
+        let board_type = BoardType::Cross;
+        // The right arm of the 7 columns wide board.
+        assert_eq!(
+            jump_over(board_type, coord!(6, 3), coord!(4, 3)),
+            Some(coord!(5, 3))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(4, 2), coord!(6, 2)),
+            Some(coord!(5, 2))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(6, 4), coord!(6, 2)),
+            Some(coord!(6, 3))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(4, 3), coord!(4, 5)),
+            Some(coord!(4, 4))
+        );
+        assert_eq!(board_type.board().graph().jumps(coord!(6, 3)).len(), 1);
+        // No jumps off the board or into the corners.
+        assert_eq!(jump_over(board_type, coord!(5, 3), coord!(7, 3)), None);
+        assert_eq!(jump_over(board_type, coord!(6, 2), coord!(6, 0)), None);
+        assert!(board_type.board().graph().jumps(coord!(7, 3)).is_empty());
+        // The cross board has no diagonals.
+        assert_eq!(jump_over(board_type, coord!(2, 2), coord!(4, 4)), None);
+        assert_eq!(jump_over(board_type, coord!(6, 2), coord!(4, 4)), None);
+    }
+
+    #[test]
+    fn test_fortress_jumps() {
+        let board_type = BoardType::Fortress;
+        assert_eq!(
+            jump_over(board_type, coord!(2, 2), coord!(4, 4)),
+            Some(coord!(3, 3))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(6, 2), coord!(4, 4)),
+            Some(coord!(5, 3))
+        );
+        assert_eq!(
+            jump_over(board_type, coord!(6, 3), coord!(4, 3)),
+            Some(coord!(5, 3))
+        );
+        assert_eq!(jump_over(board_type, coord!(5, 3), coord!(7, 5)), None);
     }
 }
 
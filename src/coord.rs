@@ -10,7 +10,7 @@ use std::ops::{Add, Sub};
 
 pub type CoordAxis = i16;
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Coord {
     pub x: CoordAxis,
     pub y: CoordAxis,
@@ -5,23 +5,33 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
+mod draw;
+mod notation;
 mod recorder;
+mod replay;
+mod rules;
 mod serialize;
+mod setup;
 
-use crate::board::{
-    BOARD_HEIGHT, BOARD_WIDTH, BoardIterator, BoardPosIterator, PosType, coord_is_on_board,
-    is_on_main_diag,
-};
+use crate::board::{BOARD_HEIGHT, BOARD_WIDTH, Board, PosType};
 use crate::coord;
-use crate::coord::{Coord, CoordAxis};
+use crate::coord::Coord;
+use crate::game_state::draw::DrawState;
 use crate::game_state::recorder::{RecordedMove, Recorder};
+pub use crate::game_state::rules::{BlockedRule, CaptureRule, RuleSet};
 use crate::net::{
     client::Client,
     consts::{MAX_PLAYERS, MAX_ROOMS},
     protocol::{
-        MSG_MOVE_ACTION_ABORT, MSG_MOVE_ACTION_MOVE, MSG_MOVE_ACTION_PICK, MSG_MOVE_ACTION_PUT,
-        MSG_MOVE_TOKEN_CURRENT, MSG_MOVE_TOKEN_SHEEP, MSG_MOVE_TOKEN_WOLF, Message, MsgGameState,
-        MsgMove, MsgPlayerList, MsgRoomList, MsgSay, MsgType,
+        MSG_DRAWOFFER_ACTION_ACCEPT, MSG_DRAWOFFER_ACTION_DECLINE, MSG_DRAWOFFER_ACTION_OFFER,
+        MSG_MOVE_ACTION_ABORT, MSG_MOVE_ACTION_END_CHAIN, MSG_MOVE_ACTION_MOVE,
+        MSG_MOVE_ACTION_PICK, MSG_MOVE_ACTION_PUT, MSG_MOVE_TOKEN_CURRENT, MSG_MOVE_TOKEN_SHEEP,
+        MSG_MOVE_TOKEN_WOLF, MSG_ROOM_FLAG_PASSWORD, MSG_TAKEBACK_ACTION_ACCEPT,
+        MSG_TAKEBACK_ACTION_DECLINE, MSG_TAKEBACK_ACTION_REQUEST, MSG_VOTE_ACTION_ACCEPT,
+        MSG_VOTE_ACTION_DECLINE, MSG_VOTE_ACTION_FAILED, MSG_VOTE_ACTION_PASSED,
+        MSG_VOTE_ACTION_REQUEST, MSG_VOTE_SUBJECT_LOAD, MSG_VOTE_SUBJECT_RESET, Message,
+        MsgDrawOffer, MsgGameState, MsgMove, MsgPlayerList, MsgResign, MsgRoomList, MsgSay,
+        MsgTakeback, MsgType, MsgVote,
     },
 };
 use crate::player::{Player, PlayerList, PlayerMode, num_to_player_mode};
@@ -30,48 +40,13 @@ use crate::random::random_alphanum;
 use anyhow as ah;
 use std::collections::VecDeque;
 use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
 
 const SAY_DEQUE_MAX_LEN: usize = 0x1000;
+/// The maximum number of move steps that can be taken back.
+const UNDO_STACK_MAX_LEN: usize = 1000;
 
-/// All possible relative move offsets that could lead to a capture.
-#[rustfmt::skip]
-const CAPTURE_OFFSETS: [Coord; 12] = [
-    // horizontal
-    coord!(-2, 0),
-    coord!(2, 0),
-    // vertical
-    coord!(0, -2),
-    coord!(0, 2),
-    // diagonal
-    coord!(-2, -2),
-    coord!(-2, 2),
-    coord!(2, -2),
-    coord!(2, 2),
-    // left barn corner
-    coord!(1, -2),
-    coord!(-1, 2),
-    // right barn corner
-    coord!(-1, -2),
-    coord!(1, 2),
-];
-
-/// All possible relative non-capture move offsets.
-#[rustfmt::skip]
-const MOVE_OFFSETS: [Coord; 8] = [
-    // horizontal
-    coord!(1, 0),
-    coord!(-1, 0),
-    // vertical
-    coord!(0, 1),
-    coord!(0, -1),
-    // diagonal
-    coord!(-1, -1),
-    coord!(-1, 1),
-    coord!(1, -1),
-    coord!(1, 1),
-];
-
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum FieldState {
     Unused,
     Empty,
@@ -103,6 +78,26 @@ pub enum WinState {
     Undecided,
     Wolf,
     Sheep,
+    Draw,
+}
+
+const fn win_state_to_num(win_state: &WinState) -> u32 {
+    match win_state {
+        WinState::Undecided => 0,
+        WinState::Wolf => 1,
+        WinState::Sheep => 2,
+        WinState::Draw => 3,
+    }
+}
+
+fn num_to_win_state(win_state: u32) -> ah::Result<WinState> {
+    match win_state {
+        0 => Ok(WinState::Undecided),
+        1 => Ok(WinState::Wolf),
+        2 => Ok(WinState::Sheep),
+        3 => Ok(WinState::Draw),
+        s => Err(ah::format_err!("Unknown win state value: {}", s)),
+    }
 }
 
 impl fmt::Display for WinState {
@@ -114,42 +109,123 @@ impl fmt::Display for WinState {
                 WinState::Undecided => "undecided",
                 WinState::Wolf => "wolf",
                 WinState::Sheep => "sheep",
+                WinState::Draw => "draw",
             }
         )
     }
 }
 
-macro_rules! unused {
-    () => {
-        FieldState::Unused
-    };
+/// The reason why a game has been decided.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum WinReason {
+    /// The sheep occupy the whole barn.
+    BarnFilled,
+    /// The wolves can't move.
+    WolvesTrapped,
+    /// The sheep can't move.
+    SheepTrapped,
+    /// Too many sheep have been captured.
+    SheepCaptured,
+    /// All wolves have been huffed.
+    WolvesHuffed,
+    /// The same position occurred for the third time.
+    Repetition,
+    /// No progress has been made for too many moves.
+    MoveLimit,
+    /// A player has resigned.
+    Resignation,
+    /// A player has run out of time.
+    Timeout,
+    /// The players agreed to a draw.
+    AgreedDraw,
 }
-macro_rules! empty {
-    () => {
-        FieldState::Empty
-    };
+
+const WIN_REASONS: [WinReason; 10] = [
+    WinReason::BarnFilled,
+    WinReason::WolvesTrapped,
+    WinReason::SheepTrapped,
+    WinReason::SheepCaptured,
+    WinReason::WolvesHuffed,
+    WinReason::Repetition,
+    WinReason::MoveLimit,
+    WinReason::Resignation,
+    WinReason::Timeout,
+    WinReason::AgreedDraw,
+];
+
+impl WinReason {
+    /// Check if the reason is an event that can't be seen on the board.
+    /// Such results have to be declared.
+    pub fn is_declared(&self) -> bool {
+        matches!(
+            self,
+            WinReason::Resignation | WinReason::Timeout | WinReason::AgreedDraw
+        )
+    }
+
+    /// Parse the text representation of a reason.
+    pub fn from_text(text: &str) -> ah::Result<WinReason> {
+        WIN_REASONS
+            .iter()
+            .find(|reason| reason.to_string() == text.trim())
+            .copied()
+            .ok_or_else(|| ah::format_err!("Unknown game result reason: {}", text))
+    }
 }
-macro_rules! wolf {
-    () => {
-        FieldState::Wolf
-    };
+
+impl fmt::Display for WinReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                WinReason::BarnFilled => "barn filled",
+                WinReason::WolvesTrapped => "wolves trapped",
+                WinReason::SheepTrapped => "sheep trapped",
+                WinReason::SheepCaptured => "too many sheep captured",
+                WinReason::WolvesHuffed => "all wolves huffed",
+                WinReason::Repetition => "repetition",
+                WinReason::MoveLimit => "move limit",
+                WinReason::Resignation => "resignation",
+                WinReason::Timeout => "timeout",
+                WinReason::AgreedDraw => "agreed draw",
+            }
+        )
+    }
 }
-macro_rules! sheep {
-    () => {
-        FieldState::Sheep
-    };
+
+const fn win_reason_to_num(reason: &Option<WinReason>) -> u32 {
+    match reason {
+        None => 0,
+        Some(WinReason::BarnFilled) => 1,
+        Some(WinReason::WolvesTrapped) => 2,
+        Some(WinReason::SheepTrapped) => 3,
+        Some(WinReason::SheepCaptured) => 4,
+        Some(WinReason::WolvesHuffed) => 5,
+        Some(WinReason::Repetition) => 6,
+        Some(WinReason::MoveLimit) => 7,
+        Some(WinReason::Resignation) => 8,
+        Some(WinReason::Timeout) => 9,
+        Some(WinReason::AgreedDraw) => 10,
+    }
 }
 
-#[rustfmt::skip]
-const INITIAL_STATE: [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize] = [
-    [unused!(), unused!(), empty!(), unused!(), unused!(), ],
-    [unused!(), empty!(),  empty!(), empty!(),  unused!(), ],
-    [empty!(),  wolf!(),   empty!(), wolf!(),   empty!(),  ],
-    [empty!(),  empty!(),  empty!(), empty!(),  empty!(),  ],
-    [sheep!(),  sheep!(),  sheep!(), sheep!(),  sheep!(),  ],
-    [sheep!(),  sheep!(),  sheep!(), sheep!(),  sheep!(),  ],
-    [sheep!(),  sheep!(),  sheep!(), sheep!(),  sheep!(),  ],
-];
+fn num_to_win_reason(reason: u32) -> ah::Result<Option<WinReason>> {
+    match reason {
+        0 => Ok(None),
+        1 => Ok(Some(WinReason::BarnFilled)),
+        2 => Ok(Some(WinReason::WolvesTrapped)),
+        3 => Ok(Some(WinReason::SheepTrapped)),
+        4 => Ok(Some(WinReason::SheepCaptured)),
+        5 => Ok(Some(WinReason::WolvesHuffed)),
+        6 => Ok(Some(WinReason::Repetition)),
+        7 => Ok(Some(WinReason::MoveLimit)),
+        8 => Ok(Some(WinReason::Resignation)),
+        9 => Ok(Some(WinReason::Timeout)),
+        10 => Ok(Some(WinReason::AgreedDraw)),
+        r => Err(ah::format_err!("Unknown win reason value: {}", r)),
+    }
+}
 
 pub fn is_opposite_token(a: FieldState, b: FieldState) -> bool {
     (a == FieldState::Sheep && b == FieldState::Wolf)
@@ -185,12 +261,35 @@ fn num_to_move_state(move_state: (u32, u32, u32)) -> ah::Result<MoveState> {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-enum Turn {
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Turn {
     Sheep,
     Wolf,
 }
 
+impl Turn {
+    /// Get the other side.
+    pub fn other(&self) -> Turn {
+        match self {
+            Turn::Sheep => Turn::Wolf,
+            Turn::Wolf => Turn::Sheep,
+        }
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Turn::Sheep => "sheep",
+                Turn::Wolf => "wolf",
+            }
+        )
+    }
+}
+
 const fn turn_to_num(turn: &Turn) -> u32 {
     match turn {
         Turn::Sheep => 0,
@@ -198,7 +297,7 @@ const fn turn_to_num(turn: &Turn) -> u32 {
     }
 }
 
-fn num_to_turn(turn: u32) -> ah::Result<Turn> {
+pub fn num_to_turn(turn: u32) -> ah::Result<Turn> {
     match turn {
         0 => Ok(Turn::Sheep),
         1 => Ok(Turn::Wolf),
@@ -207,6 +306,55 @@ fn num_to_turn(turn: u32) -> ah::Result<Turn> {
     }
 }
 
+/// The subject of a vote of the players in a server room.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VoteSubject {
+    /// Load a game state into the room.
+    Load,
+    /// Reset the game in the room.
+    Reset,
+}
+
+impl fmt::Display for VoteSubject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                VoteSubject::Load => "load a game",
+                VoteSubject::Reset => "reset the game",
+            }
+        )
+    }
+}
+
+pub const fn vote_subject_to_num(subject: VoteSubject) -> u32 {
+    match subject {
+        VoteSubject::Load => MSG_VOTE_SUBJECT_LOAD,
+        VoteSubject::Reset => MSG_VOTE_SUBJECT_RESET,
+    }
+}
+
+pub fn num_to_vote_subject(subject: u32) -> ah::Result<VoteSubject> {
+    match subject {
+        MSG_VOTE_SUBJECT_LOAD => Ok(VoteSubject::Load),
+        MSG_VOTE_SUBJECT_RESET => Ok(VoteSubject::Reset),
+        subject => Err(ah::format_err!("Unknown vote subject value: {}", subject)),
+    }
+}
+
+/// A vote of the players in the server room that waits for answers.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PendingVote {
+    pub subject: VoteSubject,
+    /// The player that has requested the vote.
+    pub requester: String,
+    /// True, if this player has requested or answered the vote.
+    pub answered: bool,
+    /// The time at which the vote passes without objection, if any.
+    pub deadline: Option<Instant>,
+}
+
 #[derive(PartialEq, Debug)]
 enum ValidationResult {
     Invalid,
@@ -214,6 +362,17 @@ enum ValidationResult {
     ValidCapture(Coord),
 }
 
+/// One legal token move.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Move {
+    /// The position the token is moved from.
+    pub from_pos: Coord,
+    /// The position the token is moved to.
+    pub to_pos: Coord,
+    /// The position of the token captured by this move, if any.
+    pub capture_pos: Option<Coord>,
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Stats {
     pub wolves: u8,
@@ -221,11 +380,28 @@ pub struct Stats {
     pub sheep_captured: u8,
 }
 
+/// A snapshot of the game position.
+/// Used for undo and redo of moves.
+#[derive(Clone)]
+struct Snapshot {
+    fields: [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+    stats: Stats,
+    turn: Turn,
+    capture_chain: Option<Coord>,
+    orig_sheep_count: u8,
+    draw_state: DrawState,
+    declared_result: Option<(WinState, WinReason)>,
+    /// True, if the move done from this position has been recorded.
+    recorded: bool,
+}
+
 pub struct GameState {
+    rules: RuleSet,
     player_mode: PlayerMode,
     player_name: String,
     room_player_list: PlayerList,
-    room_list: Vec<String>,
+    /// The name, the rules and the password protection of each room.
+    room_list: Vec<(String, String, bool)>,
 
     fields: [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
     moving: MoveState,
@@ -233,8 +409,20 @@ pub struct GameState {
     stats: Stats,
     turn: Turn,
     just_captured: Option<Coord>,
+    capture_chain: Option<Coord>,
     orig_sheep_count: u8,
+    draw_state: DrawState,
+    declared_result: Option<(WinState, WinReason)>,
+    draw_offer: Option<Turn>,
+    takeback_request: Option<Turn>,
+    room_vote: Option<PendingVote>,
     recorder: Recorder,
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<(Snapshot, String)>,
+    start_fields: [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+    start_turn: Turn,
+    start_time: SystemTime,
+    setup_active: bool,
 
     client: Option<Client>,
     client_addr: Option<String>,
@@ -244,7 +432,12 @@ pub struct GameState {
 
 impl GameState {
     /// Construct a new game state.
-    pub fn new(player_mode: PlayerMode, player_name: Option<String>) -> ah::Result<GameState> {
+    pub fn new(
+        player_mode: PlayerMode,
+        player_name: Option<String>,
+        rules: RuleSet,
+    ) -> ah::Result<GameState> {
+        rules.check()?;
         let fields = [[FieldState::Unused; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
         let stats = Stats {
             wolves: 0,
@@ -258,6 +451,7 @@ impl GameState {
             None => format!("Player-{}", random_alphanum(5)),
         };
         let mut game = GameState {
+            rules,
             player_mode,
             player_name,
             room_player_list,
@@ -268,8 +462,20 @@ impl GameState {
             stats,
             turn: Turn::Sheep,
             just_captured: None,
+            capture_chain: None,
             orig_sheep_count: 0,
+            draw_state: DrawState::default(),
+            declared_result: None,
+            draw_offer: None,
+            takeback_request: None,
+            room_vote: None,
             recorder: Recorder::new(),
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            start_fields: fields,
+            start_turn: Turn::Sheep,
+            start_time: SystemTime::now(),
+            setup_active: false,
             client: None,
             client_addr: None,
             joined_room: None,
@@ -280,6 +486,62 @@ impl GameState {
         Ok(game)
     }
 
+    /// Create a local copy of the game state.
+    /// The copy is not connected to a server and the player may move all tokens.
+    pub fn clone_local(&self) -> GameState {
+        self.clone_local_with_recorder(self.recorder.clone())
+    }
+
+    /// Create a local copy of the game state for the search of a computer move.
+    /// The copy does not have the move record of the game.
+    pub fn clone_search(&self) -> GameState {
+        self.clone_local_with_recorder(Recorder::new())
+    }
+
+    fn clone_local_with_recorder(&self, recorder: Recorder) -> GameState {
+        GameState {
+            rules: self.rules.clone(),
+            player_mode: PlayerMode::Both,
+            player_name: self.player_name.clone(),
+            room_player_list: self.room_player_list.clone(),
+            room_list: vec![],
+            fields: self.fields,
+            moving: self.moving,
+            i_am_moving: self.i_am_moving,
+            stats: self.stats,
+            turn: self.turn,
+            just_captured: self.just_captured,
+            capture_chain: self.capture_chain,
+            orig_sheep_count: self.orig_sheep_count,
+            draw_state: self.draw_state.clone(),
+            declared_result: self.declared_result,
+            draw_offer: None,
+            takeback_request: None,
+            room_vote: None,
+            recorder,
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            start_fields: self.start_fields,
+            start_turn: self.start_turn,
+            start_time: self.start_time,
+            setup_active: false,
+            client: None,
+            client_addr: None,
+            joined_room: None,
+            say_deque: VecDeque::new(),
+        }
+    }
+
+    /// Get the rules of this game.
+    pub fn get_rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// Get the board of this game.
+    pub fn board(&self) -> &'static Board {
+        self.rules.board()
+    }
+
     pub fn get_recorder(&mut self) -> &Recorder {
         if let Err(e) = self.client_update_recorder() {
             Print::error(&format!(
@@ -287,6 +549,9 @@ impl GameState {
                 e
             ));
         }
+        let (win_state, reason) = self.get_result();
+        self.recorder
+            .set_result(reason.map(|reason| (win_state, reason)));
         &self.recorder
     }
 
@@ -296,11 +561,13 @@ impl GameState {
             return;
         }
 
+        self.setup_active = false;
+        let initial_fields = self.rules.initial_fields();
         self.orig_sheep_count = 0;
-        for coord in BoardIterator::new() {
+        for coord in self.board().iter() {
             let x = coord.x as usize;
             let y = coord.y as usize;
-            self.fields[y][x] = INITIAL_STATE[y][x];
+            self.fields[y][x] = initial_fields[y][x];
             match self.fields[y][x] {
                 FieldState::Sheep => self.orig_sheep_count += 1,
                 FieldState::Wolf | FieldState::Unused | FieldState::Empty => (),
@@ -311,22 +578,182 @@ impl GameState {
         self.i_am_moving = false;
         self.turn = Turn::Sheep;
         self.just_captured = None;
+        self.capture_chain = None;
+        self.declared_result = None;
+        self.draw_offer = None;
+        self.takeback_request = None;
 
         self.recorder.reset();
+        self.clear_history();
+        self.start_fields = self.fields;
+        self.start_turn = self.turn;
+        self.start_time = SystemTime::now();
         self.recalc_stats();
+        self.reset_draw_state(None);
         self.client_send_reset_game();
     }
 
+    /// Set a new start position without any move history.
+    fn set_start_position(
+        &mut self,
+        fields: [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+        turn: Turn,
+    ) {
+        self.fields = fields;
+        self.moving = MoveState::NoMove;
+        self.i_am_moving = false;
+        self.turn = turn;
+        self.just_captured = None;
+        self.capture_chain = None;
+        self.declared_result = None;
+        self.draw_offer = None;
+        self.takeback_request = None;
+        self.recorder.reset();
+        self.clear_history();
+        self.start_fields = fields;
+        self.setup_active = false;
+        self.recalc_stats();
+        self.pass_blocked_turn();
+        self.start_turn = self.turn;
+        self.reset_draw_state(None);
+    }
+
+    /// Take over the rules, the position and the history of another game.
+    fn take_game_from(&mut self, other: &GameState) {
+        self.rules = other.rules.clone();
+        self.fields = other.fields;
+        self.moving = MoveState::NoMove;
+        self.i_am_moving = false;
+        self.stats = other.stats;
+        self.turn = other.turn;
+        self.just_captured = None;
+        self.capture_chain = other.capture_chain;
+        self.orig_sheep_count = other.orig_sheep_count;
+        self.draw_state = other.draw_state.clone();
+        self.declared_result = other.declared_result;
+        self.recorder = other.recorder.clone();
+        self.undo_stack = other.undo_stack.clone();
+        self.redo_stack = other.redo_stack.clone();
+        self.start_fields = other.start_fields;
+        self.start_turn = other.start_turn;
+        self.start_time = other.start_time;
+        self.setup_active = false;
+    }
+
+    fn make_snapshot(&self, recorded: bool) -> Snapshot {
+        Snapshot {
+            fields: self.fields,
+            stats: self.stats,
+            turn: self.turn,
+            capture_chain: self.capture_chain,
+            orig_sheep_count: self.orig_sheep_count,
+            draw_state: self.draw_state.clone(),
+            declared_result: self.declared_result,
+            recorded,
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: &Snapshot) {
+        self.fields = snapshot.fields;
+        self.stats = snapshot.stats;
+        self.turn = snapshot.turn;
+        self.capture_chain = snapshot.capture_chain;
+        self.orig_sheep_count = snapshot.orig_sheep_count;
+        self.draw_state = snapshot.draw_state.clone();
+        self.declared_result = snapshot.declared_result;
+        self.moving = MoveState::NoMove;
+        self.i_am_moving = false;
+        self.just_captured = None;
+    }
+
+    /// Remember the current position before a move is committed.
+    /// recorded is true, if the move is added to the move record.
+    fn push_undo(&mut self, recorded: bool) {
+        self.undo_stack.push_back(self.make_snapshot(recorded));
+        if self.undo_stack.len() > UNDO_STACK_MAX_LEN {
+            self.undo_stack.pop_front();
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Forget the undo and redo history.
+    fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    fn check_history_allowed(&self, what: &str) -> ah::Result<()> {
+        if self.client.is_some() {
+            return Err(ah::format_err!("{}: Not possible in a network game.", what));
+        }
+        if self.player_mode == PlayerMode::Spectator {
+            return Err(ah::format_err!(
+                "{}: Player is spectator. Not allowed to move.",
+                what
+            ));
+        }
+        if self.setup_active {
+            return Err(ah::format_err!("{}: Position setup is active.", what));
+        }
+        Ok(())
+    }
+
+    /// Check if there is a move that can be taken back.
+    pub fn can_undo(&self) -> bool {
+        self.client.is_none() && !self.setup_active && !self.undo_stack.is_empty()
+    }
+
+    /// Check if there is a taken back move that can be done again.
+    pub fn can_redo(&self) -> bool {
+        self.client.is_none() && !self.setup_active && !self.redo_stack.is_empty()
+    }
+
+    /// Take back the last move.
+    pub fn undo(&mut self) -> ah::Result<()> {
+        self.check_history_allowed("undo")?;
+        let snapshot = match self.undo_stack.pop_back() {
+            Some(snapshot) => snapshot,
+            None => return Err(ah::format_err!("undo: No move to take back.")),
+        };
+        let record = if snapshot.recorded {
+            self.recorder.pop_move().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        self.redo_stack
+            .push((self.make_snapshot(snapshot.recorded), record));
+        self.restore_snapshot(&snapshot);
+        self.print_turn();
+        Ok(())
+    }
+
+    /// Do the last taken back move again.
+    pub fn redo(&mut self) -> ah::Result<()> {
+        self.check_history_allowed("redo")?;
+        let (snapshot, record) = match self.redo_stack.pop() {
+            Some(entry) => entry,
+            None => return Err(ah::format_err!("redo: No move to do again.")),
+        };
+        self.undo_stack
+            .push_back(self.make_snapshot(snapshot.recorded));
+        if !record.is_empty() {
+            self.recorder.push_move(record);
+        }
+        self.restore_snapshot(&snapshot);
+        self.print_turn();
+        Ok(())
+    }
+
     pub fn set_player_mode(&mut self, player_mode: PlayerMode) -> ah::Result<()> {
         if self.player_mode != player_mode {
-            self.do_join_room(None, None, Some(player_mode))?;
+            self.do_join_room(None, None, Some(player_mode), "")?;
         }
         Ok(())
     }
 
     pub fn set_player_name(&mut self, player_name: &str) -> ah::Result<()> {
         if self.player_name != player_name {
-            self.do_join_room(None, Some(player_name), None)?;
+            self.do_join_room(None, Some(player_name), None, "")?;
         }
         Ok(())
     }
@@ -339,14 +766,22 @@ impl GameState {
         &self.room_player_list
     }
 
-    pub fn get_room_list(&self) -> &Vec<String> {
+    /// Get the server rooms and the names of their rule variants.
+    pub fn get_room_list(&self) -> &Vec<(String, String, bool)> {
         &self.room_list
     }
 
+    /// Check if a room of the received room list is password protected.
+    pub fn client_room_is_protected(&self, room_name: &str) -> bool {
+        self.room_list
+            .iter()
+            .any(|(name, _, protected)| name == room_name && *protected)
+    }
+
     fn recalc_stats(&mut self) {
         self.stats.wolves = 0;
         self.stats.sheep = 0;
-        for coord in BoardIterator::new() {
+        for coord in self.board().iter() {
             let x = coord.x as usize;
             let y = coord.y as usize;
             match self.fields[y][x] {
@@ -355,7 +790,7 @@ impl GameState {
                 FieldState::Unused | FieldState::Empty => (),
             }
         }
-        self.stats.sheep_captured = self.orig_sheep_count - self.stats.sheep;
+        self.stats.sheep_captured = self.orig_sheep_count.saturating_sub(self.stats.sheep);
     }
 
     /// Get statistics.
@@ -363,77 +798,413 @@ impl GameState {
         self.stats
     }
 
-    fn wolves_are_stuck(&self) -> bool {
-        if self.turn != Turn::Wolf {
-            return false;
+    /// Check if one side has no legal move.
+    fn side_is_blocked(&self, turn: Turn) -> bool {
+        let token = match turn {
+            Turn::Wolf => FieldState::Wolf,
+            Turn::Sheep => FieldState::Sheep,
+        };
+
+        // Check if any token can do a valid move or capture.
+        !self.board().iter().any(|coord| {
+            self.get_field_state(coord) == token
+                && !self.do_legal_moves_from(coord, turn).is_empty()
+        })
+    }
+
+    /// Pass the turn to the other side, if the side to move is blocked
+    /// and the rules allow passing.
+    fn pass_blocked_turn(&mut self) {
+        if self.rules.blocked_rule == BlockedRule::Pass
+            && self.capture_chain.is_none()
+            && self.side_is_blocked(self.turn)
+            && !self.side_is_blocked(self.turn.other())
+        {
+            Print::debug(&format!("The {} are blocked and pass.", self.turn));
+            self.turn = self.turn.other();
         }
+    }
 
-        let mut can_move = false;
-        for coord in BoardIterator::new() {
-            match self.fields[coord.y as usize][coord.x as usize] {
-                FieldState::Wolf => {
-                    // Check if this wolf can do a valid move.
-                    for offset in &MOVE_OFFSETS {
-                        let to_pos = coord + *offset;
-                        match self.do_validate_move(coord, to_pos, PlayerMode::Wolf, Turn::Wolf) {
-                            ValidationResult::Valid => can_move = true,
-                            ValidationResult::Invalid | ValidationResult::ValidCapture(_) => (),
-                        }
-                    }
-                    // Check if this wolf can do a valid capture.
-                    for offset in &CAPTURE_OFFSETS {
-                        let to_pos = coord + *offset;
-                        match self.do_validate_move(coord, to_pos, PlayerMode::Wolf, Turn::Wolf) {
-                            ValidationResult::ValidCapture(_) => can_move = true,
-                            ValidationResult::Invalid | ValidationResult::Valid => (),
-                        }
-                    }
-                }
-                FieldState::Sheep | FieldState::Unused | FieldState::Empty => {}
-            }
-            if can_move {
-                break;
+    /// Check if the wolf at pos can capture a sheep.
+    fn wolf_can_capture(&self, pos: Coord) -> bool {
+        self.get_field_state(pos) == FieldState::Wolf
+            && self.board().graph().jumps(pos).iter().any(|jump| {
+                matches!(
+                    self.do_validate_move(pos, jump.to, PlayerMode::Both, Turn::Wolf),
+                    ValidationResult::ValidCapture(_)
+                )
+            })
+    }
+
+    /// Check if any wolf can capture a sheep.
+    fn wolves_can_capture(&self) -> bool {
+        self.board()
+            .iter()
+            .any(|coord| self.wolf_can_capture(coord))
+    }
+
+    /// Get the wolf that is huffed, if the wolf at from_pos makes a quiet move to to_pos.
+    /// The moving wolf is huffed, if it could have captured.
+    /// Otherwise another wolf that could have captured is huffed.
+    fn huffed_wolf(&self, from_pos: Coord, to_pos: Coord) -> Option<Coord> {
+        if self.rules.capture_rule != CaptureRule::Huffing
+            || self.get_field_state(from_pos) != FieldState::Wolf
+        {
+            None
+        } else if self.wolf_can_capture(from_pos) {
+            Some(to_pos)
+        } else {
+            self.board()
+                .iter()
+                .find(|coord| *coord != from_pos && self.wolf_can_capture(*coord))
+        }
+    }
+
+    /// Get all rule conforming moves of the token at pos.
+    fn do_legal_moves_from(&self, pos: Coord, turn: Turn) -> Vec<Move> {
+        let mut moves = vec![];
+        let targets = self.board().graph().edges(pos).iter().map(|edge| edge.to);
+        let jump_targets = self.board().graph().jumps(pos).iter().map(|jump| jump.to);
+        for to_pos in targets.chain(jump_targets) {
+            match self.do_validate_move(pos, to_pos, PlayerMode::Both, turn) {
+                ValidationResult::Invalid => (),
+                ValidationResult::Valid => moves.push(Move {
+                    from_pos: pos,
+                    to_pos,
+                    capture_pos: None,
+                }),
+                ValidationResult::ValidCapture(capture_pos) => moves.push(Move {
+                    from_pos: pos,
+                    to_pos,
+                    capture_pos: Some(capture_pos),
+                }),
             }
         }
-        !can_move
+        moves
+    }
+
+    /// Get all legal moves of the token at pos.
+    /// Returns an empty list, if the token does not belong to the side to move
+    /// or if the game is already decided.
+    pub fn legal_moves_from(&self, pos: Coord) -> Vec<Move> {
+        if self.get_win_state() != WinState::Undecided {
+            return vec![];
+        }
+        self.do_legal_moves_from(pos, self.turn)
+    }
+
+    /// Get all legal moves of the side to move.
+    /// Returns an empty list, if the game is already decided.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.get_win_state() != WinState::Undecided {
+            return vec![];
+        }
+        self.possible_moves(self.turn)
+    }
+
+    /// Get all rule conforming moves of one side,
+    /// regardless of whose turn it is.
+    pub fn possible_moves(&self, turn: Turn) -> Vec<Move> {
+        self.board()
+            .iter()
+            .flat_map(|coord| self.do_legal_moves_from(coord, turn))
+            .collect()
+    }
+
+    /// Get the side to move.
+    pub fn get_turn(&self) -> Turn {
+        self.turn
     }
 
     pub fn get_win_state(&self) -> WinState {
-        if self.get_stats().sheep < 9 {
-            WinState::Wolf
+        self.get_result().0
+    }
+
+    /// Get the game-win state and the reason why the game has been decided.
+    pub fn get_result(&self) -> (WinState, Option<WinReason>) {
+        if self.setup_active {
+            return (WinState::Undecided, None);
+        }
+        if let Some((win_state, reason)) = self.declared_result {
+            return (win_state, Some(reason));
+        }
+        let (win_state, reason) = if self.get_stats().sheep < self.rules.capture_threshold {
+            (WinState::Wolf, WinReason::SheepCaptured)
+        } else if self.get_stats().wolves == 0 {
+            (WinState::Sheep, WinReason::WolvesHuffed)
+        } else if self.board().has_barn()
+            && self.board().iter_pos().all(|(coord, pos_type)| {
+                // The sheep win, if they occupy the whole barn.
+                pos_type != PosType::Barn || self.get_field_state(coord) == FieldState::Sheep
+            })
+        {
+            (WinState::Sheep, WinReason::BarnFilled)
+        } else if let Some(reason) = self.draw_state.get_draw() {
+            return (WinState::Draw, reason);
+        } else if self.side_is_blocked(self.turn) {
+            let reason = match self.turn {
+                Turn::Wolf => WinReason::WolvesTrapped,
+                Turn::Sheep => WinReason::SheepTrapped,
+            };
+            match self.rules.blocked_rule {
+                BlockedRule::Loss => match self.turn {
+                    Turn::Wolf => (WinState::Sheep, reason),
+                    Turn::Sheep => (WinState::Wolf, reason),
+                },
+                BlockedRule::Pass if !self.side_is_blocked(self.turn.other()) => {
+                    return (WinState::Undecided, None);
+                }
+                BlockedRule::Pass | BlockedRule::Draw => (WinState::Draw, reason),
+            }
         } else {
-            let mut sheep_win = true;
-            for (coord, pos_type) in BoardPosIterator::new() {
-                let x = coord.x as usize;
-                let y = coord.y as usize;
-                match pos_type {
-                    PosType::Invalid => (),
-                    PosType::Field => (),
-                    PosType::Barn => {
-                        if self.fields[y][x] != FieldState::Sheep {
-                            sheep_win = false;
-                        }
-                    }
+            return (WinState::Undecided, None);
+        };
+        (win_state, Some(reason))
+    }
+
+    /// Decide the game by an event that can't be seen on the board,
+    /// such as a resignation, a timeout or an agreed draw.
+    pub fn declare_result(&mut self, win_state: WinState, reason: WinReason) -> ah::Result<()> {
+        if !reason.is_declared() || win_state == WinState::Undecided {
+            return Err(ah::format_err!(
+                "declare_result: Invalid result: {} ({})",
+                win_state,
+                reason
+            ));
+        }
+        if self.setup_active {
+            return Err(ah::format_err!("declare_result: Position setup is active."));
+        }
+        let current = self.get_win_state();
+        if current != WinState::Undecided {
+            return Err(ah::format_err!(
+                "declare_result: Already decided: {}",
+                current
+            ));
+        }
+        self.push_undo(false);
+        self.moving = MoveState::NoMove;
+        self.i_am_moving = false;
+        self.declared_result = Some((win_state, reason));
+        self.draw_offer = None;
+        Print::debug(&format!("Game result: {} ({})", win_state, reason));
+        Ok(())
+    }
+
+    /// Check if the player holds the seat of one side.
+    pub fn player_has_seat(&self, side: Turn) -> bool {
+        match self.player_mode {
+            PlayerMode::Both => true,
+            PlayerMode::Wolf => side == Turn::Wolf,
+            PlayerMode::Sheep => side == Turn::Sheep,
+            PlayerMode::Spectator => false,
+        }
+    }
+
+    /// Check if one side may resign or offer a draw.
+    fn check_can_give_up(&self, side: Turn) -> ah::Result<()> {
+        if !self.player_has_seat(side) {
+            return Err(ah::format_err!(
+                "The player does not play the {} side.",
+                side
+            ));
+        }
+        if self.setup_active {
+            return Err(ah::format_err!("Position setup is active."));
+        }
+        let win_state = self.get_win_state();
+        if win_state != WinState::Undecided {
+            return Err(ah::format_err!(
+                "The game is already decided: {}",
+                win_state
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resign the game for one side.
+    pub fn resign(&mut self, side: Turn) -> ah::Result<()> {
+        self.check_can_give_up(side)?;
+        self.client_send_resign(side)?;
+        let win_state = match side {
+            Turn::Wolf => WinState::Sheep,
+            Turn::Sheep => WinState::Wolf,
+        };
+        self.declare_result(win_state, WinReason::Resignation)
+    }
+
+    /// Get the side that has offered a draw, if any.
+    pub fn get_draw_offer(&self) -> Option<Turn> {
+        self.draw_offer
+    }
+
+    /// Offer a draw to the other side.
+    /// The offer stands until the other side answers it or makes a move.
+    pub fn offer_draw(&mut self, side: Turn) -> ah::Result<()> {
+        self.check_can_give_up(side)?;
+        if self.draw_offer.is_some() {
+            return Err(ah::format_err!("A draw has already been offered."));
+        }
+        self.client_send_draw_offer(MSG_DRAWOFFER_ACTION_OFFER, side)?;
+        self.draw_offer = Some(side);
+        Ok(())
+    }
+
+    /// Accept or decline the draw offered by the other side.
+    pub fn answer_draw_offer(&mut self, side: Turn, accept: bool) -> ah::Result<()> {
+        self.check_can_give_up(side)?;
+        if self.draw_offer != Some(side.other()) {
+            return Err(ah::format_err!(
+                "The {} side has not offered a draw.",
+                side.other()
+            ));
+        }
+        let action = if accept {
+            MSG_DRAWOFFER_ACTION_ACCEPT
+        } else {
+            MSG_DRAWOFFER_ACTION_DECLINE
+        };
+        self.client_send_draw_offer(action, side)?;
+        self.draw_offer = None;
+        if accept {
+            self.declare_result(WinState::Draw, WinReason::AgreedDraw)?;
+        }
+        Ok(())
+    }
+
+    /// Get the side that has requested a takeback, if any.
+    pub fn get_takeback_request(&self) -> Option<Turn> {
+        self.takeback_request
+    }
+
+    /// Get the number of undo steps that take back the last move of one side.
+    /// A capture chain is taken back completely.
+    /// The moves of the other side after that move are taken back, too.
+    fn takeback_steps(&self, side: Turn) -> Option<usize> {
+        self.undo_stack
+            .iter()
+            .rposition(|snapshot| {
+                snapshot.recorded && snapshot.turn == side && snapshot.capture_chain.is_none()
+            })
+            .map(|index| self.undo_stack.len() - index)
+    }
+
+    /// Ask the other side for the permission to take back the last move.
+    /// The request stands until the other side answers it or makes a move.
+    pub fn request_takeback(&mut self, side: Turn) -> ah::Result<()> {
+        if !self.player_has_seat(side) {
+            return Err(ah::format_err!(
+                "The player does not play the {} side.",
+                side
+            ));
+        }
+        if self.setup_active {
+            return Err(ah::format_err!("Position setup is active."));
+        }
+        if self.takeback_request.is_some() {
+            return Err(ah::format_err!("A takeback has already been requested."));
+        }
+        // The history is only known to the server.
+        if self.client.is_none() && self.takeback_steps(side).is_none() {
+            return Err(ah::format_err!(
+                "The {} side has no move to take back.",
+                side
+            ));
+        }
+        self.client_send_takeback(MSG_TAKEBACK_ACTION_REQUEST, side)?;
+        self.takeback_request = Some(side);
+        Ok(())
+    }
+
+    /// Accept or decline the takeback requested by the other side.
+    /// On acceptance the last move of the other side is taken back.
+    pub fn answer_takeback(&mut self, side: Turn, accept: bool) -> ah::Result<()> {
+        if !self.player_has_seat(side) {
+            return Err(ah::format_err!(
+                "The player does not play the {} side.",
+                side
+            ));
+        }
+        let requester = side.other();
+        if self.takeback_request != Some(requester) {
+            return Err(ah::format_err!(
+                "The {} side has not requested a takeback.",
+                requester
+            ));
+        }
+        let steps = if accept && self.client.is_none() {
+            match self.takeback_steps(requester) {
+                Some(steps) => steps,
+                None => {
+                    return Err(ah::format_err!(
+                        "The {} side has no move to take back.",
+                        requester
+                    ));
                 }
             }
-            if sheep_win || self.wolves_are_stuck() {
-                WinState::Sheep
-            } else {
-                WinState::Undecided
+        } else {
+            0
+        };
+        let action = if accept {
+            MSG_TAKEBACK_ACTION_ACCEPT
+        } else {
+            MSG_TAKEBACK_ACTION_DECLINE
+        };
+        self.client_send_takeback(action, side)?;
+        self.takeback_request = None;
+        // Over the network the server takes back the moves.
+        for _ in 0..steps {
+            self.undo()?;
+        }
+        if steps > 0 {
+            self.draw_offer = None;
+            self.redo_stack.clear();
+        }
+        Ok(())
+    }
+
+    /// Get the pending vote in the server room, if any.
+    pub fn get_room_vote(&self) -> Option<&PendingVote> {
+        self.room_vote.as_ref()
+    }
+
+    /// Agree to or object to the pending vote in the server room.
+    pub fn answer_room_vote(&mut self, accept: bool) -> ah::Result<()> {
+        if self.player_mode == PlayerMode::Spectator {
+            return Err(ah::format_err!("Spectators can't vote."));
+        }
+        let subject = match self.room_vote.as_ref() {
+            Some(vote) if vote.requester == self.player_name => {
+                return Err(ah::format_err!("The player has requested the vote."));
+            }
+            Some(vote) if vote.answered => {
+                return Err(ah::format_err!("The player has already voted."));
             }
+            Some(vote) => vote.subject,
+            None => return Err(ah::format_err!("There is no vote in progress.")),
+        };
+        let action = if accept {
+            MSG_VOTE_ACTION_ACCEPT
+        } else {
+            MSG_VOTE_ACTION_DECLINE
+        };
+        self.client_send_vote(action, subject)?;
+        if let Some(vote) = self.room_vote.as_mut() {
+            vote.answered = true;
         }
+        Ok(())
     }
 
     /// Set the state of a board field.
     fn set_field_state(&mut self, pos: Coord, state: FieldState) {
-        if coord_is_on_board(pos) {
+        if self.board().contains(pos) {
             self.fields[pos.y as usize][pos.x as usize] = state;
         }
     }
 
     /// Get the current state of a board field.
     pub fn get_field_state(&self, pos: Coord) -> FieldState {
-        if coord_is_on_board(pos) {
+        if self.board().contains(pos) {
             self.fields[pos.y as usize][pos.x as usize]
         } else {
             FieldState::Unused
@@ -477,8 +1248,6 @@ impl GameState {
     }
 
     /// Check if a move from from_pos to to_pos is valid.
-    #[allow(clippy::nonminimal_bool)]
-    #[allow(clippy::collapsible_if)]
     fn do_validate_move(
         &self,
         from_pos: Coord,
@@ -487,7 +1256,7 @@ impl GameState {
         turn: Turn,
     ) -> ValidationResult {
         // Check if positions are on the board.
-        if !coord_is_on_board(from_pos) || !coord_is_on_board(to_pos) {
+        if !self.board().contains(from_pos) || !self.board().contains(to_pos) {
             return ValidationResult::Invalid;
         }
 
@@ -536,83 +1305,63 @@ impl GameState {
             }
         }
 
-        let distx = to_pos.x as isize - from_pos.x as isize;
-        let centerx = from_pos.x as isize + (distx / 2);
-        let disty = to_pos.y as isize - from_pos.y as isize;
-        let centery = from_pos.y as isize + (disty / 2);
-
-        let center_pos = coord!(centerx as CoordAxis, centery as CoordAxis);
-        let center_state = self.get_field_state(center_pos);
+        // During a capture chain only the capturing wolf may move on.
+        if turn == Turn::Wolf
+            && let Some(chain_pos) = self.capture_chain
+            && chain_pos != from_pos
+        {
+            return ValidationResult::Invalid;
+        }
 
         let mut result = ValidationResult::Invalid;
 
-        if from_state == FieldState::Sheep && to_pos.y > from_pos.y {
-            // Invalid sheep backward move.
-        } else if from_pos.x != to_pos.x && from_pos.y != to_pos.y {
-            // Diagonal move.
+        let graph = self.board().graph();
+        if let Some(edge) = graph.edge(from_pos, to_pos) {
+            // Move along a board line to a neighbor position.
             if from_state == FieldState::Wolf {
-                if is_on_main_diag(from_pos) && is_on_main_diag(to_pos) {
-                    // Wolf diagonal move.
-                    if distx.abs() == 1 && disty.abs() == 1 {
-                        // Diagonal move by one field.
-                        result = ValidationResult::Valid;
-                    } else if distx.abs() == 2 && disty.abs() == 2 {
-                        if is_opposite_token(from_state, center_state) {
-                            // Captured.
-                            result = ValidationResult::ValidCapture(center_pos);
-                        }
-                    }
-                } else if (from_pos == coord!(1, 1) && to_pos == coord!(2, 0))
-                    || (from_pos == coord!(3, 1) && to_pos == coord!(2, 0))
-                    || (from_pos == coord!(2, 0) && to_pos == coord!(1, 1))
-                    || (from_pos == coord!(2, 0) && to_pos == coord!(3, 1))
-                {
-                    // Wolf move to/from barn top.
+                // Wolves can move in any direction.
+                result = ValidationResult::Valid;
+            } else if edge.dir.y > 0 {
+                // Invalid sheep backward move.
+            } else if edge.dir.y == 0 {
+                // Sheep horizontal move.
+                if self.rules.sheep_sideways {
                     result = ValidationResult::Valid;
-                } else if (from_pos == coord!(1, 2) && to_pos == coord!(2, 0))
-                    || (from_pos == coord!(2, 0) && to_pos == coord!(1, 2))
-                {
-                    if self.get_field_state(coord!(1, 1)) == FieldState::Sheep {
-                        // Captured at top-left corner of barn.
-                        result = ValidationResult::ValidCapture(coord!(1, 1));
-                    }
-                } else if (from_pos == coord!(3, 2) && to_pos == coord!(2, 0))
-                    || (from_pos == coord!(2, 0) && to_pos == coord!(3, 2))
-                {
-                    if self.get_field_state(coord!(3, 1)) == FieldState::Sheep {
-                        // Captured at top-right corner of barn.
-                        result = ValidationResult::ValidCapture(coord!(3, 1));
-                    }
                 }
-            } else if from_state == FieldState::Sheep
-                && (from_pos == coord!(1, 1) || from_pos == coord!(3, 1))
+            } else if edge.dir.x == 0
+                || self.rules.sheep_diagonal
+                || !graph.edges(from_pos).iter().any(|e| e.dir == coord!(0, -1))
             {
-                // Sheep move to barn top.
+                // Sheep forward move.
+                // Unless the rules allow it, sheep only move diagonally,
+                // if there is no straight way forward.
                 result = ValidationResult::Valid;
             }
-        } else if from_pos.x != to_pos.x && from_pos.y == to_pos.y {
-            // Horizontal move.
-            if distx.abs() == 1 {
-                result = ValidationResult::Valid;
-            } else if distx.abs() == 2 {
-                if from_state == FieldState::Wolf && is_opposite_token(from_state, center_state) {
-                    // Captured.
-                    result = ValidationResult::ValidCapture(center_pos);
-                }
-            }
-        } else if from_pos.x == to_pos.x && from_pos.y != to_pos.y {
-            // Vertical move.
-            if disty.abs() == 1 {
-                result = ValidationResult::Valid;
-            } else if disty.abs() == 2 {
-                if from_state == FieldState::Wolf && is_opposite_token(from_state, center_state) {
-                    // Captured.
-                    result = ValidationResult::ValidCapture(center_pos);
-                }
+        } else if let Some(jump) = graph.jump(from_pos, to_pos) {
+            // Jump over a neighbor position.
+            if from_state == FieldState::Wolf
+                && is_opposite_token(from_state, self.get_field_state(jump.over))
+            {
+                // Captured.
+                result = ValidationResult::ValidCapture(jump.over);
             }
-        } else {
-            // Can never happen.
-            Print::error("Internal error: validate_move() invalid state.");
+        }
+
+        if result == ValidationResult::Valid
+            && from_state == FieldState::Wolf
+            && turn == Turn::Wolf
+            && self.capture_chain.is_some()
+        {
+            // Quiet wolf move, but a capture chain can only be continued by capturing.
+            result = ValidationResult::Invalid;
+        }
+        if result == ValidationResult::Valid
+            && from_state == FieldState::Wolf
+            && self.rules.capture_rule == CaptureRule::Compulsory
+            && self.wolves_can_capture()
+        {
+            // Quiet wolf move, but a capture is compulsory.
+            result = ValidationResult::Invalid;
         }
 
         result
@@ -628,15 +1377,17 @@ impl GameState {
         match self.turn {
             Turn::Sheep => {
                 self.turn = Turn::Wolf;
+                self.capture_chain = None;
             }
             Turn::Wolf => {
                 // The next turn is sheep, except if a wolf has just captured a sheep
                 // and it can capture another one.
+                // Then only this wolf may continue the capture chain.
+                self.capture_chain = None;
                 let mut more = false;
                 if let Some(wolf_pos) = self.just_captured {
-                    for offset in &CAPTURE_OFFSETS {
-                        let to_pos = wolf_pos + *offset;
-                        match self.validate_move(wolf_pos, to_pos) {
+                    for jump in self.board().graph().jumps(wolf_pos) {
+                        match self.validate_move(wolf_pos, jump.to) {
                             ValidationResult::ValidCapture(_) => {
                                 Print::debug("Wolf can capture more sheep.");
                                 more = true;
@@ -645,11 +1396,12 @@ impl GameState {
                         }
                     }
                 }
-                //TODO: Can we have a WolfOrSheep turn in the "more" case?
                 self.turn = if more { Turn::Wolf } else { Turn::Sheep };
+                self.capture_chain = if more { self.just_captured } else { None };
             }
         }
         self.just_captured = None;
+        self.pass_blocked_turn();
         self.print_turn();
     }
 
@@ -664,6 +1416,9 @@ impl GameState {
         if self.moving != MoveState::NoMove {
             return Err(ah::format_err!("move_pick: Already moving."));
         }
+        if self.setup_active {
+            return Err(ah::format_err!("move_pick: Position setup is active."));
+        }
         if self.player_mode == PlayerMode::Spectator {
             return Err(ah::format_err!(
                 "move_pick: Player is spectator. Not allowed to move."
@@ -673,6 +1428,14 @@ impl GameState {
         if win_state != WinState::Undecided {
             return Err(ah::format_err!("move_pick: Already decided: {}", win_state));
         }
+        if let Some(chain_pos) = self.capture_chain
+            && chain_pos != pos
+        {
+            return Err(ah::format_err!(
+                "move_pick: The wolf at {} has to continue the capture chain.",
+                chain_pos
+            ));
+        }
 
         // Try to pick the token. This might fail.
         let result = match self.get_field_state(pos) {
@@ -697,7 +1460,8 @@ impl GameState {
     }
 
     /// Actually commit the move-put.
-    fn do_move_put(&mut self, to_pos: Coord, captured: bool) {
+    fn do_move_put(&mut self, to_pos: Coord, captured: bool, huffed: Option<Coord>) {
+        let sheep_in_barn = self.sheep_in_barn();
         match self.moving {
             MoveState::NoMove => Print::error("Internal error: Invalid move source."),
             MoveState::Wolf(from_pos) => {
@@ -709,15 +1473,41 @@ impl GameState {
                 self.set_field_state(from_pos, FieldState::Empty);
             }
         }
+        if let Some(huffed) = huffed {
+            self.set_field_state(huffed, FieldState::Empty);
+            Print::debug(&format!("Huffed wolf at {}", huffed));
+        }
+        let continues_chain = match self.moving {
+            MoveState::Wolf(from_pos) => captured && self.capture_chain == Some(from_pos),
+            MoveState::NoMove | MoveState::Sheep(_) => false,
+        };
+        // A move of the other side declines a pending draw offer or takeback request.
+        let moved_side = match self.moving {
+            MoveState::NoMove => None,
+            MoveState::Wolf(_) => Some(Turn::Wolf),
+            MoveState::Sheep(_) => Some(Turn::Sheep),
+        };
+        if self.draw_offer.is_some() && self.draw_offer != moved_side {
+            self.draw_offer = None;
+        }
+        if self.takeback_request.is_some() && self.takeback_request != moved_side {
+            self.takeback_request = None;
+        }
         self.recalc_stats();
         self.next_turn();
+        let progress = captured || huffed.is_some() || self.sheep_in_barn() > sheep_in_barn;
+        self.update_draw_state(progress);
         let recorded_move = RecordedMove {
             move_state: self.moving,
             to_pos,
             captured,
             win_state: self.get_win_state(),
         };
-        self.recorder.record_move(&recorded_move);
+        if continues_chain {
+            self.recorder.continue_move(&recorded_move);
+        } else {
+            self.recorder.record_move(&recorded_move);
+        }
         self.moving = MoveState::NoMove;
     }
 
@@ -747,13 +1537,16 @@ impl GameState {
                 ValidationResult::Invalid => Err(ah::format_err!("move_put: Invalid move.")),
                 ValidationResult::Valid => {
                     self.client_send_move_put(pos, token_id)?;
-                    self.do_move_put(pos, false);
+                    let huffed = self.huffed_wolf(from_pos, pos);
+                    self.push_undo(true);
+                    self.do_move_put(pos, false, huffed);
                     Ok(())
                 }
                 ValidationResult::ValidCapture(capture_pos) => {
                     self.client_send_move_put(pos, token_id)?;
+                    self.push_undo(true);
                     self.capture(from_pos, pos, capture_pos);
-                    self.do_move_put(pos, true);
+                    self.do_move_put(pos, true, None);
                     Ok(())
                 }
             },
@@ -762,6 +1555,76 @@ impl GameState {
         result
     }
 
+    /// Do a complete pick-and-put move operation.
+    pub fn make_move(&mut self, mov: &Move) -> ah::Result<()> {
+        self.move_pick(mov.from_pos)?;
+        if let Err(e) = self.move_put(mov.to_pos) {
+            self.move_abort();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Get the wolf that has to continue a capture chain, if any.
+    pub fn get_capture_chain(&self) -> Option<Coord> {
+        self.capture_chain
+    }
+
+    /// Check if the player may end the current capture chain.
+    pub fn can_end_capture_chain(&self) -> bool {
+        self.capture_chain.is_some()
+            && self.moving == MoveState::NoMove
+            && !self.setup_active
+            && self.rules.capture_rule != CaptureRule::Compulsory
+            && matches!(self.player_mode, PlayerMode::Wolf | PlayerMode::Both)
+    }
+
+    /// End the capture chain of the wolves without capturing more sheep.
+    /// With the huffing rules the wolf is huffed,
+    /// because it could have captured another sheep.
+    pub fn end_capture_chain(&mut self) -> ah::Result<()> {
+        let chain_pos = match self.capture_chain {
+            Some(pos) => pos,
+            None => return Err(ah::format_err!("end_capture_chain: No capture chain.")),
+        };
+        if self.moving != MoveState::NoMove {
+            return Err(ah::format_err!("end_capture_chain: A token is moving."));
+        }
+        if self.setup_active {
+            return Err(ah::format_err!(
+                "end_capture_chain: Position setup is active."
+            ));
+        }
+        match self.player_mode {
+            PlayerMode::Wolf | PlayerMode::Both => (),
+            PlayerMode::Sheep | PlayerMode::Spectator => {
+                return Err(ah::format_err!(
+                    "end_capture_chain: Player is not allowed to move the wolves."
+                ));
+            }
+        }
+        if self.rules.capture_rule == CaptureRule::Compulsory {
+            return Err(ah::format_err!(
+                "end_capture_chain: Capturing is compulsory."
+            ));
+        }
+
+        self.client_send_end_chain(chain_pos)?;
+        self.push_undo(false);
+        let huffed = self.rules.capture_rule == CaptureRule::Huffing;
+        if huffed {
+            self.set_field_state(chain_pos, FieldState::Empty);
+            Print::debug(&format!("Huffed wolf at {}", chain_pos));
+            self.recalc_stats();
+        }
+        self.turn = Turn::Sheep;
+        self.capture_chain = None;
+        self.pass_blocked_turn();
+        self.update_draw_state(huffed);
+        self.print_turn();
+        Ok(())
+    }
+
     /// Abort a move operation.
     pub fn move_abort(&mut self) {
         if self.player_mode == PlayerMode::Spectator {
@@ -784,14 +1647,24 @@ impl GameState {
     pub fn make_state_message(&self) -> MsgGameState {
         let mut fields =
             [[field_state_to_num(FieldState::Unused); BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
-        for coord in BoardIterator::new() {
+        for coord in self.board().iter() {
             let x = coord.x as usize;
             let y = coord.y as usize;
             fields[y][x] = field_state_to_num(self.fields[y][x]);
         }
         let (moving_state, moving_x, moving_y) = move_state_to_num(&self.moving);
         let turn = turn_to_num(&self.turn);
-        MsgGameState::new(fields, moving_state, moving_x, moving_y, turn)
+        let capture_chain = self.capture_chain.map(|pos| (pos.x as u32, pos.y as u32));
+        let (win_state, reason) = self.get_result();
+        MsgGameState::new(
+            fields,
+            moving_state,
+            moving_x,
+            moving_y,
+            turn,
+            capture_chain,
+            (win_state_to_num(&win_state), win_reason_to_num(&reason)),
+        )
     }
 
     pub fn read_state_message(&mut self, msg: &MsgGameState, force: bool) -> ah::Result<bool> {
@@ -803,7 +1676,7 @@ impl GameState {
 
         let mut changed = false;
         if !self.i_am_moving {
-            for coord in BoardIterator::new() {
+            for coord in self.board().iter() {
                 let x = coord.x as usize;
                 let y = coord.y as usize;
                 let field = match num_to_field_state(msg.get_fields()[y][x]) {
@@ -843,9 +1716,46 @@ impl GameState {
                 changed = true;
             }
 
+            let capture_chain = msg
+                .get_capture_chain()
+                .map(|(x, y)| coord!(x as i16, y as i16))
+                .filter(|pos| self.board().contains(*pos));
+            if capture_chain != self.capture_chain {
+                self.capture_chain = capture_chain;
+                changed = true;
+            }
+
+            // Draws and declared results can't be detected from the position alone.
+            let (result, reason) = msg.get_result();
+            let (draw, declared_result) =
+                match (num_to_win_state(result), num_to_win_reason(reason)) {
+                    // The reason of a draw from an older peer is unknown.
+                    (Ok(WinState::Draw), Ok(None)) => (Some(None), None),
+                    (
+                        Ok(WinState::Draw),
+                        Ok(Some(reason @ (WinReason::Repetition | WinReason::MoveLimit))),
+                    ) => (Some(Some(reason)), None),
+                    (Ok(win_state), Ok(Some(reason))) if reason.is_declared() => {
+                        (None, Some((win_state, reason)))
+                    }
+                    (Ok(_), Ok(_)) => (None, None),
+                    (Err(e), _) | (_, Err(e)) => {
+                        Print::error(&format!("Received invalid game result: {}", e));
+                        (self.draw_state.get_draw(), self.declared_result)
+                    }
+                };
+            if draw != self.draw_state.get_draw() || declared_result != self.declared_result {
+                changed = true;
+            }
+
             if changed {
                 self.recorder.reset();
+                self.clear_history();
+                self.start_fields = self.fields;
+                self.start_turn = self.turn;
                 self.recalc_stats();
+                self.reset_draw_state(draw);
+                self.declared_result = declared_result;
             }
         }
         Ok(changed)
@@ -877,8 +1787,9 @@ impl GameState {
             return;
         }
 
-        self.room_list
-            .resize_with(total_count as usize, || "".to_string());
+        self.room_list.resize_with(total_count as usize, || {
+            ("".to_string(), "".to_string(), false)
+        });
 
         let room_name = match msg.get_room_name() {
             Ok(n) => n,
@@ -888,13 +1799,73 @@ impl GameState {
             }
         };
 
+        let rules_name = match msg.get_rules_name() {
+            Ok(n) => n,
+            Err(e) => {
+                Print::error(&format!("Received RoomList with invalid rules name: {}", e));
+                return;
+            }
+        };
+
         let index = msg.get_index() as usize;
         if index >= self.room_list.len() {
             Print::error("Received RoomList with invalid index.");
             return;
         }
 
-        self.room_list[index] = room_name;
+        let protected = msg.get_flags() & MSG_ROOM_FLAG_PASSWORD != 0;
+        self.room_list[index] = (room_name, rules_name, protected);
+
+        if self.client_adopt_room_rules()
+            && let Some(client) = self.client.as_mut()
+            && let Err(e) = client.send_request_gamestate()
+        {
+            Print::error(&format!("Failed to request the game state: {}", e));
+        }
+    }
+
+    /// Switch to the rules of the joined room, if they are known.
+    /// Returns true, if the rules have been changed.
+    fn client_adopt_room_rules(&mut self) -> bool {
+        let Some(joined_room) = self.joined_room.as_ref() else {
+            return false;
+        };
+        let Some((_, rules_name, _)) = self
+            .room_list
+            .iter()
+            .find(|(name, _, _)| name == joined_room)
+        else {
+            return false;
+        };
+        if rules_name.is_empty() || *rules_name == self.rules.name {
+            return false;
+        }
+        match RuleSet::from_name(rules_name) {
+            Ok(rules) => {
+                Print::info(&format!(
+                    "Room '{}' plays the rules '{}'.",
+                    joined_room, rules.name
+                ));
+                self.rules = rules;
+                self.fields = [[FieldState::Unused; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+                self.moving = MoveState::NoMove;
+                self.i_am_moving = false;
+                self.capture_chain = None;
+                self.declared_result = None;
+                self.recorder.reset();
+                self.clear_history();
+                self.recalc_stats();
+                self.reset_draw_state(None);
+                true
+            }
+            Err(e) => {
+                Print::error(&format!(
+                    "Room '{}' plays unsupported rules: {}",
+                    joined_room, e
+                ));
+                false
+            }
+        }
     }
 
     fn client_handle_rx_msg_playerlist(&mut self, msg: &MsgPlayerList) {
@@ -953,6 +1924,114 @@ impl GameState {
         }
     }
 
+    fn client_handle_rx_msg_resign(&mut self, msg: &MsgResign) {
+        match num_to_turn(msg.get_turn()) {
+            Ok(side) => self.client_push_notice(
+                &msg.get_player_name(),
+                &format!("resigns for the {}.", side),
+            ),
+            Err(e) => Print::error(&format!("Received invalid Resign: {}", e)),
+        }
+    }
+
+    fn client_handle_rx_msg_draw_offer(&mut self, msg: &MsgDrawOffer) -> bool {
+        let (action, turn) = msg.get_action();
+        let side = match num_to_turn(turn) {
+            Ok(side) => side,
+            Err(e) => {
+                Print::error(&format!("Received invalid DrawOffer: {}", e));
+                return false;
+            }
+        };
+        let (draw_offer, text) = match action {
+            MSG_DRAWOFFER_ACTION_OFFER => (Some(side), "offers a draw"),
+            MSG_DRAWOFFER_ACTION_ACCEPT => (None, "accepts the draw"),
+            MSG_DRAWOFFER_ACTION_DECLINE => (None, "declines the draw"),
+            action => {
+                Print::error(&format!("Received invalid DrawOffer action: {}", action));
+                return false;
+            }
+        };
+        self.client_push_notice(&msg.get_player_name(), &format!("{} ({}).", text, side));
+        let changed = self.draw_offer != draw_offer;
+        self.draw_offer = draw_offer;
+        changed
+    }
+
+    fn client_handle_rx_msg_takeback(&mut self, msg: &MsgTakeback) -> bool {
+        let (action, turn) = msg.get_action();
+        let side = match num_to_turn(turn) {
+            Ok(side) => side,
+            Err(e) => {
+                Print::error(&format!("Received invalid Takeback: {}", e));
+                return false;
+            }
+        };
+        let (takeback_request, text) = match action {
+            MSG_TAKEBACK_ACTION_REQUEST => (Some(side), "requests a takeback"),
+            MSG_TAKEBACK_ACTION_ACCEPT => (None, "accepts the takeback"),
+            MSG_TAKEBACK_ACTION_DECLINE => (None, "declines the takeback"),
+            action => {
+                Print::error(&format!("Received invalid Takeback action: {}", action));
+                return false;
+            }
+        };
+        self.client_push_notice(&msg.get_player_name(), &format!("{} ({}).", text, side));
+        let changed = self.takeback_request != takeback_request;
+        self.takeback_request = takeback_request;
+        changed
+    }
+
+    fn client_handle_rx_msg_vote(&mut self, msg: &MsgVote) -> bool {
+        let (action, subject) = msg.get_action();
+        let subject = match num_to_vote_subject(subject) {
+            Ok(subject) => subject,
+            Err(e) => {
+                Print::error(&format!("Received invalid Vote: {}", e));
+                return false;
+            }
+        };
+        let player_name = msg.get_player_name();
+        let text = match action {
+            MSG_VOTE_ACTION_REQUEST => {
+                let timeout = msg.get_timeout();
+                self.room_vote = Some(PendingVote {
+                    subject,
+                    requester: player_name.clone(),
+                    answered: player_name == self.player_name,
+                    deadline: (timeout > 0)
+                        .then(|| Instant::now() + Duration::from_secs(timeout as u64)),
+                });
+                format!("asks the players to {}.", subject)
+            }
+            MSG_VOTE_ACTION_ACCEPT => format!("agrees to {}.", subject),
+            MSG_VOTE_ACTION_DECLINE => format!("objects to {}.", subject),
+            MSG_VOTE_ACTION_PASSED => {
+                self.room_vote = None;
+                format!("may {}. The vote has passed.", subject)
+            }
+            MSG_VOTE_ACTION_FAILED => {
+                self.room_vote = None;
+                format!("may not {}. The vote has failed.", subject)
+            }
+            action => {
+                Print::error(&format!("Received invalid Vote action: {}", action));
+                return false;
+            }
+        };
+        self.client_push_notice(&player_name, &text);
+        true
+    }
+
+    /// Show a game event of another player in the chat messages.
+    fn client_push_notice(&mut self, player_name: &str, text: &str) {
+        self.say_deque
+            .push_back(format!("*** {} {}", player_name, text));
+        if self.say_deque.len() > SAY_DEQUE_MAX_LEN {
+            self.say_deque.pop_front();
+        }
+    }
+
     fn client_handle_rx_messages(&mut self, messages: Vec<Box<dyn Message>>) -> bool {
         let mut redraw = false;
         for message in &messages {
@@ -968,6 +2047,8 @@ impl GameState {
                 | MsgType::Reset(_)
                 | MsgType::ReqGameState(_)
                 | MsgType::ReqRoomList(_)
+                | MsgType::CreateRoom(_)
+                | MsgType::CloseRoom(_)
                 | MsgType::ReqPlayerList(_)
                 | MsgType::ReqRecord(_)
                 | MsgType::Record(_)
@@ -990,6 +2071,26 @@ impl GameState {
                 MsgType::Say(msg) => {
                     self.client_handle_rx_msg_say(msg);
                 }
+                MsgType::Resign(msg) => {
+                    if self.joined_room.is_some() {
+                        self.client_handle_rx_msg_resign(msg);
+                    }
+                }
+                MsgType::DrawOffer(msg) => {
+                    if self.joined_room.is_some() && self.client_handle_rx_msg_draw_offer(msg) {
+                        redraw = true;
+                    }
+                }
+                MsgType::Takeback(msg) => {
+                    if self.joined_room.is_some() && self.client_handle_rx_msg_takeback(msg) {
+                        redraw = true;
+                    }
+                }
+                MsgType::Vote(msg) => {
+                    if self.joined_room.is_some() && self.client_handle_rx_msg_vote(msg) {
+                        redraw = true;
+                    }
+                }
             }
         }
 
@@ -1028,6 +2129,7 @@ impl GameState {
         room_name: Option<&str>,
         player_name: Option<&str>,
         player_mode: Option<PlayerMode>,
+        password: &str,
     ) -> ah::Result<()> {
         let room_name = match room_name {
             Some(room_name) => Some(room_name.to_string()),
@@ -1049,7 +2151,7 @@ impl GameState {
         if let Some(client) = self.client.as_mut()
             && let Some(room_name) = room_name
         {
-            match client.send_join(&room_name, player_name, player_mode) {
+            match client.send_join(&room_name, player_name, player_mode, password) {
                 Ok(_) => {
                     self.joined_room = Some(room_name);
                 }
@@ -1067,14 +2169,16 @@ impl GameState {
     }
 
     /// Join a room on the server.
-    pub fn client_join_room(&mut self, room_name: &str) -> ah::Result<()> {
+    /// The password is only needed for password protected rooms.
+    pub fn client_join_room(&mut self, room_name: &str, password: &str) -> ah::Result<()> {
         if self.client.is_none() {
             return Err(ah::format_err!(
                 "Cannot join room. Not connected to a server."
             ));
         }
         Print::info(&format!("Joining room '{}' ...", room_name));
-        self.do_join_room(Some(room_name), None, None)?;
+        self.do_join_room(Some(room_name), None, None, password)?;
+        self.client_adopt_room_rules();
         if let Some(client) = self.client.as_mut() {
             client.send_request_gamestate()?;
             self.fields = [[FieldState::Unused; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
@@ -1082,6 +2186,37 @@ impl GameState {
         Ok(())
     }
 
+    /// Create a new room on the server.
+    /// An empty rules_name selects the default rules of the server.
+    /// An empty password creates a room that is not password protected.
+    pub fn client_create_room(
+        &mut self,
+        room_name: &str,
+        rules_name: &str,
+        password: &str,
+    ) -> ah::Result<()> {
+        let Some(client) = self.client.as_mut() else {
+            return Err(ah::format_err!(
+                "Cannot create room. Not connected to a server."
+            ));
+        };
+        Print::info(&format!("Creating room '{}' ...", room_name));
+        client.send_create_room(room_name, rules_name, password)
+    }
+
+    /// Close the joined room on the server.
+    /// Only the player who has created the room can close it.
+    pub fn client_close_room(&mut self) -> ah::Result<()> {
+        let (Some(client), Some(room_name)) = (self.client.as_mut(), self.joined_room.as_ref())
+        else {
+            return Err(ah::format_err!("Cannot close room. No room joined."));
+        };
+        Print::info(&format!("Closing room '{}' ...", room_name));
+        client.send_close_room(room_name)?;
+        self.joined_room = None;
+        Ok(())
+    }
+
     /// Disconnect from a game server.
     pub fn client_disconnect(&mut self) {
         if let Some(client) = self.client.take() {
@@ -1162,6 +2297,71 @@ impl GameState {
         Ok(())
     }
 
+    /// Send the end of a capture chain to the server.
+    fn client_send_end_chain(&mut self, pos: Coord) -> ah::Result<()> {
+        if let Some(client) = self.client.as_mut()
+            && let Err(e) = client.send_move_token(
+                MSG_MOVE_ACTION_END_CHAIN,
+                MSG_MOVE_TOKEN_WOLF,
+                pos.x as u32,
+                pos.y as u32,
+            )
+        {
+            let msg = format!("Ending the capture chain failed on server: {}", e);
+            Print::error(&msg);
+            return Err(ah::format_err!("{}", msg));
+        }
+        Ok(())
+    }
+
+    /// Send the resignation of one side to the server.
+    fn client_send_resign(&mut self, side: Turn) -> ah::Result<()> {
+        if let Some(client) = self.client.as_mut()
+            && let Err(e) = client.send_resign(turn_to_num(&side))
+        {
+            let msg = format!("Resignation failed on server: {}", e);
+            Print::error(&msg);
+            return Err(ah::format_err!("{}", msg));
+        }
+        Ok(())
+    }
+
+    /// Send a draw offer or the answer to a draw offer to the server.
+    fn client_send_draw_offer(&mut self, action: u32, side: Turn) -> ah::Result<()> {
+        if let Some(client) = self.client.as_mut()
+            && let Err(e) = client.send_draw_offer(action, turn_to_num(&side))
+        {
+            let msg = format!("Draw offer failed on server: {}", e);
+            Print::error(&msg);
+            return Err(ah::format_err!("{}", msg));
+        }
+        Ok(())
+    }
+
+    /// Send a takeback request or the answer to a takeback request to the server.
+    fn client_send_takeback(&mut self, action: u32, side: Turn) -> ah::Result<()> {
+        if let Some(client) = self.client.as_mut()
+            && let Err(e) = client.send_takeback(action, turn_to_num(&side))
+        {
+            let msg = format!("Takeback failed on server: {}", e);
+            Print::error(&msg);
+            return Err(ah::format_err!("{}", msg));
+        }
+        Ok(())
+    }
+
+    /// Send the answer to a vote in the server room.
+    fn client_send_vote(&mut self, action: u32, subject: VoteSubject) -> ah::Result<()> {
+        if let Some(client) = self.client.as_mut()
+            && let Err(e) = client.send_vote(action, vote_subject_to_num(subject))
+        {
+            let msg = format!("Vote failed on server: {}", e);
+            Print::error(&msg);
+            return Err(ah::format_err!("{}", msg));
+        }
+        Ok(())
+    }
+
     fn client_send_full_gamestate(&mut self) -> ah::Result<()> {
         let mut game_state_msg = self.make_state_message();
         if let Some(client) = self.client.as_mut() {
@@ -1200,6 +2400,61 @@ impl GameState {
 //////////////////////////////////////////////////////////////////////////////
 
 impl GameState {
+    /// Check if a received game state can be reached with the rules of this game.
+    pub fn server_check_state_message(&self, msg: &MsgGameState) -> ah::Result<()> {
+        let mut fields = [[FieldState::Unused; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+        for (y, row) in fields.iter_mut().enumerate() {
+            for (x, field) in row.iter_mut().enumerate() {
+                let pos = coord!(x as i16, y as i16);
+                *field = num_to_field_state(msg.get_fields()[y][x])?;
+                match (*field, self.board().contains(pos)) {
+                    (FieldState::Unused, true) => {
+                        return Err(ah::format_err!("The board position {} is missing.", pos));
+                    }
+                    (FieldState::Empty | FieldState::Wolf | FieldState::Sheep, false) => {
+                        return Err(ah::format_err!("The position {} is not on the board.", pos));
+                    }
+                    (FieldState::Unused, false)
+                    | (FieldState::Empty | FieldState::Wolf | FieldState::Sheep, true) => (),
+                }
+            }
+        }
+        self.check_position(&fields)?;
+
+        let field_at = |pos: Coord| {
+            if self.board().contains(pos) {
+                fields[pos.y as usize][pos.x as usize]
+            } else {
+                FieldState::Unused
+            }
+        };
+        match num_to_move_state(msg.get_moving())? {
+            MoveState::NoMove => (),
+            MoveState::Wolf(pos) if field_at(pos) == FieldState::Wolf => (),
+            MoveState::Sheep(pos) if field_at(pos) == FieldState::Sheep => (),
+            MoveState::Wolf(pos) | MoveState::Sheep(pos) => {
+                return Err(ah::format_err!(
+                    "The moving token is not at the position {}.",
+                    pos
+                ));
+            }
+        }
+        num_to_turn(msg.get_turn())?;
+        if let Some((x, y)) = msg.get_capture_chain() {
+            let pos = coord!(x as i16, y as i16);
+            if field_at(pos) != FieldState::Wolf {
+                return Err(ah::format_err!(
+                    "The capture chain does not continue at a wolf at {}.",
+                    pos
+                ));
+            }
+        }
+        let (result, reason) = msg.get_result();
+        num_to_win_state(result)?;
+        num_to_win_reason(reason)?;
+        Ok(())
+    }
+
     pub fn server_handle_rx_msg_move(&mut self, msg: &MsgMove) -> ah::Result<()> {
         match msg.get_action() {
             (MSG_MOVE_ACTION_PICK, x, y) => {
@@ -1214,12 +2469,499 @@ impl GameState {
             (MSG_MOVE_ACTION_ABORT, _x, _y) => {
                 self.move_abort();
             }
+            (MSG_MOVE_ACTION_END_CHAIN, _x, _y) => {
+                self.end_capture_chain()?;
+            }
             (action, _, _) => {
                 Print::error(&format!("Received invalid move action: {}", action));
             }
         }
         Ok(())
     }
+
+    /// Make the message that tells the clients about a draw offer
+    /// that has been dropped by a move or by a reset of the game.
+    pub fn server_make_dropped_draw_offer_msg(
+        &self,
+        draw_offer: Option<Turn>,
+        player_name: &str,
+    ) -> ah::Result<Option<MsgDrawOffer>> {
+        match draw_offer {
+            Some(side) if self.draw_offer.is_none() => Ok(Some(MsgDrawOffer::new(
+                MSG_DRAWOFFER_ACTION_DECLINE,
+                turn_to_num(&side.other()),
+                player_name,
+            )?)),
+            Some(_) | None => Ok(None),
+        }
+    }
+
+    /// Make the message that tells the clients about a takeback request
+    /// that has been dropped by a move or by a reset of the game.
+    pub fn server_make_dropped_takeback_msg(
+        &self,
+        takeback_request: Option<Turn>,
+        player_name: &str,
+    ) -> ah::Result<Option<MsgTakeback>> {
+        match takeback_request {
+            Some(side) if self.takeback_request.is_none() => Ok(Some(MsgTakeback::new(
+                MSG_TAKEBACK_ACTION_DECLINE,
+                turn_to_num(&side.other()),
+                player_name,
+            )?)),
+            Some(_) | None => Ok(None),
+        }
+    }
+
+    pub fn server_handle_rx_msg_resign(&mut self, msg: &MsgResign) -> ah::Result<()> {
+        self.resign(num_to_turn(msg.get_turn())?)
+    }
+
+    pub fn server_handle_rx_msg_draw_offer(&mut self, msg: &MsgDrawOffer) -> ah::Result<()> {
+        match msg.get_action() {
+            (MSG_DRAWOFFER_ACTION_OFFER, turn) => self.offer_draw(num_to_turn(turn)?),
+            (MSG_DRAWOFFER_ACTION_ACCEPT, turn) => self.answer_draw_offer(num_to_turn(turn)?, true),
+            (MSG_DRAWOFFER_ACTION_DECLINE, turn) => {
+                self.answer_draw_offer(num_to_turn(turn)?, false)
+            }
+            (action, _) => Err(ah::format_err!(
+                "Received invalid draw offer action: {}",
+                action
+            )),
+        }
+    }
+
+    pub fn server_handle_rx_msg_takeback(&mut self, msg: &MsgTakeback) -> ah::Result<()> {
+        match msg.get_action() {
+            (MSG_TAKEBACK_ACTION_REQUEST, turn) => self.request_takeback(num_to_turn(turn)?),
+            (MSG_TAKEBACK_ACTION_ACCEPT, turn) => self.answer_takeback(num_to_turn(turn)?, true),
+            (MSG_TAKEBACK_ACTION_DECLINE, turn) => self.answer_takeback(num_to_turn(turn)?, false),
+            (action, _) => Err(ah::format_err!(
+                "Received invalid takeback action: {}",
+                action
+            )),
+        }
+    }
+}
+
+/// Create a local game for the tests.
+/// The game starts at the position in the one-line text notation, if a position is given.
+#[cfg(test)]
+pub(crate) fn new_test_game(rules: &str, position: Option<&str>) -> GameState {
+    let mut game =
+        GameState::new(PlayerMode::Both, None, RuleSet::from_name(rules).unwrap()).unwrap();
+    if let Some(position) = position {
+        game.set_position_notation(position).unwrap();
+    }
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The wolf on a4 can capture the sheep on b4 and then the sheep on d4.
+    /// The wolf on c7 can't capture.
+    const CHAIN_POSITION: &str = "W/3/5/WS1S1/SSS2/SSSSS/SSSSS w";
+
+    const INITIAL_POSITION: &str = "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s";
+
+    fn find_move(game: &GameState, from_pos: Coord, to_pos: Coord) -> Option<Move> {
+        game.legal_moves()
+            .into_iter()
+            .find(|mov| mov.from_pos == from_pos && mov.to_pos == to_pos)
+    }
+
+    fn do_move(game: &mut GameState, from_pos: Coord, to_pos: Coord) {
+        let mov = find_move(game, from_pos, to_pos).unwrap();
+        game.make_move(&mov).unwrap();
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut game = new_test_game("standard", Some(INITIAL_POSITION));
+        let moves = game.legal_moves();
+        assert!(!moves.is_empty());
+        for mov in &moves {
+            assert_eq!(game.get_field_state(mov.from_pos), FieldState::Sheep);
+            assert_eq!(game.get_field_state(mov.to_pos), FieldState::Empty);
+            assert_eq!(mov.capture_pos, None);
+        }
+        assert!(game.legal_moves_from(coord!(1, 2)).is_empty());
+        assert_eq!(game.possible_moves(Turn::Wolf).len(), 8);
+
+        // After b3-b4 the wolf on b5 can capture on b4.
+        game.move_pick(coord!(1, 4)).unwrap();
+        game.move_put(coord!(1, 3)).unwrap();
+        let moves = game.legal_moves_from(coord!(1, 2));
+        assert!(moves.contains(&Move {
+            from_pos: coord!(1, 2),
+            to_pos: coord!(1, 4),
+            capture_pos: Some(coord!(1, 3)),
+        }));
+        assert!(moves.contains(&Move {
+            from_pos: coord!(1, 2),
+            to_pos: coord!(1, 1),
+            capture_pos: None,
+        }));
+        assert!(game.legal_moves_from(coord!(1, 3)).is_empty());
+
+        // No moves in a decided game.
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        game.declare_result(WinState::Sheep, WinReason::Resignation)
+            .unwrap();
+        assert!(game.legal_moves().is_empty());
+        assert!(game.legal_moves_from(coord!(0, 3)).is_empty());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        let start = game.get_position_notation();
+        assert!(!game.can_undo());
+        assert!(game.undo().is_err());
+
+        do_move(&mut game, coord!(0, 3), coord!(2, 3));
+        do_move(&mut game, coord!(2, 3), coord!(4, 3));
+        do_move(&mut game, coord!(1, 4), coord!(1, 3));
+        let end = game.get_position_notation();
+        let moves = game.get_recorder().get_moves().clone();
+        assert_eq!(moves, ["Wa4xc4xe4", "Sb3-b4"]);
+
+        while game.can_undo() {
+            game.undo().unwrap();
+        }
+        assert_eq!(game.get_position_notation(), start);
+        assert!(game.get_recorder().get_moves().is_empty());
+        assert_eq!(game.get_stats().sheep_captured, 0);
+
+        while game.can_redo() {
+            game.redo().unwrap();
+        }
+        assert_eq!(game.get_position_notation(), end);
+        assert_eq!(game.get_recorder().get_moves(), &moves);
+
+        // A new move discards the taken back moves.
+        game.undo().unwrap();
+        do_move(&mut game, coord!(0, 4), coord!(0, 3));
+        assert!(!game.can_redo());
+        assert!(game.redo().is_err());
+    }
+
+    #[test]
+    fn test_undo_stack_limit() {
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        for _ in 0..(UNDO_STACK_MAX_LEN + 10) {
+            game.push_undo(false);
+        }
+        assert_eq!(game.undo_stack.len(), UNDO_STACK_MAX_LEN);
+    }
+
+    #[test]
+    fn test_optional_capture() {
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        assert!(find_move(&game, coord!(0, 3), coord!(0, 2)).is_some());
+        do_move(&mut game, coord!(0, 3), coord!(0, 2));
+        assert_eq!(game.get_stats().wolves, 2);
+        assert_eq!(game.get_turn(), Turn::Sheep);
+    }
+
+    #[test]
+    fn test_compulsory_capture() {
+        let mut game = new_test_game("compulsory", Some(CHAIN_POSITION));
+        let moves = game.legal_moves();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].capture_pos, Some(coord!(1, 3)));
+        let quiet = Move {
+            from_pos: coord!(0, 3),
+            to_pos: coord!(0, 2),
+            capture_pos: None,
+        };
+        assert!(game.make_move(&quiet).is_err());
+
+        do_move(&mut game, coord!(0, 3), coord!(2, 3));
+        assert_eq!(game.get_capture_chain(), Some(coord!(2, 3)));
+        assert!(game.end_capture_chain().is_err());
+        do_move(&mut game, coord!(2, 3), coord!(4, 3));
+        assert_eq!(game.get_capture_chain(), None);
+        assert_eq!(game.get_turn(), Turn::Sheep);
+        assert_eq!(game.get_stats().sheep_captured, 2);
+    }
+
+    #[test]
+    fn test_huffing() {
+        // The wolf that could have captured is huffed after its quiet move.
+        let mut game = new_test_game("huffing", Some(CHAIN_POSITION));
+        do_move(&mut game, coord!(0, 3), coord!(0, 2));
+        assert_eq!(game.get_field_state(coord!(0, 2)), FieldState::Empty);
+        assert_eq!(game.get_field_state(coord!(2, 0)), FieldState::Wolf);
+        assert_eq!(game.get_stats().wolves, 1);
+
+        // The other wolf is huffed after a quiet move of the wolf that can't capture.
+        let mut game = new_test_game("huffing", Some(CHAIN_POSITION));
+        do_move(&mut game, coord!(2, 0), coord!(2, 1));
+        assert_eq!(game.get_field_state(coord!(0, 3)), FieldState::Empty);
+        assert_eq!(game.get_field_state(coord!(2, 1)), FieldState::Wolf);
+        assert_eq!(game.get_stats().wolves, 1);
+
+        // The sheep win, if all wolves have been huffed.
+        let mut game = new_test_game("huffing", Some("1/3/5/WS1S1/SSS2/SSSSS/SSSSS w"));
+        do_move(&mut game, coord!(0, 3), coord!(0, 2));
+        assert_eq!(
+            game.get_result(),
+            (WinState::Sheep, Some(WinReason::WolvesHuffed))
+        );
+    }
+
+    #[test]
+    fn test_capture_chain() {
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        do_move(&mut game, coord!(0, 3), coord!(2, 3));
+        assert_eq!(game.get_turn(), Turn::Wolf);
+        assert_eq!(game.get_capture_chain(), Some(coord!(2, 3)));
+        // Only the chain wolf may continue and only by capturing.
+        let moves = game.legal_moves();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].from_pos, coord!(2, 3));
+        assert_eq!(moves[0].capture_pos, Some(coord!(3, 3)));
+
+        game.end_capture_chain().unwrap();
+        assert_eq!(game.get_turn(), Turn::Sheep);
+        assert_eq!(game.get_capture_chain(), None);
+        assert_eq!(game.get_field_state(coord!(2, 3)), FieldState::Wolf);
+        assert!(game.end_capture_chain().is_err());
+
+        // The chain wolf is huffed, if it ends the chain.
+        let mut game = new_test_game("huffing", Some(CHAIN_POSITION));
+        do_move(&mut game, coord!(0, 3), coord!(2, 3));
+        game.end_capture_chain().unwrap();
+        assert_eq!(game.get_field_state(coord!(2, 3)), FieldState::Empty);
+        assert_eq!(game.get_stats().wolves, 1);
+    }
+
+    #[test]
+    fn test_state_message_result() {
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        game.declare_result(WinState::Sheep, WinReason::Resignation)
+            .unwrap();
+        let mut other = new_test_game("standard", Some(CHAIN_POSITION));
+        other
+            .read_state_message(&game.make_state_message(), true)
+            .unwrap();
+        assert_eq!(
+            other.get_result(),
+            (WinState::Sheep, Some(WinReason::Resignation))
+        );
+
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        game.declare_result(WinState::Draw, WinReason::AgreedDraw)
+            .unwrap();
+        let msg = game.make_state_message();
+        let mut other = new_test_game("standard", Some(CHAIN_POSITION));
+        other.read_state_message(&msg, true).unwrap();
+        assert_eq!(
+            other.get_result(),
+            (WinState::Draw, Some(WinReason::AgreedDraw))
+        );
+
+        // A draw from an older peer has no reason.
+        let (moving_state, moving_x, moving_y) = msg.get_moving();
+        let old_msg = MsgGameState::new(
+            *msg.get_fields(),
+            moving_state,
+            moving_x,
+            moving_y,
+            msg.get_turn(),
+            msg.get_capture_chain(),
+            (msg.get_result().0, 0),
+        );
+        let mut other = new_test_game("standard", Some(CHAIN_POSITION));
+        other.read_state_message(&old_msg, true).unwrap();
+        assert_eq!(other.get_result(), (WinState::Draw, None));
+    }
+
+    #[test]
+    fn test_variants() {
+        // The fox captures along the arm of the 7 columns wide cross board.
+        let mut game = new_test_game("fox-and-geese", Some("3/3/7/S4SW/7/SSS/SSS w"));
+        assert_eq!(
+            find_move(&game, coord!(6, 3), coord!(4, 3))
+                .unwrap()
+                .capture_pos,
+            Some(coord!(5, 3))
+        );
+        do_move(&mut game, coord!(6, 3), coord!(4, 3));
+        assert_eq!(game.get_field_state(coord!(5, 3)), FieldState::Empty);
+        assert_eq!(game.get_field_state(coord!(4, 3)), FieldState::Wolf);
+
+        // The geese may not move diagonally, the asalto soldiers may.
+        let game = new_test_game("fox-and-geese", Some("3/3/7/3W3/SS2S2/SSS/SSS s"));
+        assert!(find_move(&game, coord!(4, 4), coord!(3, 3)).is_none());
+        assert!(find_move(&game, coord!(4, 4), coord!(4, 3)).is_some());
+        let game = new_test_game("asalto", Some("3/3/W1W4/7/SS2S2/SSS/SSS s"));
+        assert!(find_move(&game, coord!(4, 4), coord!(3, 3)).is_some());
+        assert!(find_move(&game, coord!(4, 4), coord!(5, 3)).is_some());
+    }
+
+    #[test]
+    fn test_blocked() {
+        // The wolf on c7 is enclosed. The sheep can't move without sideways moves.
+        let position = "W/SSS/SSSSS/SSSSS/5/5/5";
+        let game = new_test_game("forward-sheep", Some(&format!("{} s", position)));
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(
+            game.get_result(),
+            (WinState::Wolf, Some(WinReason::SheepTrapped))
+        );
+        let game = new_test_game("forward-sheep", Some(&format!("{} w", position)));
+        assert_eq!(
+            game.get_result(),
+            (WinState::Sheep, Some(WinReason::WolvesTrapped))
+        );
+        let game = new_test_game(
+            "forward-sheep,blocked=draw",
+            Some(&format!("{} s", position)),
+        );
+        assert_eq!(
+            game.get_result(),
+            (WinState::Draw, Some(WinReason::SheepTrapped))
+        );
+        let game = new_test_game(
+            "forward-sheep,blocked=pass",
+            Some(&format!("{} s", position)),
+        );
+        assert_eq!(
+            game.get_result(),
+            (WinState::Draw, Some(WinReason::SheepTrapped))
+        );
+
+        // With sideways moves only the wolf is blocked.
+        let game = new_test_game("standard,blocked=pass", Some("W/SSS/SSSSS/SSSS1/5/5/S4 w"));
+        assert_eq!(game.get_result(), (WinState::Undecided, None));
+        let game = new_test_game("standard", Some("W/SSS/SSSSS/SSSS1/5/5/S4 s"));
+        assert_eq!(game.get_result(), (WinState::Undecided, None));
+        assert!(!game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut game = new_test_game("standard", Some(INITIAL_POSITION));
+        game.resign(Turn::Wolf).unwrap();
+        assert_eq!(
+            game.get_result(),
+            (WinState::Sheep, Some(WinReason::Resignation))
+        );
+        assert!(game.resign(Turn::Sheep).is_err());
+        assert!(game.offer_draw(Turn::Sheep).is_err());
+    }
+
+    #[test]
+    fn test_draw_offer() {
+        let mut game = new_test_game("standard", Some(INITIAL_POSITION));
+        game.offer_draw(Turn::Sheep).unwrap();
+        assert_eq!(game.get_draw_offer(), Some(Turn::Sheep));
+        assert!(game.offer_draw(Turn::Wolf).is_err());
+        assert!(game.answer_draw_offer(Turn::Sheep, true).is_err());
+        game.answer_draw_offer(Turn::Wolf, false).unwrap();
+        assert_eq!(game.get_draw_offer(), None);
+        assert_eq!(game.get_result(), (WinState::Undecided, None));
+
+        // The offer stands after a move of the offering side
+        // and is declined by a move of the other side.
+        game.offer_draw(Turn::Sheep).unwrap();
+        do_move(&mut game, coord!(0, 4), coord!(0, 3));
+        assert_eq!(game.get_draw_offer(), Some(Turn::Sheep));
+        do_move(&mut game, coord!(1, 2), coord!(1, 1));
+        assert_eq!(game.get_draw_offer(), None);
+
+        game.offer_draw(Turn::Wolf).unwrap();
+        game.answer_draw_offer(Turn::Sheep, true).unwrap();
+        assert_eq!(
+            game.get_result(),
+            (WinState::Draw, Some(WinReason::AgreedDraw))
+        );
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_takeback() {
+        let mut game = new_test_game("standard", Some(INITIAL_POSITION));
+        assert!(game.request_takeback(Turn::Sheep).is_err());
+        do_move(&mut game, coord!(0, 4), coord!(0, 3));
+        game.request_takeback(Turn::Sheep).unwrap();
+        assert_eq!(game.get_takeback_request(), Some(Turn::Sheep));
+        assert!(game.request_takeback(Turn::Wolf).is_err());
+        assert!(game.answer_takeback(Turn::Sheep, true).is_err());
+        game.answer_takeback(Turn::Wolf, false).unwrap();
+        assert_eq!(game.get_takeback_request(), None);
+        assert_eq!(game.get_recorder().get_moves().len(), 1);
+
+        game.request_takeback(Turn::Sheep).unwrap();
+        game.answer_takeback(Turn::Wolf, true).unwrap();
+        assert_eq!(game.get_takeback_request(), None);
+        assert!(game.get_recorder().get_moves().is_empty());
+        assert_eq!(game.get_turn(), Turn::Sheep);
+
+        // The whole capture chain and the following move are taken back.
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        let start = game.get_position_notation();
+        do_move(&mut game, coord!(0, 3), coord!(2, 3));
+        do_move(&mut game, coord!(2, 3), coord!(4, 3));
+        do_move(&mut game, coord!(1, 4), coord!(1, 3));
+        game.request_takeback(Turn::Wolf).unwrap();
+        game.answer_takeback(Turn::Sheep, true).unwrap();
+        assert_eq!(game.get_position_notation(), start);
+        assert!(game.get_recorder().get_moves().is_empty());
+    }
+
+    #[test]
+    fn test_check_state_message() {
+        let game = new_test_game("standard", Some(CHAIN_POSITION));
+        let msg = game.make_state_message();
+        assert!(game.server_check_state_message(&msg).is_ok());
+
+        let turn = msg.get_turn();
+        let with_fields = |pos: Coord, num: u32| {
+            let mut fields = *msg.get_fields();
+            fields[pos.y as usize][pos.x as usize] = num;
+            MsgGameState::new(fields, 0, 0, 0, turn, None, (0, 0))
+        };
+        let with_state = |moving: (u32, u32, u32), turn, chain, result| {
+            let (state, x, y) = moving;
+            MsgGameState::new(*msg.get_fields(), state, x, y, turn, chain, result)
+        };
+
+        // A moving wolf and a capture chain at the wolf on a4.
+        let valid = with_state((1, 0, 3), turn, Some((0, 3)), (0, 0));
+        assert!(game.server_check_state_message(&valid).is_ok());
+
+        for (index, msg) in [
+            // A third wolf.
+            with_fields(coord!(2, 3), field_state_to_num(FieldState::Wolf)),
+            // A missing board position.
+            with_fields(coord!(2, 3), field_state_to_num(FieldState::Unused)),
+            // A position that is not on the board.
+            with_fields(coord!(0, 0), field_state_to_num(FieldState::Empty)),
+            // An unknown field state.
+            with_fields(coord!(2, 3), 9),
+            // A moving wolf at a sheep.
+            with_state((1, 1, 3), turn, None, (0, 0)),
+            // An unknown moving state.
+            with_state((5, 0, 0), turn, None, (0, 0)),
+            // A capture chain at a sheep.
+            with_state((0, 0, 0), turn, Some((1, 3)), (0, 0)),
+            // An unknown turn.
+            with_state((0, 0, 0), 7, None, (0, 0)),
+            // An unknown result or reason.
+            with_state((0, 0, 0), turn, None, (9, 0)),
+            with_state((0, 0, 0), turn, None, (0, 99)),
+        ]
+        .iter()
+        .enumerate()
+        {
+            assert!(game.server_check_state_message(msg).is_err(), "{}", index);
+        }
+    }
 }
 
 // vim: ts=4 sw=4 expandtab
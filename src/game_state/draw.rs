@@ -0,0 +1,185 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use super::{FieldState, GameState, WinReason};
+use crate::board::PosType;
+use crate::print::Print;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The number of repetitions of a position that draw the game.
+const DRAW_REPETITIONS: usize = 3;
+
+/// The state needed to detect a drawn game.
+#[derive(Clone, Default)]
+pub(super) struct DrawState {
+    /// The hashes of all positions since the last capture or barn progress.
+    position_hashes: Vec<u64>,
+    /// The number of moves since the last capture or barn progress.
+    quiet_moves: u32,
+    /// Some, if the game has been drawn.
+    /// The reason is None, if a draw has been received without a reason.
+    draw: Option<Option<WinReason>>,
+}
+
+impl DrawState {
+    /// Get the reason of the draw, if the game has been drawn.
+    /// The reason is None, if it is unknown.
+    pub fn get_draw(&self) -> Option<Option<WinReason>> {
+        self.draw
+    }
+}
+
+impl GameState {
+    /// Calculate the hash of the current position.
+    pub fn position_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.fields.hash(&mut hasher);
+        self.turn.hash(&mut hasher);
+        self.capture_chain.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Get the number of sheep in the barn.
+    pub(super) fn sheep_in_barn(&self) -> usize {
+        self.board()
+            .iter_pos()
+            .filter(|(coord, pos_type)| {
+                *pos_type == PosType::Barn && self.get_field_state(*coord) == FieldState::Sheep
+            })
+            .count()
+    }
+
+    /// Start the draw detection at the current position.
+    pub(super) fn reset_draw_state(&mut self, draw: Option<Option<WinReason>>) {
+        self.draw_state = DrawState {
+            position_hashes: vec![self.position_hash()],
+            quiet_moves: 0,
+            draw,
+        };
+    }
+
+    /// Update the draw detection after a move.
+    /// progress is true, if the move has captured a sheep or a sheep has entered the barn.
+    /// The game is drawn, if the position has been repeated three times or
+    /// if there has been no progress for the number of moves set by the rules.
+    pub(super) fn update_draw_state(&mut self, progress: bool) {
+        if progress {
+            // The previous positions can't be repeated anymore.
+            self.reset_draw_state(None);
+            return;
+        }
+
+        let hash = self.position_hash();
+        let draw_state = &mut self.draw_state;
+        draw_state.position_hashes.push(hash);
+        draw_state.quiet_moves += 1;
+
+        let repetitions = draw_state
+            .position_hashes
+            .iter()
+            .filter(|h| **h == hash)
+            .count();
+        if repetitions >= DRAW_REPETITIONS {
+            Print::debug("Draw by repetition.");
+            draw_state.draw = Some(Some(WinReason::Repetition));
+        } else if self.rules.draw_moves > 0 && draw_state.quiet_moves >= self.rules.draw_moves {
+            Print::debug(&format!(
+                "Draw after {} moves without progress.",
+                draw_state.quiet_moves
+            ));
+            draw_state.draw = Some(Some(WinReason::MoveLimit));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::WinState;
+    use super::*;
+    use crate::coord;
+    use crate::coord::Coord;
+    use crate::game_state::new_test_game;
+
+    fn do_move(game: &mut GameState, from_pos: Coord, to_pos: Coord) {
+        let mov = game
+            .legal_moves()
+            .into_iter()
+            .find(|mov| mov.from_pos == from_pos && mov.to_pos == to_pos)
+            .unwrap();
+        game.make_move(&mov).unwrap();
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = new_test_game("standard", None);
+        do_move(&mut game, coord!(0, 4), coord!(0, 3));
+        for i in 0..2 {
+            do_move(&mut game, coord!(1, 2), coord!(1, 1));
+            do_move(&mut game, coord!(0, 3), coord!(1, 3));
+            do_move(&mut game, coord!(1, 1), coord!(1, 2));
+            assert_eq!(game.get_result(), (WinState::Undecided, None));
+            do_move(&mut game, coord!(1, 3), coord!(0, 3));
+            if i == 0 {
+                assert_eq!(game.get_result(), (WinState::Undecided, None));
+            }
+        }
+        assert_eq!(
+            game.get_result(),
+            (WinState::Draw, Some(WinReason::Repetition))
+        );
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_move_limit() {
+        let moves = [
+            (coord!(0, 4), coord!(0, 3)),
+            (coord!(1, 2), coord!(1, 1)),
+            (coord!(0, 3), coord!(1, 3)),
+            (coord!(1, 1), coord!(1, 2)),
+            (coord!(1, 3), coord!(2, 3)),
+            (coord!(1, 2), coord!(1, 1)),
+        ];
+
+        let mut game = new_test_game("standard,draw-moves=6", None);
+        for (i, (from_pos, to_pos)) in moves.iter().enumerate() {
+            assert_eq!(game.get_result(), (WinState::Undecided, None), "{}", i);
+            do_move(&mut game, *from_pos, *to_pos);
+        }
+        assert_eq!(
+            game.get_result(),
+            (WinState::Draw, Some(WinReason::MoveLimit))
+        );
+
+        // The move limit can be disabled.
+        let mut game = new_test_game("standard,draw-moves=0", None);
+        for (from_pos, to_pos) in moves {
+            do_move(&mut game, from_pos, to_pos);
+        }
+        assert_eq!(game.get_result(), (WinState::Undecided, None));
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut game = new_test_game("standard,draw-moves=3", None);
+        do_move(&mut game, coord!(0, 4), coord!(0, 3));
+        do_move(&mut game, coord!(1, 2), coord!(1, 1));
+        // A sheep entering the barn restarts the move count.
+        do_move(&mut game, coord!(0, 3), coord!(0, 2));
+        do_move(&mut game, coord!(1, 1), coord!(1, 2));
+        do_move(&mut game, coord!(0, 5), coord!(0, 4));
+        assert_eq!(game.get_result(), (WinState::Undecided, None));
+        do_move(&mut game, coord!(1, 2), coord!(1, 1));
+        assert_eq!(
+            game.get_result(),
+            (WinState::Draw, Some(WinReason::MoveLimit))
+        );
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
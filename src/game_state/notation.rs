@@ -0,0 +1,297 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+// One-line position notation.
+//
+// Example (the initial position):
+//
+//   1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s - -
+//
+// The four space separated parts are:
+//
+// - The board rows from top (row 7) to bottom (row 1), separated by '/'.
+//   Each board position is 'W' (wolf), 'S' (sheep) or a digit counting empty positions.
+// - The side to move: 's' (sheep) or 'w' (wolf).
+// - The wolf that has to continue a capture chain (e.g. 'c3') or '-'.
+// - The token that is currently picked up (e.g. 'Wc5') or '-'.
+//
+// Each row lists the positions of that row on the board of the rules, from left to right.
+// Positions use the same a-g / 1-7 names as the recorder.
+
+use super::recorder::{coord_to_recorder_pos, recorder_pos_to_coord};
+use super::{FieldState, GameState, MoveState, Turn};
+use crate::board::{BOARD_HEIGHT, BOARD_WIDTH, Board};
+use crate::coord;
+use crate::coord::{Coord, CoordAxis};
+use anyhow as ah;
+
+type Fields = [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+
+/// Convert the board fields to the rows part of the notation.
+pub fn fields_to_text(board: &Board, fields: &Fields) -> String {
+    let mut text = String::new();
+    for (y, row) in fields.iter().enumerate().take(board.height() as usize) {
+        if y > 0 {
+            text.push('/');
+        }
+        let mut empty = 0;
+        for (x, field) in row.iter().enumerate() {
+            if !board.contains(coord!(x as CoordAxis, y as CoordAxis)) {
+                continue;
+            }
+            let c = match field {
+                FieldState::Unused | FieldState::Empty => {
+                    empty += 1;
+                    continue;
+                }
+                FieldState::Wolf => 'W',
+                FieldState::Sheep => 'S',
+            };
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+                empty = 0;
+            }
+            text.push(c);
+        }
+        if empty > 0 {
+            text.push_str(&empty.to_string());
+        }
+    }
+    text
+}
+
+/// Parse the rows part of the notation to board fields.
+pub fn text_to_fields(board: &Board, text: &str) -> ah::Result<Fields> {
+    let height = board.height() as usize;
+    let rows: Vec<&str> = text.trim().split('/').collect();
+    if rows.len() != height {
+        return Err(ah::format_err!(
+            "Position: Invalid number of rows ({} != {}).",
+            rows.len(),
+            height
+        ));
+    }
+    let mut fields = [[FieldState::Unused; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+    for (y, row) in rows.iter().enumerate() {
+        let mut tokens = vec![];
+        for c in row.chars() {
+            match c {
+                'W' | 'w' => tokens.push(FieldState::Wolf),
+                'S' | 's' => tokens.push(FieldState::Sheep),
+                '1'..='9' => {
+                    let count = c.to_digit(10).unwrap_or(0) as usize;
+                    tokens.extend(std::iter::repeat_n(FieldState::Empty, count));
+                }
+                c => {
+                    return Err(ah::format_err!(
+                        "Position: Invalid character '{}' in row {}.",
+                        c,
+                        height - y
+                    ));
+                }
+            }
+        }
+        let mut tokens = tokens.into_iter();
+        for x in 0..board.width() {
+            if board.contains(coord!(x, y as CoordAxis)) {
+                match tokens.next() {
+                    Some(token) => fields[y][x as usize] = token,
+                    None => {
+                        return Err(ah::format_err!(
+                            "Position: Too few positions in row {}.",
+                            height - y
+                        ));
+                    }
+                }
+            }
+        }
+        if tokens.next().is_some() {
+            return Err(ah::format_err!(
+                "Position: Too many positions in row {}.",
+                height - y
+            ));
+        }
+    }
+    Ok(fields)
+}
+
+fn text_to_pos(text: &str) -> ah::Result<Coord> {
+    let chars: Vec<char> = text.chars().collect();
+    recorder_pos_to_coord(&chars)
+}
+
+impl GameState {
+    /// Get the current position in the one-line text notation.
+    pub fn get_position_notation(&self) -> String {
+        let turn = match self.turn {
+            Turn::Sheep => "s",
+            Turn::Wolf => "w",
+        };
+        let chain = match self.capture_chain {
+            Some(pos) => coord_to_recorder_pos(&pos),
+            None => "-".to_string(),
+        };
+        let moving = match self.moving {
+            MoveState::NoMove => "-".to_string(),
+            MoveState::Wolf(pos) => format!("W{}", coord_to_recorder_pos(&pos)),
+            MoveState::Sheep(pos) => format!("S{}", coord_to_recorder_pos(&pos)),
+        };
+        format!(
+            "{} {} {} {}",
+            fields_to_text(self.board(), &self.fields),
+            turn,
+            chain,
+            moving
+        )
+    }
+
+    /// Set up a position from the one-line text notation.
+    /// The capture chain and the moving token parts may be omitted.
+    /// The move history is cleared.
+    pub fn set_position_notation(&mut self, text: &str) -> ah::Result<()> {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        if parts.len() < 2 || parts.len() > 4 {
+            return Err(ah::format_err!(
+                "Position: Expected 2 to 4 parts, but got {}.",
+                parts.len()
+            ));
+        }
+
+        let fields = text_to_fields(self.board(), parts[0])?;
+        let turn = match parts[1] {
+            "s" | "S" => Turn::Sheep,
+            "w" | "W" => Turn::Wolf,
+            t => return Err(ah::format_err!("Position: Invalid side to move: {}", t)),
+        };
+        let field_at = |pos: Coord| fields[pos.y as usize][pos.x as usize];
+
+        let capture_chain = match parts.get(2) {
+            None | Some(&"-") => None,
+            Some(chain) => {
+                let pos = text_to_pos(chain)?;
+                if turn != Turn::Wolf || field_at(pos) != FieldState::Wolf {
+                    return Err(ah::format_err!(
+                        "Position: Capture chain {} is not a wolf to move.",
+                        chain
+                    ));
+                }
+                Some(pos)
+            }
+        };
+
+        let moving = match parts.get(3) {
+            None | Some(&"-") => MoveState::NoMove,
+            Some(moving) => {
+                let (move_state, token) = match moving.chars().next() {
+                    Some('W') | Some('w') => {
+                        (MoveState::Wolf as fn(Coord) -> MoveState, FieldState::Wolf)
+                    }
+                    Some('S') | Some('s') => (
+                        MoveState::Sheep as fn(Coord) -> MoveState,
+                        FieldState::Sheep,
+                    ),
+                    _ => {
+                        return Err(ah::format_err!(
+                            "Position: Invalid moving token: {}",
+                            moving
+                        ));
+                    }
+                };
+                let pos = text_to_pos(&moving[1..])?;
+                if field_at(pos) != token {
+                    return Err(ah::format_err!(
+                        "Position: Moving token {} is not on the board.",
+                        moving
+                    ));
+                }
+                move_state(pos)
+            }
+        };
+
+        self.check_position(&fields)?;
+        self.set_start_position(fields, turn);
+        self.capture_chain = capture_chain;
+        self.moving = moving;
+        self.reset_draw_state(None);
+        // Send the local game state to the server (if any).
+        self.client_send_full_gamestate()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::new_test_game;
+
+    const INITIAL: &str = "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s - -";
+
+    #[test]
+    fn test_initial_position() {
+        let game = new_test_game("standard", None);
+        assert_eq!(game.get_position_notation(), INITIAL);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for rules in ["standard", "fox-and-geese", "asalto"] {
+            let mut game = new_test_game(rules, None);
+            for _ in 0..6 {
+                let mov = game.legal_moves()[0];
+                game.make_move(&mov).unwrap();
+            }
+            let text = game.get_position_notation();
+
+            let copy = new_test_game(rules, Some(&text));
+            assert_eq!(copy.get_position_notation(), text);
+            assert_eq!(copy.fields, game.fields);
+            assert_eq!(copy.turn, game.turn);
+        }
+    }
+
+    #[test]
+    fn test_optional_parts() {
+        let mut game = new_test_game("standard", None);
+        game.set_position_notation("1/3/1W1W1/5/SSSSS/SSSSS/SSSSS w")
+            .unwrap();
+        assert_eq!(
+            game.get_position_notation(),
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS w - -"
+        );
+
+        let text = "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS w b5 Wb5";
+        game.set_position_notation(text).unwrap();
+        assert_eq!(game.get_position_notation(), text);
+        assert_eq!(game.capture_chain, Some(coord!(1, 2)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut game = new_test_game("standard", None);
+        for text in [
+            "",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s - - x",
+            "1/3/1W1W1/5/SSSSS/SSSSS s",
+            "1/3/1X1W1/5/SSSSS/SSSSS/SSSSS s",
+            "1/3/1W1W1/4/SSSSS/SSSSS/SSSSS s",
+            "1/3/1W1W1/6/SSSSS/SSSSS/SSSSS s",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS x",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s b5",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS w a1",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS w z9",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s - Wa1",
+            "1/3/1W1W1/5/SSSSS/SSSSS/SSSSS s - Xa1",
+        ] {
+            assert!(game.set_position_notation(text).is_err(), "{}", text);
+        }
+        // A failed attempt leaves the position unchanged.
+        assert_eq!(game.get_position_notation(), INITIAL);
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
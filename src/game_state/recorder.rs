@@ -5,20 +5,20 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
-use super::{MoveState, WinState};
-use crate::board::{BOARD_HEIGHT, BOARD_WIDTH, coord_is_on_board};
+use super::{MoveState, WinReason, WinState};
+use crate::board::{BOARD_HEIGHT, BOARD_WIDTH};
 use crate::coord;
 use crate::coord::{Coord, CoordAxis};
 use anyhow as ah;
 
-const X_NAMES: [char; BOARD_WIDTH as usize] = ['a', 'b', 'c', 'd', 'e'];
+const X_NAMES: [char; BOARD_WIDTH as usize] = ['a', 'b', 'c', 'd', 'e', 'f', 'g'];
 const Y_NAMES: [char; BOARD_HEIGHT as usize] = ['7', '6', '5', '4', '3', '2', '1'];
 
-fn coord_to_recorder_pos(pos: &Coord) -> String {
+pub fn coord_to_recorder_pos(pos: &Coord) -> String {
     format!("{}{}", X_NAMES[pos.x as usize], Y_NAMES[pos.y as usize])
 }
 
-fn recorder_pos_to_coord(chars: &[char]) -> ah::Result<Coord> {
+pub fn recorder_pos_to_coord(chars: &[char]) -> ah::Result<Coord> {
     if chars.len() != 2 {
         return Err(ah::format_err!(
             "Recorder position: Invalid size ({} != 2).",
@@ -45,15 +45,37 @@ fn recorder_pos_to_coord(chars: &[char]) -> ah::Result<Coord> {
             ));
         }
     };
-    let pos = coord!(x as CoordAxis, y as CoordAxis);
-    if !coord_is_on_board(pos) {
-        return Err(ah::format_err!(
-            "Recorder position: Position {}{} is not on the board.",
-            chars[0],
-            chars[1]
-        ));
-    }
-    Ok(pos)
+    Ok(coord!(x as CoordAxis, y as CoordAxis))
+}
+
+/// The prefix of the result line at the end of a move record.
+const RESULT_PREFIX: &str = "Result:";
+
+/// Format the result line of a move record (e.g. "Result: sheep (barn filled)").
+pub fn result_to_recorder_line(win_state: WinState, reason: WinReason) -> String {
+    format!("{} {} ({})", RESULT_PREFIX, win_state, reason)
+}
+
+/// Parse the result line of a move record.
+/// Returns None, if the line is not a result line.
+pub fn recorder_line_to_result(line: &str) -> ah::Result<Option<(WinState, WinReason)>> {
+    let Some(result) = line.trim().strip_prefix(RESULT_PREFIX) else {
+        return Ok(None);
+    };
+    let Some((win_state, reason)) = result
+        .trim()
+        .strip_suffix(')')
+        .and_then(|r| r.split_once('('))
+    else {
+        return Err(ah::format_err!("Invalid result line: {}", line.trim()));
+    };
+    let win_state = match win_state.trim() {
+        "wolf" => WinState::Wolf,
+        "sheep" => WinState::Sheep,
+        "draw" => WinState::Draw,
+        w => return Err(ah::format_err!("Invalid game result: {}", w)),
+    };
+    Ok(Some((win_state, WinReason::from_text(reason)?)))
 }
 
 /// One recorded game move.
@@ -70,7 +92,11 @@ pub struct RecordedMove {
 
 impl RecordedMove {
     /// Parse a move record line string.
-    fn parse_log_line(line: &str) -> ah::Result<RecordedMove> {
+    /// A capture chain is recorded as one compound move (e.g. Wc5xc3xe3).
+    /// It is returned as one recorded move per capture.
+    /// A move that decides the game is followed by the result:
+    /// #w (the wolves win), #s (the sheep win) or = (draw).
+    pub fn parse_log_line(line: &str) -> ah::Result<Vec<RecordedMove>> {
         let chars: Vec<char> = line.chars().collect();
         let mut offset = 0;
 
@@ -91,10 +117,52 @@ impl RecordedMove {
         if chars[offset..].len() < 2 {
             return Err(ah::format_err!("Recorder log: No from-position."));
         }
-        let from_pos = recorder_pos_to_coord(&chars[offset..offset + 2])?;
-        let move_state = move_state_type(from_pos);
+        let mut from_pos = recorder_pos_to_coord(&chars[offset..offset + 2])?;
         offset += 2;
 
+        let mut moves = vec![];
+        loop {
+            let move_state = move_state_type(from_pos);
+            let mut recorded_move = Self::parse_log_step(&chars[offset..], move_state)?;
+            if !moves.is_empty() && !recorded_move.captured {
+                return Err(ah::format_err!(
+                    "Recorder log: Only a capture can continue a move."
+                ));
+            }
+            offset += 3;
+
+            // Game result.
+            let (win_state, len) = match chars[offset..] {
+                ['#', 'w' | 'W', ..] => (WinState::Wolf, 2),
+                ['#', 's' | 'S', ..] => (WinState::Sheep, 2),
+                ['=', ..] => (WinState::Draw, 1),
+                _ => (recorded_move.win_state, 0),
+            };
+            recorded_move.win_state = win_state;
+            offset += len;
+
+            from_pos = recorded_move.to_pos;
+            let continues = recorded_move.captured
+                && recorded_move.win_state == WinState::Undecided
+                && offset < chars.len();
+            moves.push(recorded_move);
+            if !continues {
+                break;
+            }
+        }
+        if offset < chars.len() {
+            return Err(ah::format_err!(
+                "Recorder log: Unexpected characters after the move: {}",
+                chars[offset..].iter().collect::<String>()
+            ));
+        }
+        Ok(moves)
+    }
+
+    /// Parse the move type and to-position of one move step.
+    fn parse_log_step(chars: &[char], move_state: MoveState) -> ah::Result<RecordedMove> {
+        let mut offset = 0;
+
         // Move type.
         if chars[offset..].is_empty() {
             return Err(ah::format_err!("Recorder log: No move type."));
@@ -102,6 +170,7 @@ impl RecordedMove {
         let (win_state, captured) = match chars[offset] {
             '-' => (WinState::Undecided, false),
             'X' | 'x' => (WinState::Undecided, true),
+            // Old format of a move that has decided the game.
             '#' => match move_state {
                 MoveState::Wolf(_) => (WinState::Wolf, true),
                 MoveState::Sheep(_) => (WinState::Sheep, false),
@@ -129,6 +198,36 @@ impl RecordedMove {
             win_state,
         })
     }
+
+    /// Format the move as continuation of a compound move (e.g. xe3).
+    pub fn to_continuation_string(&self) -> String {
+        let move_type = if self.captured { "x" } else { "-" };
+        let result = match self.win_state {
+            WinState::Undecided => "",
+            WinState::Wolf => "#w",
+            WinState::Sheep => "#s",
+            WinState::Draw => "=",
+        };
+        format!(
+            "{}{}{}",
+            move_type,
+            &coord_to_recorder_pos(&self.to_pos),
+            result
+        )
+    }
+
+    /// Format the recorded moves of one capture chain as one compound move.
+    fn to_compound_string(moves: &[RecordedMove]) -> String {
+        let mut text = String::new();
+        for (i, mov) in moves.iter().enumerate() {
+            if i == 0 {
+                text.push_str(&mov.to_string());
+            } else {
+                text.push_str(&mov.to_continuation_string());
+            }
+        }
+        text
+    }
 }
 
 impl std::fmt::Display for RecordedMove {
@@ -138,38 +237,38 @@ impl std::fmt::Display for RecordedMove {
             MoveState::Wolf(from_pos) => ("W", from_pos),
             MoveState::Sheep(from_pos) => ("S", from_pos),
         };
-        let move_type = match self.win_state {
-            WinState::Undecided => {
-                if self.captured {
-                    "x"
-                } else {
-                    "-"
-                }
-            }
-            WinState::Wolf | WinState::Sheep => "#",
-        };
         write!(
             f,
-            "{}{}{}{}",
+            "{}{}{}",
             from_type,
             &coord_to_recorder_pos(&from_pos),
-            move_type,
-            &coord_to_recorder_pos(&self.to_pos)
+            &self.to_continuation_string()
         )
     }
 }
 
+#[derive(Clone)]
 pub struct Recorder {
     move_log: Vec<String>,
+    result: Option<(WinState, WinReason)>,
 }
 
 impl Recorder {
     pub fn new() -> Recorder {
-        Recorder { move_log: vec![] }
+        Recorder {
+            move_log: vec![],
+            result: None,
+        }
     }
 
     pub fn reset(&mut self) {
         self.move_log.clear();
+        self.result = None;
+    }
+
+    /// Set the result of the recorded game and the reason why it has been decided.
+    pub fn set_result(&mut self, result: Option<(WinState, WinReason)>) {
+        self.result = result;
     }
 
     /// Add a move to the move record.
@@ -177,24 +276,155 @@ impl Recorder {
         self.move_log.push(recorded_move.to_string());
     }
 
+    /// Add a capture to the last move of the move record.
+    /// The last move becomes a compound move (e.g. Wc5xc3xe3).
+    pub fn continue_move(&mut self, recorded_move: &RecordedMove) {
+        match self.move_log.last_mut() {
+            Some(last) => last.push_str(&recorded_move.to_continuation_string()),
+            None => self.record_move(recorded_move),
+        }
+    }
+
+    /// Remove the last move step from the move record.
+    /// The last capture of a compound move is removed from the compound move.
+    pub fn pop_move(&mut self) -> Option<String> {
+        let last = self.move_log.last_mut()?;
+        if let Ok(mut moves) = RecordedMove::parse_log_line(last)
+            && moves.len() > 1
+        {
+            let step = moves.pop()?;
+            *last = RecordedMove::to_compound_string(&moves);
+            Some(step.to_continuation_string())
+        } else {
+            self.move_log.pop()
+        }
+    }
+
+    /// Add an already formatted move step to the move record.
+    /// A continuation step (e.g. xe3) is added to the last compound move.
+    pub fn push_move(&mut self, move_text: String) {
+        match self.move_log.last_mut() {
+            Some(last) if move_text.starts_with(['x', 'X']) => last.push_str(&move_text),
+            _ => self.move_log.push(move_text),
+        }
+    }
+
     pub fn get_moves(&self) -> &Vec<String> {
         &self.move_log
     }
 
+    /// Get the moves and the result line of the game.
     pub fn get_moves_as_text(&self) -> String {
-        self.get_moves().join("\n")
+        let mut lines = self.get_moves().clone();
+        if let Some((win_state, reason)) = self.result {
+            lines.push(result_to_recorder_line(win_state, reason));
+        }
+        lines.join("\n")
     }
 
     pub fn parse_text(&mut self, text: &str) -> ah::Result<()> {
         self.reset();
         for line in text.split("\n").map(|l| l.trim()) {
-            if !line.is_empty() {
-                let mov = RecordedMove::parse_log_line(line)?;
-                self.move_log.push(mov.to_string());
+            if let Some(result) = recorder_line_to_result(line)? {
+                self.result = Some(result);
+            } else if !line.is_empty() {
+                let moves = RecordedMove::parse_log_line(line)?;
+                self.move_log.push(RecordedMove::to_compound_string(&moves));
             }
         }
         Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::super::WIN_REASONS;
+    use super::*;
+
+    #[test]
+    fn test_pos() {
+        for coord in [coord!(0, 0), coord!(3, 4), coord!(6, 6)] {
+            let text: Vec<char> = coord_to_recorder_pos(&coord).chars().collect();
+            assert_eq!(recorder_pos_to_coord(&text).unwrap(), coord);
+        }
+        assert_eq!(coord_to_recorder_pos(&coord!(0, 6)), "a1");
+        assert_eq!(coord_to_recorder_pos(&coord!(6, 0)), "g7");
+        assert!(recorder_pos_to_coord(&['h', '1']).is_err());
+        assert!(recorder_pos_to_coord(&['a', '8']).is_err());
+        assert!(recorder_pos_to_coord(&['a']).is_err());
+    }
+
+    #[test]
+    fn test_parse_log_line() {
+        let moves = RecordedMove::parse_log_line("Sa3-a4").unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].move_state, MoveState::Sheep(coord!(0, 4)));
+        assert_eq!(moves[0].to_pos, coord!(0, 3));
+        assert!(!moves[0].captured);
+        assert_eq!(moves[0].win_state, WinState::Undecided);
+
+        let moves = RecordedMove::parse_log_line("Wa4xc4xe4#w").unwrap();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].move_state, MoveState::Wolf(coord!(0, 3)));
+        assert_eq!(moves[1].move_state, MoveState::Wolf(coord!(2, 3)));
+        assert_eq!(moves[1].to_pos, coord!(4, 3));
+        assert!(moves.iter().all(|m| m.captured));
+        assert_eq!(moves[0].win_state, WinState::Undecided);
+        assert_eq!(moves[1].win_state, WinState::Wolf);
+        assert_eq!(RecordedMove::to_compound_string(&moves), "Wa4xc4xe4#w");
+
+        // Old format of a move that has decided the game.
+        let moves = RecordedMove::parse_log_line("Sb5#a5").unwrap();
+        assert_eq!(moves[0].win_state, WinState::Sheep);
+
+        for line in [
+            "",
+            "X",
+            "Sa3",
+            "Sa3a4",
+            "Sa3-a",
+            "Sa3-a4x",
+            "Wa4-c4xe4",
+            "Wa4#c4xe4",
+        ] {
+            assert!(RecordedMove::parse_log_line(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_result_line() {
+        for reason in WIN_REASONS {
+            for win_state in [WinState::Wolf, WinState::Sheep, WinState::Draw] {
+                let line = result_to_recorder_line(win_state, reason);
+                assert_eq!(
+                    recorder_line_to_result(&line).unwrap(),
+                    Some((win_state, reason))
+                );
+            }
+        }
+        assert_eq!(recorder_line_to_result("Sa3-a4").unwrap(), None);
+        assert!(recorder_line_to_result("Result: wolf").is_err());
+        assert!(recorder_line_to_result("Result: nobody (resignation)").is_err());
+        assert!(recorder_line_to_result("Result: wolf (bored)").is_err());
+    }
+
+    #[test]
+    fn test_recorder() {
+        let mut recorder = Recorder::new();
+        recorder.push_move("Wa4xc4".to_string());
+        recorder.push_move("xe4".to_string());
+        recorder.push_move("Sb3-b4".to_string());
+        assert_eq!(recorder.get_moves(), &vec!["Wa4xc4xe4", "Sb3-b4"]);
+        recorder.set_result(Some((WinState::Draw, WinReason::AgreedDraw)));
+        let text = recorder.get_moves_as_text();
+        assert_eq!(text, "Wa4xc4xe4\nSb3-b4\nResult: draw (agreed draw)");
+
+        let mut parsed = Recorder::new();
+        parsed.parse_text(&text).unwrap();
+        assert_eq!(parsed.get_moves_as_text(), text);
+        assert_eq!(parsed.pop_move().as_deref(), Some("Sb3-b4"));
+        assert_eq!(parsed.pop_move().as_deref(), Some("xe4"));
+    }
+}
+
 // vim: ts=4 sw=4 expandtab
@@ -0,0 +1,234 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use super::recorder::{RecordedMove, recorder_line_to_result};
+use super::{FieldState, GameState, MoveState, WinReason, WinState};
+use anyhow as ah;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::path::Path;
+
+impl GameState {
+    /// Load a recorded game from a text file and replay it.
+    pub fn load_record(&mut self, filename: &Path) -> ah::Result<()> {
+        let mut file = OpenOptions::new().read(true).open(filename)?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        self.replay_record(&text)
+    }
+
+    /// Rebuild the game by replaying a recorded move log.
+    /// The replay starts from the initial position and each move is checked
+    /// against the game rules.
+    /// On error the game is left at the position before the first illegal move.
+    pub fn replay_record(&mut self, text: &str) -> ah::Result<()> {
+        if self.client_is_connected() {
+            return Err(ah::format_err!(
+                "Cannot replay a recorded game while connected to a server."
+            ));
+        }
+        self.reset_game(true);
+        self.replay_moves(text)
+    }
+
+    /// Replay a recorded move log, starting at the current position.
+    /// A capture chain that is not continued by the next recorded move
+    /// has been ended by the wolf player.
+    /// A result line at the end of the log declares or checks the game result.
+    pub(super) fn replay_moves(&mut self, text: &str) -> ah::Result<()> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let line_number = index + 1;
+            if let Some((win_state, reason)) = recorder_line_to_result(line)
+                .map_err(|e| ah::format_err!("Line {}: {}", line_number, e))?
+            {
+                self.replay_result(win_state, reason)
+                    .map_err(|e| ah::format_err!("Line {}: {}", line_number, e))?;
+                continue;
+            }
+            let recorded_moves = RecordedMove::parse_log_line(line)
+                .map_err(|e| ah::format_err!("Line {}: {}", line_number, e))?;
+            for recorded_move in &recorded_moves {
+                self.replay_move(recorded_move).map_err(|e| {
+                    ah::format_err!("Line {}: Illegal move '{}': {}", line_number, line, e)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the recorded game result.
+    /// A resignation, timeout or agreed draw is declared.
+    /// Any other result must match the replayed game.
+    fn replay_result(&mut self, win_state: WinState, reason: WinReason) -> ah::Result<()> {
+        if reason.is_declared() {
+            return self.declare_result(win_state, reason);
+        }
+        let (actual_win_state, actual_reason) = self.get_result();
+        if actual_win_state != win_state || actual_reason != Some(reason) {
+            return Err(ah::format_err!(
+                "The recorded game result ({} ({})) does not match the actual result ({}).",
+                win_state,
+                reason,
+                actual_win_state
+            ));
+        }
+        Ok(())
+    }
+
+    /// Apply one recorded move to the game.
+    fn replay_move(&mut self, recorded_move: &RecordedMove) -> ah::Result<()> {
+        let (from_pos, token) = match recorded_move.move_state {
+            MoveState::NoMove => return Err(ah::format_err!("No token is moved.")),
+            MoveState::Wolf(pos) => (pos, FieldState::Wolf),
+            MoveState::Sheep(pos) => (pos, FieldState::Sheep),
+        };
+
+        if let Some(chain_pos) = self.get_capture_chain()
+            && (chain_pos != from_pos || !recorded_move.captured)
+        {
+            self.end_capture_chain()?;
+        }
+
+        let win_state = self.get_win_state();
+        if win_state != WinState::Undecided {
+            return Err(ah::format_err!(
+                "The game is already decided: {}",
+                win_state
+            ));
+        }
+        if self.get_field_state(from_pos) != token {
+            return Err(ah::format_err!("The moved token is not on the board."));
+        }
+        let mov = match self
+            .legal_moves_from(from_pos)
+            .into_iter()
+            .find(|mov| mov.to_pos == recorded_move.to_pos)
+        {
+            Some(mov) => mov,
+            None => {
+                return Err(ah::format_err!(
+                    "The move is against the rules. Next turn is: {}",
+                    self.get_turn()
+                ));
+            }
+        };
+        if mov.capture_pos.is_some() != recorded_move.captured {
+            return Err(ah::format_err!(
+                "The recorded capture does not match the move."
+            ));
+        }
+
+        self.make_move(&mov)?;
+
+        let win_state = self.get_win_state();
+        if win_state != recorded_move.win_state {
+            return Err(ah::format_err!(
+                "The recorded game result ({}) does not match the actual result ({}).",
+                recorded_move.win_state,
+                win_state
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::new_test_game;
+
+    /// A wolf on a4 that can capture the sheep on b4 and d4.
+    const CHAIN_POSITION: &str = "W/3/5/WS1S1/SSS2/SSSSS/SSSSS w";
+
+    #[test]
+    fn test_replay_record() {
+        let mut game = new_test_game("standard", None);
+        for _ in 0..8 {
+            let mov = game.legal_moves()[0];
+            game.make_move(&mov).unwrap();
+        }
+        let text = game.get_recorder().get_moves_as_text();
+
+        let mut replayed = new_test_game("standard", None);
+        replayed.replay_record(&text).unwrap();
+        assert_eq!(replayed.fields, game.fields);
+        assert_eq!(
+            replayed.get_recorder().get_moves(),
+            game.get_recorder().get_moves()
+        );
+    }
+
+    #[test]
+    fn test_capture_chain() {
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        game.replay_moves("Wa4xc4xe4").unwrap();
+        assert_eq!(
+            game.get_position_notation(),
+            "W/3/5/4W/SSS2/SSSSS/SSSSS s - -"
+        );
+        assert_eq!(game.get_recorder().get_moves(), &vec!["Wa4xc4xe4"]);
+
+        // A chain that is not continued has been ended.
+        let mut game = new_test_game("standard", Some(CHAIN_POSITION));
+        game.replay_moves("Wa4xc4\nSb3-b4").unwrap();
+        assert_eq!(
+            game.get_position_notation(),
+            "W/3/5/1SWS1/S1S2/SSSSS/SSSSS w - -"
+        );
+        assert_eq!(game.get_recorder().get_moves(), &vec!["Wa4xc4", "Sb3-b4"]);
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut game = new_test_game("standard", None);
+        game.replay_record("Sb3-b4\nWb5xb3").unwrap();
+        assert_eq!(game.get_stats().sheep_captured, 1);
+        assert_eq!(game.get_recorder().get_moves(), &vec!["Sb3-b4", "Wb5xb3"]);
+
+        // The capture must be recorded.
+        let mut game = new_test_game("standard", None);
+        assert!(game.replay_record("Sb3-b4\nWb5-b3").is_err());
+    }
+
+    #[test]
+    fn test_result() {
+        let mut game = new_test_game("standard", None);
+        game.replay_record("Sa3-a4\nResult: sheep (resignation)")
+            .unwrap();
+        assert_eq!(
+            game.get_result(),
+            (WinState::Sheep, Some(WinReason::Resignation))
+        );
+
+        // A result that can be seen on the board must match.
+        let mut game = new_test_game("standard", None);
+        assert!(
+            game.replay_record("Sa3-a4\nResult: sheep (barn filled)")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_illegal_move() {
+        let mut game = new_test_game("standard", None);
+        let err = game.replay_record("Sa3-a4\nWc5-c1\nSa2-a3").unwrap_err();
+        assert!(err.to_string().starts_with("Line 2:"), "{}", err);
+        // The game stops before the illegal move.
+        assert_eq!(game.get_recorder().get_moves(), &vec!["Sa3-a4"]);
+
+        let mut game = new_test_game("standard", None);
+        assert!(game.replay_record("Sa3xa4").is_err());
+        assert!(game.replay_record("Wa3-a4").is_err());
+        assert!(game.replay_record("Sa3#a4").is_err());
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
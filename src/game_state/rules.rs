@@ -0,0 +1,344 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use super::FieldState;
+use crate::board::{BOARD_HEIGHT, BOARD_WIDTH, Board, BoardType, PosType};
+use crate::coord;
+use crate::coord::Coord;
+use anyhow as ah;
+
+/// The names of all built-in rule variants.
+pub const RULE_SET_NAMES: [&str; 7] = [
+    "standard",
+    "twenty-sheep",
+    "forward-sheep",
+    "compulsory",
+    "huffing",
+    "fox-and-geese",
+    "asalto",
+];
+
+/// What happens, if a wolf could capture a sheep, but does not.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CaptureRule {
+    /// Capturing is optional.
+    Optional,
+    /// A quiet wolf move is rejected, if any wolf can capture.
+    Compulsory,
+    /// A quiet wolf move is allowed, if any wolf can capture,
+    /// but a wolf that could have captured is removed from the board ("huffed").
+    Huffing,
+}
+
+/// What happens, if the side to move has no legal move.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BlockedRule {
+    /// The blocked side loses the game.
+    Loss,
+    /// The blocked side passes and the other side moves again.
+    /// The game is a draw, if both sides are blocked.
+    Pass,
+    /// The game is a draw.
+    Draw,
+}
+
+impl BlockedRule {
+    fn from_name(name: &str) -> ah::Result<BlockedRule> {
+        match name {
+            "loss" => Ok(BlockedRule::Loss),
+            "pass" => Ok(BlockedRule::Pass),
+            "draw" => Ok(BlockedRule::Draw),
+            name => Err(ah::format_err!(
+                "Invalid blocked rule '{}'. May be loss, pass or draw.",
+                name
+            )),
+        }
+    }
+}
+
+/// The rules of one game variant.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RuleSet {
+    /// The name of the rule variant.
+    pub name: String,
+    /// The board layout.
+    pub board: BoardType,
+    /// The number of wolves at the start of the game (1 or 2).
+    pub wolves: u8,
+    /// The number of sheep at the start of the game.
+    pub sheep: u8,
+    /// The wolves win, if fewer sheep than this are left on the board.
+    pub capture_threshold: u8,
+    /// Whether a wolf that can capture a sheep must capture.
+    pub capture_rule: CaptureRule,
+    /// Sheep may move horizontally.
+    pub sheep_sideways: bool,
+    /// Sheep may move diagonally forward, even if they could move straight forward.
+    pub sheep_diagonal: bool,
+    /// What happens, if the side to move is blocked.
+    pub blocked_rule: BlockedRule,
+    /// The game is drawn after this number of moves without a capture
+    /// or a sheep entering the barn. 0 disables this draw rule.
+    pub draw_moves: u32,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            name: "standard".to_string(),
+            board: BoardType::Muehle,
+            wolves: 2,
+            sheep: 15,
+            capture_threshold: 9,
+            capture_rule: CaptureRule::Optional,
+            sheep_sideways: true,
+            sheep_diagonal: false,
+            blocked_rule: BlockedRule::Loss,
+            draw_moves: 0,
+        }
+    }
+}
+
+impl RuleSet {
+    /// Get one of the built-in rule variants by name.
+    /// The name may be followed by comma separated rule options,
+    /// for example "standard,blocked=draw".
+    pub fn from_name(name: &str) -> ah::Result<RuleSet> {
+        let name = name.to_lowercase();
+        let mut parts = name.split(',').map(|p| p.trim());
+        let standard = RuleSet::default();
+        let mut rules = match parts.next().unwrap_or_default() {
+            "standard" => standard,
+            "twenty-sheep" => RuleSet {
+                name: "twenty-sheep".to_string(),
+                sheep: 20,
+                ..standard
+            },
+            "forward-sheep" => RuleSet {
+                name: "forward-sheep".to_string(),
+                sheep_sideways: false,
+                ..standard
+            },
+            "compulsory" => RuleSet {
+                name: "compulsory".to_string(),
+                capture_rule: CaptureRule::Compulsory,
+                ..standard
+            },
+            "huffing" => RuleSet {
+                name: "huffing".to_string(),
+                capture_rule: CaptureRule::Huffing,
+                ..standard
+            },
+            "fox-and-geese" => RuleSet {
+                name: "fox-and-geese".to_string(),
+                board: BoardType::Cross,
+                wolves: 1,
+                sheep: 13,
+                capture_threshold: 6,
+                ..standard
+            },
+            "asalto" => RuleSet {
+                name: "asalto".to_string(),
+                board: BoardType::Fortress,
+                wolves: 2,
+                sheep: 24,
+                capture_threshold: 9,
+                capture_rule: CaptureRule::Huffing,
+                sheep_diagonal: true,
+                ..standard
+            },
+            name => {
+                return Err(ah::format_err!(
+                    "Unknown rule variant '{}'. Available variants: {}",
+                    name,
+                    RULE_SET_NAMES.join(", ")
+                ));
+            }
+        };
+        for option in parts {
+            rules.set_option(option)?;
+        }
+        rules.check()?;
+        Ok(rules)
+    }
+
+    /// Apply one "key=value" rule option.
+    /// The option is appended to the name of the rules.
+    fn set_option(&mut self, option: &str) -> ah::Result<()> {
+        let Some((key, value)) = option.split_once('=') else {
+            return Err(ah::format_err!(
+                "Invalid rule option '{}'. Expected key=value.",
+                option
+            ));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match key {
+            "blocked" => self.blocked_rule = BlockedRule::from_name(value)?,
+            "draw-moves" => {
+                self.draw_moves = value
+                    .parse()
+                    .map_err(|_| ah::format_err!("Invalid number of draw moves '{}'.", value))?
+            }
+            key => {
+                return Err(ah::format_err!(
+                    "Unknown rule option '{}'. Available options: blocked, draw-moves",
+                    key
+                ));
+            }
+        }
+        self.name = format!("{},{}={}", self.name, key, value);
+        Ok(())
+    }
+
+    /// Get the board of these rules.
+    pub fn board(&self) -> &'static Board {
+        self.board.board()
+    }
+
+    /// Check if the rules can be played on the board.
+    pub fn check(&self) -> ah::Result<()> {
+        if self.wolves < 1 || self.wolves > 2 {
+            return Err(ah::format_err!(
+                "Rules '{}': The number of wolves must be 1 or 2.",
+                self.name
+            ));
+        }
+        let wolf_start = self.board().wolf_start(self.wolves);
+        let fields = self
+            .board()
+            .iter_pos()
+            .filter(|(coord, pos_type)| *pos_type == PosType::Field && !wolf_start.contains(coord))
+            .count();
+        if self.sheep < 1 || self.sheep as usize > fields {
+            return Err(ah::format_err!(
+                "Rules '{}': The number of sheep must be between 1 and {}.",
+                self.name,
+                fields
+            ));
+        }
+        if self.capture_threshold < 1 || self.capture_threshold > self.sheep {
+            return Err(ah::format_err!(
+                "Rules '{}': The capture threshold must be between 1 and the number of sheep.",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the start position of these rules.
+    /// The wolves start at the start positions of the board and the sheep fill
+    /// the fields of the board from the bottom row upwards.
+    pub fn initial_fields(&self) -> [[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize] {
+        let board = self.board();
+        let mut fields = [[FieldState::Unused; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+        for coord in board.iter() {
+            fields[coord.y as usize][coord.x as usize] = FieldState::Empty;
+        }
+
+        for pos in board.wolf_start(self.wolves) {
+            fields[pos.y as usize][pos.x as usize] = FieldState::Wolf;
+        }
+
+        let mut sheep = self.sheep;
+        'rows: for y in (0..board.height()).rev() {
+            for x in 0..board.width() {
+                if sheep == 0 {
+                    break 'rows;
+                }
+                let (xu, yu) = (x as usize, y as usize);
+                if board.pos_type(coord!(x, y)) == PosType::Field
+                    && fields[yu][xu] == FieldState::Empty
+                {
+                    fields[yu][xu] = FieldState::Sheep;
+                    sheep -= 1;
+                }
+            }
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        for name in RULE_SET_NAMES {
+            let rules = RuleSet::from_name(name).unwrap();
+            assert_eq!(rules.name, name);
+
+            // The start position has all tokens of the rules.
+            let fields = rules.initial_fields();
+            let count = |state| {
+                rules
+                    .board()
+                    .iter()
+                    .filter(|c| fields[c.y as usize][c.x as usize] == state)
+                    .count()
+            };
+            assert_eq!(count(FieldState::Wolf), rules.wolves as usize, "{}", name);
+            assert_eq!(count(FieldState::Sheep), rules.sheep as usize, "{}", name);
+        }
+
+        assert_eq!(RuleSet::from_name("standard").unwrap().draw_moves, 0);
+        let rules = RuleSet::from_name(" Huffing , blocked=pass, draw-moves=50").unwrap();
+        assert_eq!(rules.name, "huffing,blocked=pass,draw-moves=50");
+        assert_eq!(rules.capture_rule, CaptureRule::Huffing);
+        assert_eq!(rules.blocked_rule, BlockedRule::Pass);
+        assert_eq!(rules.draw_moves, 50);
+
+        for name in [
+            "",
+            "chess",
+            "standard,",
+            "standard,blocked",
+            "standard,blocked=never",
+            "standard,draw-moves=-1",
+            "standard,draw-moves=x",
+            "standard,sheep=3",
+        ] {
+            assert!(RuleSet::from_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let standard = RuleSet::default();
+        assert!(standard.check().is_ok());
+        for rules in [
+            RuleSet {
+                wolves: 0,
+                ..standard.clone()
+            },
+            RuleSet {
+                wolves: 3,
+                ..standard.clone()
+            },
+            RuleSet {
+                sheep: 0,
+                ..standard.clone()
+            },
+            RuleSet {
+                sheep: 26,
+                ..standard.clone()
+            },
+            RuleSet {
+                capture_threshold: 0,
+                ..standard.clone()
+            },
+            RuleSet {
+                capture_threshold: 16,
+                ..standard.clone()
+            },
+        ] {
+            assert!(rules.check().is_err(), "{:?}", rules);
+        }
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
@@ -5,12 +5,37 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
-use super::GameState;
-use crate::net::protocol::{Message, MsgType, message_from_bytes};
+use super::notation::{fields_to_text, text_to_fields};
+use super::recorder::result_to_recorder_line;
+use super::{GameState, RuleSet, Turn, WinState};
+use crate::net::protocol::{MsgType, message_from_bytes};
+use crate::player::PlayerMode;
+use crate::print::Print;
 use anyhow as ah;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// First line of a save file.
+/// Files that don't start with this are legacy files containing game state packets.
+const SAVE_MAGIC: &str = "wolfsmuehle-savegame";
+/// Version of the save file format.
+const SAVE_VERSION: u32 = 2;
+
+fn time_to_text(time: SystemTime) -> String {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs().to_string(),
+        Err(_) => "0".to_string(),
+    }
+}
+
+fn text_to_time(text: &str) -> ah::Result<SystemTime> {
+    let secs: u64 = text
+        .parse()
+        .map_err(|_| ah::format_err!("Invalid time stamp: {}", text))?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
 
 impl GameState {
     pub fn save_game(&self, filename: &Path) -> ah::Result<()> {
@@ -28,16 +53,184 @@ impl GameState {
         let mut file = OpenOptions::new().read(true).open(filename)?;
         let mut buf = vec![];
         file.read_to_end(&mut buf)?;
-        self.deserialize(&buf)?;
+        if buf.starts_with(SAVE_MAGIC.as_bytes()) {
+            self.deserialize(&buf)?;
+        } else {
+            self.deserialize_legacy(&buf)?;
+        }
         Ok(())
     }
 
+    /// Get the names of the players of one side.
+    fn get_side_player_names(&self, turn: Turn) -> Vec<String> {
+        let mode = match turn {
+            Turn::Wolf => PlayerMode::Wolf,
+            Turn::Sheep => PlayerMode::Sheep,
+        };
+        if self.joined_room.is_some() {
+            self.room_player_list
+                .iter()
+                .filter(|p| p.mode == mode || p.mode == PlayerMode::Both)
+                .map(|p| p.name.clone())
+                .collect()
+        } else if self.player_mode == mode || self.player_mode == PlayerMode::Both {
+            vec![self.player_name.clone()]
+        } else {
+            vec![]
+        }
+    }
+
     fn serialize(&self) -> ah::Result<Vec<u8>> {
-        let game_state_msg = self.make_state_message();
-        Ok(game_state_msg.to_bytes())
+        let mut text = format!("{} {}\n", SAVE_MAGIC, SAVE_VERSION);
+        text.push_str(&format!("started: {}\n", time_to_text(self.start_time)));
+        text.push_str(&format!("saved: {}\n", time_to_text(SystemTime::now())));
+        if let Some(room) = self.client_get_joined_room() {
+            text.push_str(&format!("room: {}\n", room));
+        }
+        for turn in [Turn::Wolf, Turn::Sheep] {
+            for name in self.get_side_player_names(turn) {
+                text.push_str(&format!("{}-player: {}\n", turn, name));
+            }
+        }
+        text.push_str(&format!("rules: {}\n", self.rules.name));
+        text.push_str(&format!("result: {}\n", self.get_win_state()));
+        text.push_str(&format!(
+            "start: {}\n",
+            fields_to_text(self.board(), &self.start_fields)
+        ));
+        text.push_str(&format!("start-turn: {}\n", self.start_turn));
+        text.push_str(&format!("turn: {}\n", self.turn));
+        text.push_str("moves:\n");
+        for mov in self.recorder.get_moves() {
+            text.push_str(mov);
+            text.push('\n');
+        }
+        if let (win_state, Some(reason)) = self.get_result() {
+            text.push_str(&result_to_recorder_line(win_state, reason));
+            text.push('\n');
+        }
+        Ok(text.into_bytes())
     }
 
     fn deserialize(&mut self, data: &[u8]) -> ah::Result<()> {
+        let text = std::str::from_utf8(data)?;
+        let mut lines = text.lines();
+
+        // Check the file header.
+        let header = lines.next().unwrap_or_default();
+        let version: u32 = match header.strip_prefix(SAVE_MAGIC) {
+            Some(v) => v
+                .trim()
+                .parse()
+                .map_err(|_| ah::format_err!("Invalid save file version: {}", v.trim()))?,
+            None => return Err(ah::format_err!("Invalid save file header.")),
+        };
+        if version != SAVE_VERSION {
+            return Err(ah::format_err!(
+                "Save file version {} is not supported.",
+                version
+            ));
+        }
+
+        // Parse the meta data up to the move list.
+        let mut rules = None;
+        let mut start_fields = None;
+        let mut start_turn = Turn::Sheep;
+        let mut turn = None;
+        let mut start_time = SystemTime::now();
+        let mut result = None;
+        let mut players = vec![];
+        for line in lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "moves:" {
+                break;
+            }
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(ah::format_err!("Invalid save file line: {}", line)),
+            };
+            match key {
+                "started" => start_time = text_to_time(value)?,
+                "rules" => rules = Some(RuleSet::from_name(value)?),
+                "start" => start_fields = Some(value.to_string()),
+                "start-turn" => {
+                    start_turn = match value {
+                        "wolf" => Turn::Wolf,
+                        "sheep" => Turn::Sheep,
+                        v => return Err(ah::format_err!("Invalid start turn: {}", v)),
+                    }
+                }
+                "turn" => {
+                    turn = match value {
+                        "wolf" => Some(Turn::Wolf),
+                        "sheep" => Some(Turn::Sheep),
+                        v => return Err(ah::format_err!("Invalid turn: {}", v)),
+                    }
+                }
+                "result" => result = Some(value.to_string()),
+                "wolf-player" | "sheep-player" => players.push(format!("{} ({})", value, key)),
+                _ => (), // Ignore unknown meta data.
+            }
+        }
+        let start_fields = match start_fields {
+            Some(f) => f,
+            None => return Err(ah::format_err!("Save file has no start position.")),
+        };
+
+        // Replay the moves on a local copy of the game with the rules of the saved game.
+        let mut game = self.clone_local();
+        if let Some(rules) = rules
+            && rules.name != self.rules.name
+        {
+            if self.client.is_some() {
+                return Err(ah::format_err!(
+                    "The saved game uses the rules '{}', but this game uses the rules '{}'.",
+                    rules.name,
+                    self.rules.name
+                ));
+            }
+            game.rules = rules;
+            game.reset_game(true);
+        }
+        let start_fields = text_to_fields(game.board(), &start_fields)?;
+        game.check_position(&start_fields)?;
+        game.set_start_position(start_fields, start_turn);
+        game.start_time = start_time;
+        let moves: Vec<&str> = lines.collect();
+        game.replay_moves(&moves.join("\n"))?;
+        if turn == Some(Turn::Sheep)
+            && game.get_capture_chain().is_some()
+            && game.get_win_state() == WinState::Undecided
+        {
+            // The capture chain of the last move has been ended.
+            game.end_capture_chain()?;
+        }
+        if let Some(result) = result
+            && result != game.get_win_state().to_string()
+        {
+            return Err(ah::format_err!(
+                "The saved game result '{}' does not match the replayed result '{}'.",
+                result,
+                game.get_win_state()
+            ));
+        }
+        if !players.is_empty() {
+            Print::info(&format!("Loaded game of {}.", players.join(", ")));
+        }
+
+        // Set the local game state to the loaded game.
+        self.take_game_from(&game);
+        // Send the local game state to the server (if any).
+        self.client_send_full_gamestate()?;
+
+        Ok(())
+    }
+
+    /// Load a version 1 file that only contains game state packets.
+    fn deserialize_legacy(&mut self, data: &[u8]) -> ah::Result<()> {
         let mut offset = 0;
         let mut messages = vec![];
         loop {
@@ -84,4 +277,126 @@ impl GameState {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::new_test_game;
+    use crate::net::protocol::Message;
+
+    fn play(game: &mut GameState, count: usize) {
+        for _ in 0..count {
+            let mov = game.legal_moves()[0];
+            game.make_move(&mov).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for rules in ["standard", "fox-and-geese"] {
+            let mut game = new_test_game(rules, None);
+            play(&mut game, 7);
+            let data = game.serialize().unwrap();
+            assert!(data.starts_with(format!("{} {}\n", SAVE_MAGIC, SAVE_VERSION).as_bytes()));
+
+            let mut loaded = new_test_game(rules, None);
+            loaded.deserialize(&data).unwrap();
+            assert_eq!(loaded.get_position_notation(), game.get_position_notation());
+            assert_eq!(
+                loaded.get_recorder().get_moves(),
+                game.get_recorder().get_moves()
+            );
+        }
+    }
+
+    #[test]
+    fn test_file() {
+        let mut game = new_test_game("standard", None);
+        play(&mut game, 4);
+        let path =
+            std::env::temp_dir().join(format!("wolfsmuehle-test-{}.save", std::process::id()));
+        game.save_game(&path).unwrap();
+        let mut loaded = new_test_game("standard", None);
+        let res = loaded.load_game(&path);
+        std::fs::remove_file(&path).unwrap();
+        res.unwrap();
+        assert_eq!(loaded.fields, game.fields);
+    }
+
+    #[test]
+    fn test_legacy() {
+        let mut game = new_test_game("standard", None);
+        play(&mut game, 5);
+        let data = game.make_state_message().to_bytes();
+
+        let mut loaded = new_test_game("standard", None);
+        loaded.deserialize_legacy(&data).unwrap();
+        assert_eq!(loaded.fields, game.fields);
+
+        assert!(loaded.deserialize_legacy(&[]).is_err());
+        assert!(loaded.deserialize_legacy(b"garbage data").is_err());
+    }
+
+    #[test]
+    fn test_version() {
+        let game = new_test_game("standard", None);
+        let data = String::from_utf8(game.serialize().unwrap()).unwrap();
+        let header = format!("{} {}", SAVE_MAGIC, SAVE_VERSION);
+        for version in ["0", "1", "3", "x", ""] {
+            let data = data.replacen(&header, &format!("{} {}", SAVE_MAGIC, version), 1);
+            let mut loaded = new_test_game("standard", None);
+            assert!(loaded.deserialize(data.as_bytes()).is_err(), "{}", version);
+        }
+    }
+
+    #[test]
+    fn test_other_rules() {
+        let mut game = new_test_game("fox-and-geese", None);
+        play(&mut game, 5);
+        let data = game.serialize().unwrap();
+
+        // A local game takes over the rules of the saved game.
+        let mut loaded = new_test_game("standard", None);
+        loaded.deserialize(&data).unwrap();
+        assert_eq!(loaded.get_rules().name, "fox-and-geese");
+        assert_eq!(loaded.fields, game.fields);
+        assert_eq!(loaded.orig_sheep_count, game.orig_sheep_count);
+        assert_eq!(
+            loaded.get_recorder().get_moves(),
+            game.get_recorder().get_moves()
+        );
+        assert_eq!(loaded.legal_moves(), game.legal_moves());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut game = new_test_game("standard", None);
+        play(&mut game, 3);
+        let data = String::from_utf8(game.serialize().unwrap()).unwrap();
+
+        // Unknown rules.
+        let broken = data.replacen("rules: standard", "rules: unknown", 1);
+        let mut loaded = new_test_game("standard", None);
+        assert!(loaded.deserialize(broken.as_bytes()).is_err());
+
+        // Missing start position.
+        let broken: Vec<&str> = data
+            .lines()
+            .filter(|line| !line.starts_with("start:"))
+            .collect();
+        let mut loaded = new_test_game("standard", None);
+        assert!(loaded.deserialize(broken.join("\n").as_bytes()).is_err());
+
+        // Wrong result.
+        let broken = data.replacen("result: ", "result: x", 1);
+        assert!(loaded.deserialize(broken.as_bytes()).is_err());
+
+        // Illegal move.
+        let broken = format!("{}a1-a2\n", data);
+        assert!(loaded.deserialize(broken.as_bytes()).is_err());
+
+        // Failed loads leave the game untouched.
+        assert_eq!(loaded.fields, new_test_game("standard", None).fields);
+    }
+}
+
 // vim: ts=4 sw=4 expandtab
@@ -0,0 +1,189 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use super::{FieldState, GameState, Turn};
+use crate::board::{BOARD_HEIGHT, BOARD_WIDTH};
+use crate::coord::Coord;
+use crate::player::PlayerMode;
+use anyhow as ah;
+
+impl GameState {
+    /// Check if a position can be played.
+    pub(super) fn check_position(
+        &self,
+        fields: &[[FieldState; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize],
+    ) -> ah::Result<()> {
+        let mut wolves = 0;
+        let mut sheep = 0;
+        for coord in self.board().iter() {
+            match fields[coord.y as usize][coord.x as usize] {
+                FieldState::Wolf => wolves += 1,
+                FieldState::Sheep => sheep += 1,
+                FieldState::Unused | FieldState::Empty => (),
+            }
+        }
+        if wolves > self.rules.wolves {
+            return Err(ah::format_err!(
+                "The position has {} wolves. At most {} wolves are allowed.",
+                wolves,
+                self.rules.wolves
+            ));
+        }
+        if sheep > self.orig_sheep_count {
+            return Err(ah::format_err!(
+                "The position has {} sheep. At most {} sheep are allowed.",
+                sheep,
+                self.orig_sheep_count
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check if the position setup mode is active.
+    pub fn setup_is_active(&self) -> bool {
+        self.setup_active
+    }
+
+    /// Enter the position setup mode.
+    /// In setup mode tokens can be placed and removed freely, but no moves can be made.
+    pub fn setup_begin(&mut self) -> ah::Result<()> {
+        if self.client.is_some() {
+            return Err(ah::format_err!(
+                "Position setup is not possible in a network game."
+            ));
+        }
+        if self.player_mode == PlayerMode::Spectator {
+            return Err(ah::format_err!(
+                "Player is spectator. Not allowed to set up a position."
+            ));
+        }
+        self.move_abort();
+        self.capture_chain = None;
+        self.setup_active = true;
+        Ok(())
+    }
+
+    fn check_setup_active(&self) -> ah::Result<()> {
+        if self.setup_active {
+            Ok(())
+        } else {
+            Err(ah::format_err!("Position setup is not active."))
+        }
+    }
+
+    /// Put a token onto a board position or remove it in setup mode.
+    pub fn setup_set_field(&mut self, pos: Coord, state: FieldState) -> ah::Result<()> {
+        self.check_setup_active()?;
+        if !self.board().contains(pos) || state == FieldState::Unused {
+            return Err(ah::format_err!("Invalid setup of position {}.", pos));
+        }
+        self.set_field_state(pos, state);
+        self.recalc_stats();
+        Ok(())
+    }
+
+    /// Set the side to move in setup mode.
+    pub fn setup_set_turn(&mut self, turn: Turn) -> ah::Result<()> {
+        self.check_setup_active()?;
+        self.turn = turn;
+        Ok(())
+    }
+
+    /// Remove all tokens from the board in setup mode.
+    pub fn setup_clear(&mut self) -> ah::Result<()> {
+        self.check_setup_active()?;
+        for coord in self.board().iter() {
+            self.set_field_state(coord, FieldState::Empty);
+        }
+        self.recalc_stats();
+        Ok(())
+    }
+
+    /// Check the set up position and leave the setup mode.
+    /// The set up position becomes the start position of the game.
+    pub fn setup_end(&mut self) -> ah::Result<()> {
+        self.check_setup_active()?;
+        self.check_position(&self.fields)?;
+        self.set_start_position(self.fields, self.turn);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord;
+    use crate::game_state::RuleSet;
+    use crate::game_state::new_test_game;
+
+    #[test]
+    fn test_setup() {
+        let mut game = new_test_game("standard", None);
+        assert!(game.setup_clear().is_err());
+        assert!(game.setup_end().is_err());
+
+        game.setup_begin().unwrap();
+        assert!(game.setup_is_active());
+        // No moves can be made in setup mode.
+        let mov = game.legal_moves()[0];
+        assert!(game.make_move(&mov).is_err());
+        game.setup_clear().unwrap();
+        game.setup_set_field(coord!(2, 0), FieldState::Wolf)
+            .unwrap();
+        for x in 0..5 {
+            game.setup_set_field(coord!(x, 6), FieldState::Sheep)
+                .unwrap();
+            game.setup_set_field(coord!(x, 5), FieldState::Sheep)
+                .unwrap();
+        }
+        game.setup_set_turn(Turn::Wolf).unwrap();
+        assert!(
+            game.setup_set_field(coord!(0, 0), FieldState::Sheep)
+                .is_err()
+        );
+        assert!(
+            game.setup_set_field(coord!(2, 1), FieldState::Unused)
+                .is_err()
+        );
+        game.setup_end().unwrap();
+
+        assert!(!game.setup_is_active());
+        assert_eq!(game.get_position_notation(), "W/3/5/5/5/SSSSS/SSSSS w - -");
+        assert!(!game.legal_moves().is_empty());
+        assert!(game.get_recorder().get_moves().is_empty());
+    }
+
+    #[test]
+    fn test_setup_invalid() {
+        let mut game = new_test_game("standard", None);
+        game.setup_begin().unwrap();
+        for x in 0..5 {
+            game.setup_set_field(coord!(x, 3), FieldState::Wolf)
+                .unwrap();
+        }
+        // Too many wolves. The setup mode stays active.
+        assert!(game.setup_end().is_err());
+        assert!(game.setup_is_active());
+
+        game.setup_clear().unwrap();
+        for coord in game.board().iter() {
+            game.setup_set_field(coord, FieldState::Sheep).unwrap();
+        }
+        // Too many sheep.
+        assert!(game.setup_end().is_err());
+
+        let mut game = GameState::new(
+            PlayerMode::Spectator,
+            None,
+            RuleSet::from_name("standard").unwrap(),
+        )
+        .unwrap();
+        assert!(game.setup_begin().is_err());
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
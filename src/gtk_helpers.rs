@@ -31,4 +31,53 @@ pub fn messagebox_error<T: IsA<gtk::Window>>(parent: Option<&T>, text: &str) {
     dlg.show();
 }
 
+/// Ask for a password.
+/// The callback is called with the entered password, if OK is clicked.
+pub fn password_dialog<T: IsA<gtk::Window>, F: Fn(&str) + 'static>(
+    parent: Option<&T>,
+    text: &str,
+    callback: F,
+) {
+    let win = gtk::Window::builder()
+        .title("Password")
+        .modal(true)
+        .default_width(300)
+        .build();
+    win.set_transient_for(parent);
+
+    let vbox = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    vbox.set_margin_top(12);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(12);
+    vbox.set_margin_end(12);
+
+    vbox.append(&gtk::Label::new(Some(text)));
+    let entry = gtk::PasswordEntry::new();
+    entry.set_show_peek_icon(true);
+    vbox.append(&entry);
+
+    let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+    button_box.set_halign(gtk::Align::End);
+    let cancel_btn = gtk::Button::with_label("Cancel");
+    let ok_btn = gtk::Button::with_label("OK");
+    button_box.append(&cancel_btn);
+    button_box.append(&ok_btn);
+    vbox.append(&button_box);
+
+    win.set_child(Some(&vbox));
+
+    let win2 = win.clone();
+    cancel_btn.connect_clicked(move |_| {
+        win2.close();
+    });
+
+    let win2 = win.clone();
+    ok_btn.connect_clicked(move |_| {
+        win2.close();
+        callback(entry.text().as_str());
+    });
+
+    win.present();
+}
+
 // vim: ts=4 sw=4 expandtab
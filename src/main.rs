@@ -8,6 +8,7 @@
 #![forbid(unsafe_code)]
 #![windows_subsystem = "windows"]
 
+mod ai;
 mod board;
 mod coord;
 mod game_state;
@@ -21,17 +22,30 @@ mod player;
 mod print;
 mod random;
 
+#[cfg(feature = "gui")]
+use crate::ai::ComputerConfig;
+use crate::game_state::RuleSet;
+#[cfg(feature = "gui")]
+use crate::game_state::Turn;
 #[cfg(feature = "gui")]
 use crate::gtk_helpers::*;
 #[cfg(feature = "gui")]
 use crate::main_window::MainWindow;
 #[cfg(feature = "server")]
-use crate::net::server::Server;
+use crate::net::server::{
+    ConfigFile, LoadPolicy, ResetPolicy, RoomConfig, SeatPolicy, Server, ServerConfig,
+};
 #[cfg(feature = "gui")]
 use crate::player::PlayerMode;
 use crate::print::Print;
 use anyhow as ah;
-use clap::Parser;
+#[cfg(feature = "server")]
+use clap::{ArgMatches, parser::ValueSource};
+use clap::{CommandFactory, FromArgMatches, Parser};
+#[cfg(feature = "server")]
+use std::path::PathBuf;
+#[cfg(feature = "gui")]
+use std::time::Duration;
 
 /// Wolfsmühle board game.
 #[derive(Parser, Debug)]
@@ -51,26 +65,66 @@ struct Opts {
     server: bool,
 
     /// Bind the server to this address.
+    /// May be given multiple times to bind the server to several addresses.
     #[cfg(feature = "server")]
     #[arg(short = 'b', long, default_value = "0.0.0.0")]
-    server_bind: String,
+    server_bind: Vec<String>,
 
     /// Maximum number of connections to accept in server mode.
     #[cfg(feature = "server")]
     #[arg(short = 'M', long, default_value = "10")]
     max_connections: u16,
 
+    /// Read the server settings and the server rooms from this TOML config file.
+    /// Options given on the command line override the settings of the file.
+    /// Room settings that are not given in the file are taken from the command line options.
+    /// The file is reloaded, if it is modified while the server is running.
+    #[cfg(feature = "server")]
+    #[arg(long)]
+    config: Option<PathBuf>,
+
     /// Server room to open (server) or join (client).
+    /// Server rooms are opened in addition to the rooms of --config
+    /// and replace the rooms of the same name.
+    /// A server room may be given as "NAME:RULES:RESET-POLICY:SEAT-POLICY" to use other
+    /// rules or policies than --rules, --reset-policy and --seat-policy.
+    /// Trailing parts may be omitted and empty parts select the defaults.
     #[arg(short, long)]
     room: Option<Vec<String>>,
 
-    /// Restrict the player modes that can join a room.
-    /// With this option set, only one Wolf player and only
-    /// one Sheep player can join a room.
-    /// In restricted mode, the player mode "both" is not allowed.
+    /// The rule variant of a local game or of the server rooms.
+    /// May be "standard", "twenty-sheep", "forward-sheep", "compulsory", "huffing",
+    /// "fox-and-geese" or "asalto".
+    /// Rule options may follow, separated by commas:
+    /// "blocked=loss", "blocked=pass" or "blocked=draw" selects the outcome,
+    /// if the side to move has no legal move.
+    /// "draw-moves=N" draws the game after N moves without progress (0 = never, the default).
+    #[arg(long, default_value = "standard")]
+    rules: String,
+
+    /// The way the players of a server room take the wolf and the sheep side.
+    /// May be "open" (every player moves the sides of its player mode) or
+    /// "seats" (one wolf seat and one sheep seat; the player mode "both"
+    /// takes both seats; only the player in the seat of a side moves that side).
+    #[cfg(feature = "server")]
+    #[arg(long, default_value = "open")]
+    seat_policy: String,
+
+    /// The players that may load a game state into a server room.
+    /// May be "open" (every seated player), "owner" (only the seated player
+    /// that has been in the room for the longest time) or "agree"
+    /// (all other seated players have to agree).
+    #[cfg(feature = "server")]
+    #[arg(long, default_value = "open")]
+    load_policy: String,
+
+    /// The way a seated player resets the game in a server room.
+    /// May be "open" (reset at once), "agree" (all other seated players
+    /// have to agree) or "timeout=SECONDS" (reset, if no other seated
+    /// player objects within the timeout).
     #[cfg(feature = "server")]
-    #[arg(short = 'R', long)]
-    restrict_player_modes: bool,
+    #[arg(long, default_value = "timeout=30")]
+    reset_policy: String,
 
     /// Connect to a server.
     #[cfg(feature = "gui")]
@@ -91,28 +145,89 @@ struct Opts {
     #[cfg(feature = "gui")]
     #[arg(short = 'm', long, default_value = "both")]
     player_mode: String,
+
+    /// Let the computer play one side of a local game.
+    /// May be "wolf" or "sheep".
+    #[cfg(feature = "gui")]
+    #[arg(short = 'C', long)]
+    computer: Option<String>,
+
+    /// The engine used by the computer player.
+    /// May be "alphabeta" or "mcts" (Monte Carlo tree search).
+    #[cfg(feature = "gui")]
+    #[arg(long, default_value = "alphabeta")]
+    computer_engine: String,
+
+    /// Maximum search depth of the computer player (alphabeta engine).
+    #[cfg(feature = "gui")]
+    #[arg(long, default_value = "8")]
+    computer_depth: u32,
+
+    /// Maximum thinking time of the computer player, in seconds.
+    #[cfg(feature = "gui")]
+    #[arg(long, default_value = "2.0")]
+    computer_time: f32,
+
+    /// Number of search iterations of the computer player (mcts engine).
+    #[cfg(feature = "gui")]
+    #[arg(long, default_value = "2000")]
+    computer_iterations: u32,
 }
 
 #[cfg(feature = "server")]
-fn server_fn(opt: &Opts) -> ah::Result<()> {
-    let addr = format!("{}:{}", opt.server_bind, opt.port);
-
-    Print::info(&format!("Running dedicated server on {} ...", addr));
-    let mut s = Server::new(addr, opt.max_connections, opt.restrict_player_modes)?;
-
-    let default_rooms = vec!["default".to_string()];
-    let rooms = match opt.room.as_ref() {
-        Some(r) => r,
-        None => {
-            Print::warning(&format!(
-                "No server rooms specified. Using '{}'.",
-                default_rooms[0]
-            ));
-            &default_rooms
-        }
+fn server_fn(opt: &Opts, matches: &ArgMatches) -> ah::Result<()> {
+    let mut defaults = RoomConfig::new(RuleSet::from_name(&opt.rules)?);
+    defaults.load_policy = LoadPolicy::from_name(&opt.load_policy)?;
+    defaults.reset_policy = ResetPolicy::from_name(&opt.reset_policy)?;
+    defaults.seat_policy = SeatPolicy::from_name(&opt.seat_policy)?;
+
+    // The options given on the command line override the config file.
+    let given = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let mut overrides = ServerConfig {
+        bind: given("server_bind").then(|| opt.server_bind.clone()),
+        port: given("port").then_some(opt.port),
+        max_connections: given("max_connections").then_some(opt.max_connections),
+        log_level: given("log_level").then_some(opt.log_level),
+        max_created_rooms: None,
+        abandoned_room_timeout: None,
+        rooms: vec![],
+    };
+    for spec in opt.room.iter().flatten() {
+        let room = defaults.with_spec(spec)?;
+        overrides.rooms.retain(|r| r.name != room.name);
+        overrides.rooms.push(room);
+    }
+
+    let mut config_file = opt
+        .config
+        .as_ref()
+        .map(|path| ConfigFile::new(path, defaults.clone()));
+    let mut config = match config_file.as_mut() {
+        Some(config_file) => config_file.read()?,
+        None => ServerConfig::default(),
     };
+    config.override_with(&overrides);
 
-    s.run(rooms)?;
+    if let Some(log_level) = config.log_level {
+        Print::set_level_number(log_level);
+    }
+    if config.rooms.is_empty() {
+        Print::warning(&format!(
+            "No server rooms specified. Using '{}'.",
+            defaults.name
+        ));
+        config.rooms.push(defaults.clone());
+    }
+
+    let mut s = Server::new(
+        config.bind.as_ref().unwrap_or(&opt.server_bind),
+        config.port.unwrap_or(opt.port),
+        config.max_connections.unwrap_or(opt.max_connections),
+        defaults,
+        config_file,
+        overrides,
+    )?;
+    s.run(config)?;
     Ok(())
 }
 
@@ -145,15 +260,46 @@ fn app_fn(app: &gtk::Application) {
         _ => panic!("Invalid --player-mode."),
     };
 
-    MainWindow::new(app, connect, room_name, opt.player_name, player_mode)
-        .expect("Startup failed")
-        .borrow()
-        .main_window()
-        .show();
+    let rules = match RuleSet::from_name(&opt.rules) {
+        Ok(rules) => rules,
+        Err(e) => panic!("Invalid --rules: {}", e),
+    };
+
+    let computer_engine = match opt.computer_engine.to_lowercase().trim() {
+        engine @ ("alphabeta" | "mcts") => engine.to_string(),
+        _ => panic!("Invalid --computer-engine."),
+    };
+    let computer_config = ComputerConfig {
+        engine: computer_engine,
+        depth: opt.computer_depth,
+        time_limit: Some(Duration::from_secs_f32(opt.computer_time.max(0.01))),
+        iterations: opt.computer_iterations,
+    };
+    let computer_side = opt.computer.map(|side| match side.to_lowercase().trim() {
+        "wolf" => Turn::Wolf,
+        "sheep" => Turn::Sheep,
+        _ => panic!("Invalid --computer."),
+    });
+
+    MainWindow::new(
+        app,
+        connect,
+        room_name,
+        opt.player_name,
+        player_mode,
+        rules,
+        computer_config,
+        computer_side,
+    )
+    .expect("Startup failed")
+    .borrow()
+    .main_window()
+    .show();
 }
 
 fn main() -> ah::Result<()> {
-    let opt = Opts::parse();
+    let matches = Opts::command().get_matches();
+    let opt = Opts::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     Print::set_level_number(opt.log_level);
 
     #[cfg(feature = "gui")]
@@ -163,7 +309,7 @@ fn main() -> ah::Result<()> {
 
     #[cfg(feature = "server")]
     if run_server {
-        server_fn(&opt)?;
+        server_fn(&opt, &matches)?;
         return Ok(());
     }
 
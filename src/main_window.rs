@@ -11,13 +11,14 @@ use game_meta_view::GameMetaView;
 mod drawing_area;
 use drawing_area::DrawingArea;
 
-use crate::game_state::GameState;
+use crate::ai::{Computer, ComputerConfig};
+use crate::game_state::{GameState, RuleSet, Turn, WinState};
 use crate::gtk_helpers::*;
 use crate::player::PlayerMode;
 use anyhow as ah;
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const ABOUT_TEXT: &str = "Wolfsmühle - Board game\n\
      \n\
@@ -32,6 +33,8 @@ pub struct MainWindow {
     draw: Rc<RefCell<DrawingArea>>,
     game: Rc<RefCell<GameState>>,
     game_meta_view: Rc<RefCell<GameMetaView>>,
+    computer_config: ComputerConfig,
+    computer: Option<Computer>,
 }
 
 impl MainWindow {
@@ -41,6 +44,9 @@ impl MainWindow {
         room_name: String,
         player_name: Option<String>,
         player_mode: PlayerMode,
+        rules: RuleSet,
+        computer_config: ComputerConfig,
+        computer_side: Option<Turn>,
     ) -> ah::Result<Rc<RefCell<MainWindow>>> {
         // Create main window.
         let ui_source = include_str!("main_window.ui");
@@ -54,11 +60,24 @@ impl MainWindow {
         let game_meta_info_grid: gtk::Grid = builder.object("game_meta_info_grid").unwrap();
 
         // Create game state.
-        let game = Rc::new(RefCell::new(GameState::new(player_mode, player_name)?));
+        let game = Rc::new(RefCell::new(GameState::new(
+            player_mode,
+            player_name,
+            rules,
+        )?));
+        let mut protected_room = None;
         if let Some(connect_to_server) = &connect_to_server {
             let mut game = game.borrow_mut();
             game.client_connect(connect_to_server)?;
-            game.client_join_room(&room_name)?;
+            if let Err(e) = game.client_join_room(&room_name, "") {
+                // The room list has been received before the reply to the join request.
+                game.poll_server();
+                if !game.client_room_is_protected(&room_name) {
+                    return Err(e);
+                }
+                // Ask for the password, when the main window is shown.
+                protected_room = Some(room_name);
+            }
             game_meta_info_grid.show();
         } else {
             game_meta_info_grid.hide();
@@ -87,6 +106,13 @@ impl MainWindow {
             Rc::clone(&game),
         )?));
 
+        // Create the computer player.
+        let computer = match computer_side {
+            Some(side) => Some(computer_config.new_computer(side)?),
+            None => None,
+        };
+        draw.borrow_mut().set_computer_side(computer_side);
+
         let mainwnd = Rc::new(RefCell::new(MainWindow {
             appwindow,
             status_label,
@@ -94,6 +120,8 @@ impl MainWindow {
             draw,
             game,
             game_meta_view,
+            computer_config,
+            computer,
         }));
 
         // Create game polling timer.
@@ -116,9 +144,19 @@ impl MainWindow {
 
         // Set up window actions for menu items.
         Self::setup_actions(&mainwnd);
+        app.set_accels_for_action("win.undo", &["<Primary>z"]);
+        app.set_accels_for_action("win.redo", &["<Primary><Shift>z", "<Primary>y"]);
+        app.set_accels_for_action("win.end_chain", &["<Primary>e"]);
 
         mainwnd.borrow().update_status();
 
+        if let Some(room_name) = protected_room {
+            let game_meta_view = Rc::clone(&mainwnd.borrow().game_meta_view);
+            glib::idle_add_local_once(move || {
+                GameMetaView::join_protected_room(&game_meta_view, &room_name);
+            });
+        }
+
         Ok(mainwnd)
     }
 
@@ -135,6 +173,106 @@ impl MainWindow {
         });
         appwindow.add_action(&action);
 
+        // End capture chain action
+        let action = gio::SimpleAction::new("end_chain", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                let result = mw.draw.borrow_mut().end_capture_chain();
+                if let Err(e) = result {
+                    messagebox_error(
+                        Some(&mw.appwindow),
+                        &format!("Failed to end the capture chain:\n{}", e),
+                    );
+                }
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Resign action
+        let action = gio::SimpleAction::new("resign", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.resign();
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Draw offer actions
+        let action = gio::SimpleAction::new("offer_draw", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.offer_draw();
+            }
+        });
+        appwindow.add_action(&action);
+
+        for (name, accept) in [("accept_draw", true), ("decline_draw", false)] {
+            let action = gio::SimpleAction::new(name, None);
+            let mw = Rc::clone(mainwnd);
+            action.connect_activate(move |_, _| {
+                if let Ok(mw) = mw.try_borrow() {
+                    mw.answer_draw_offer(accept);
+                }
+            });
+            appwindow.add_action(&action);
+        }
+
+        // Takeback actions
+        let action = gio::SimpleAction::new("request_takeback", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.request_takeback();
+            }
+        });
+        appwindow.add_action(&action);
+
+        for (name, accept) in [("accept_takeback", true), ("decline_takeback", false)] {
+            let action = gio::SimpleAction::new(name, None);
+            let mw = Rc::clone(mainwnd);
+            action.connect_activate(move |_, _| {
+                if let Ok(mw) = mw.try_borrow() {
+                    mw.answer_takeback(accept);
+                }
+            });
+            appwindow.add_action(&action);
+        }
+
+        // Room vote actions
+        for (name, accept) in [("agree_vote", true), ("object_vote", false)] {
+            let action = gio::SimpleAction::new(name, None);
+            let mw = Rc::clone(mainwnd);
+            action.connect_activate(move |_, _| {
+                if let Ok(mw) = mw.try_borrow() {
+                    mw.answer_room_vote(accept);
+                }
+            });
+            appwindow.add_action(&action);
+        }
+
+        // Undo move action
+        let action = gio::SimpleAction::new("undo", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.undo_move();
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Redo move action
+        let action = gio::SimpleAction::new("redo", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.redo_move();
+            }
+        });
+        appwindow.add_action(&action);
+
         // Load game action
         let action = gio::SimpleAction::new("loadgame", None);
         let mw = Rc::clone(mainwnd);
@@ -155,6 +293,111 @@ impl MainWindow {
         });
         appwindow.add_action(&action);
 
+        // Position notation action
+        let action = gio::SimpleAction::new("position", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.position_show();
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Position setup action
+        let action = gio::SimpleAction::new_stateful("setup", None, &false.to_variant());
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |action, _| {
+            if let Ok(mw) = mw.try_borrow()
+                && let Some(active) = action.state().and_then(|s| s.get::<bool>())
+                && mw.set_setup(!active)
+            {
+                action.set_state(&(!active).to_variant());
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Position setup side to move action
+        let action = gio::SimpleAction::new_stateful(
+            "setup_turn",
+            Some(glib::VariantTy::STRING),
+            &"sheep".to_variant(),
+        );
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |action, param| {
+            if let Some(param) = param
+                && let Some(turn) = param.str()
+                && let Ok(mw) = mw.try_borrow()
+            {
+                let turn = match turn {
+                    "wolf" => Turn::Wolf,
+                    _ => Turn::Sheep,
+                };
+                if mw.game.borrow_mut().setup_set_turn(turn).is_ok() {
+                    action.set_state(param);
+                }
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Position setup clear action
+        let action = gio::SimpleAction::new("setup_clear", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow()
+                && let Err(e) = mw.draw.borrow_mut().setup_clear()
+            {
+                messagebox_error(Some(&mw.appwindow), &format!("{}", e));
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Computer player action
+        let side = match mainwnd.borrow().computer.as_ref().map(|c| c.get_side()) {
+            None => "none",
+            Some(Turn::Wolf) => "wolf",
+            Some(Turn::Sheep) => "sheep",
+        };
+        let action = gio::SimpleAction::new_stateful(
+            "computer",
+            Some(glib::VariantTy::STRING),
+            &side.to_variant(),
+        );
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |action, param| {
+            if let Some(param) = param
+                && let Some(side) = param.str()
+                && let Ok(mut mw) = mw.try_borrow_mut()
+            {
+                let side = match side {
+                    "wolf" => Some(Turn::Wolf),
+                    "sheep" => Some(Turn::Sheep),
+                    _ => None,
+                };
+                mw.set_computer(side);
+                action.set_state(param);
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Computer engine action
+        let engine = mainwnd.borrow().computer_config.engine.clone();
+        let action = gio::SimpleAction::new_stateful(
+            "computer_engine",
+            Some(glib::VariantTy::STRING),
+            &engine.to_variant(),
+        );
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |action, param| {
+            if let Some(param) = param
+                && let Some(engine) = param.str()
+                && let Ok(mut mw) = mw.try_borrow_mut()
+            {
+                mw.set_computer_engine(engine);
+                action.set_state(param);
+            }
+        });
+        appwindow.add_action(&action);
+
         // Quit action
         let action = gio::SimpleAction::new("quit", None);
         action.connect_activate(|_, _| {
@@ -182,6 +425,36 @@ impl MainWindow {
         });
         appwindow.add_action(&action);
 
+        // Join room action
+        let action = gio::SimpleAction::new("join_room", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mut mw) = mw.try_borrow_mut() {
+                mw.join_room();
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Create room action
+        let action = gio::SimpleAction::new("create_room", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mut mw) = mw.try_borrow_mut() {
+                mw.create_room();
+            }
+        });
+        appwindow.add_action(&action);
+
+        // Close room action
+        let action = gio::SimpleAction::new("close_room", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mut mw) = mw.try_borrow_mut() {
+                mw.close_room();
+            }
+        });
+        appwindow.add_action(&action);
+
         // Record show action
         let action = gio::SimpleAction::new("record_show", None);
         let mw = Rc::clone(mainwnd);
@@ -192,6 +465,16 @@ impl MainWindow {
         });
         appwindow.add_action(&action);
 
+        // Record load action
+        let action = gio::SimpleAction::new("record_load", None);
+        let mw = Rc::clone(mainwnd);
+        action.connect_activate(move |_, _| {
+            if let Ok(mw) = mw.try_borrow() {
+                mw.record_load();
+            }
+        });
+        appwindow.add_action(&action);
+
         // About action
         let action = gio::SimpleAction::new("about", None);
         let mw = Rc::clone(mainwnd);
@@ -258,24 +541,301 @@ impl MainWindow {
                 draw.redraw();
             }
         }
+        self.poll_computer();
+        self.update_actions();
         self.update_status();
     }
 
+    /// Let the computer player move, if it's its turn.
+    /// The computer player is disabled, if it fails.
+    fn poll_computer(&mut self) {
+        if let Some(computer) = self.computer.as_mut() {
+            let moved = match self.game.try_borrow_mut() {
+                Ok(mut game) if !game.client_is_connected() => computer.poll(&mut game),
+                Ok(_) | Err(_) => Ok(false),
+            };
+            match moved {
+                Ok(true) => {
+                    if let Ok(draw) = self.draw.try_borrow() {
+                        draw.redraw();
+                    }
+                }
+                Ok(false) => (),
+                Err(e) => {
+                    self.set_computer(None);
+                    if let Some(action) = self.appwindow.lookup_action("computer") {
+                        action.change_state(&"none".to_variant());
+                    }
+                    messagebox_error(
+                        Some(&self.appwindow),
+                        &format!("The computer player has been disabled:\n{}", e),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Enter or leave the position setup mode.
+    /// Returns true, if the mode has been changed.
+    fn set_setup(&self, active: bool) -> bool {
+        match self.draw.borrow_mut().setup(active) {
+            Ok(()) => true,
+            Err(e) => {
+                let text = if active {
+                    format!("Failed to start the position setup:\n{}", e)
+                } else {
+                    format!("The position can't be played:\n{}", e)
+                };
+                messagebox_error(Some(&self.appwindow), &text);
+                false
+            }
+        }
+    }
+
+    /// Get the side played by the user.
+    /// In a game against the computer, this is the side of the human player.
+    /// A player of both sides plays the side to move.
+    fn player_side(&self, game: &GameState) -> Turn {
+        match self.computer.as_ref() {
+            Some(computer) => computer.get_side().other(),
+            None if game.player_has_seat(game.get_turn()) => game.get_turn(),
+            None => game.get_turn().other(),
+        }
+    }
+
+    /// Resign the game for the side played by the user.
+    fn resign(&self) {
+        let side = self.player_side(&self.game.borrow());
+        if let Err(e) = self.draw.borrow_mut().resign(side) {
+            messagebox_error(Some(&self.appwindow), &format!("Failed to resign:\n{}", e));
+        }
+    }
+
+    /// Offer a draw to the opponent.
+    fn offer_draw(&self) {
+        let side = self.player_side(&self.game.borrow());
+        if let Err(e) = self.draw.borrow_mut().offer_draw(side) {
+            messagebox_error(
+                Some(&self.appwindow),
+                &format!("Failed to offer a draw:\n{}", e),
+            );
+        }
+    }
+
+    /// Accept or decline the draw offered by the opponent.
+    fn answer_draw_offer(&self, accept: bool) {
+        let side = match self.game.borrow().get_draw_offer() {
+            Some(side) => side.other(),
+            None => return,
+        };
+        if let Err(e) = self.draw.borrow_mut().answer_draw_offer(side, accept) {
+            messagebox_error(
+                Some(&self.appwindow),
+                &format!("Failed to answer the draw offer:\n{}", e),
+            );
+        }
+    }
+
+    /// Ask the opponent for the permission to take back the last move.
+    fn request_takeback(&self) {
+        let side = self.player_side(&self.game.borrow());
+        if let Err(e) = self.draw.borrow_mut().request_takeback(side) {
+            messagebox_error(
+                Some(&self.appwindow),
+                &format!("Failed to request a takeback:\n{}", e),
+            );
+        }
+    }
+
+    /// Accept or decline the takeback requested by the opponent.
+    fn answer_takeback(&self, accept: bool) {
+        let side = match self.game.borrow().get_takeback_request() {
+            Some(side) => side.other(),
+            None => return,
+        };
+        if let Err(e) = self.draw.borrow_mut().answer_takeback(side, accept) {
+            messagebox_error(
+                Some(&self.appwindow),
+                &format!("Failed to answer the takeback request:\n{}", e),
+            );
+        }
+    }
+
+    /// Agree to or object to the vote requested by another player in the room.
+    fn answer_room_vote(&self, accept: bool) {
+        if let Err(e) = self.draw.borrow_mut().answer_room_vote(accept) {
+            messagebox_error(
+                Some(&self.appwindow),
+                &format!("Failed to answer the vote:\n{}", e),
+            );
+        }
+    }
+
+    /// Take back the last move.
+    /// In a game against the computer, the computer's moves are taken back, too.
+    fn undo_move(&self) {
+        let computer_side = self.computer.as_ref().map(|c| c.get_side());
+        loop {
+            if let Err(e) = self.draw.borrow_mut().undo_move() {
+                messagebox_error(Some(&self.appwindow), &format!("Failed to undo:\n{}", e));
+                break;
+            }
+            let game = self.game.borrow();
+            if computer_side != Some(game.get_turn()) || !game.can_undo() {
+                break;
+            }
+        }
+    }
+
+    /// Do the last taken back move again.
+    /// In a game against the computer, the computer's moves are redone, too.
+    fn redo_move(&self) {
+        let computer_side = self.computer.as_ref().map(|c| c.get_side());
+        loop {
+            if let Err(e) = self.draw.borrow_mut().redo_move() {
+                messagebox_error(Some(&self.appwindow), &format!("Failed to redo:\n{}", e));
+                break;
+            }
+            let game = self.game.borrow();
+            if computer_side != Some(game.get_turn()) || !game.can_redo() {
+                break;
+            }
+        }
+    }
+
+    /// Select the engine of the computer player.
+    fn set_computer_engine(&mut self, engine: &str) {
+        self.computer_config.engine = engine.to_string();
+        let side = self.computer.as_ref().map(|c| c.get_side());
+        self.set_computer(side);
+    }
+
+    /// Enable or disable the computer player.
+    fn set_computer(&mut self, side: Option<Turn>) {
+        self.computer = match side {
+            Some(side) => match self.computer_config.new_computer(side) {
+                Ok(computer) => Some(computer),
+                Err(e) => {
+                    messagebox_error(
+                        Some(&self.appwindow),
+                        &format!("Failed to create computer player:\n{}", e),
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let side = self.computer.as_ref().map(|c| c.get_side());
+        self.draw.borrow_mut().set_computer_side(side);
+    }
+
     pub fn main_window(&self) -> gtk::ApplicationWindow {
         self.appwindow.clone()
     }
 
+    /// Enable or disable the window actions that depend on the game state.
+    fn update_actions(&self) {
+        let computer_side = self.computer.as_ref().map(|c| c.get_side());
+        let Ok(game) = self.game.try_borrow() else {
+            return;
+        };
+        let seated = !game.setup_is_active() && game.player_has_seat(self.player_side(&game));
+        let playing = seated && game.get_win_state() == WinState::Undecided;
+        let online = playing && game.client_is_connected();
+        let draw_offer = game.get_draw_offer();
+        let takeback_request = game.get_takeback_request();
+        let can_undo = game.can_undo();
+        let can_redo = game.can_redo();
+        let can_end_chain = game.can_end_capture_chain() && computer_side != Some(Turn::Wolf);
+        let can_offer_draw = online && draw_offer.is_none();
+        let can_answer_draw =
+            online && draw_offer.is_some_and(|side| game.player_has_seat(side.other()));
+        let can_request_takeback =
+            seated && game.client_is_connected() && takeback_request.is_none();
+        let can_answer_takeback = seated
+            && game.client_is_connected()
+            && takeback_request.is_some_and(|side| game.player_has_seat(side.other()));
+        let can_answer_vote = seated && game.get_room_vote().is_some_and(|vote| !vote.answered);
+        let setup = game.setup_is_active();
+        let turn = game.get_turn();
+        let connected = game.client_is_connected();
+        let joined = game.client_get_joined_room().is_some();
+        drop(game);
+
+        for (name, enabled) in [
+            ("end_chain", can_end_chain),
+            ("resign", playing),
+            ("offer_draw", can_offer_draw),
+            ("accept_draw", can_answer_draw),
+            ("decline_draw", can_answer_draw),
+            ("request_takeback", can_request_takeback),
+            ("accept_takeback", can_answer_takeback),
+            ("decline_takeback", can_answer_takeback),
+            ("agree_vote", can_answer_vote),
+            ("object_vote", can_answer_vote),
+            ("undo", can_undo),
+            ("redo", can_redo),
+            ("setup_turn", setup),
+            ("setup_clear", setup),
+            ("join_room", connected),
+            ("create_room", connected),
+            ("close_room", joined),
+        ] {
+            if let Some(action) = self.appwindow.lookup_action(name)
+                && let Ok(action) = action.downcast::<gio::SimpleAction>()
+            {
+                action.set_enabled(enabled);
+            }
+        }
+        // The setup mode may have been left by loading a game.
+        if let Some(action) = self.appwindow.lookup_action("setup") {
+            action.change_state(&setup.to_variant());
+        }
+        if let Some(action) = self.appwindow.lookup_action("setup_turn") {
+            action.change_state(&turn.to_string().to_variant());
+        }
+    }
+
     /// Update the status bar.
     fn update_status(&self) {
         let mut status = None;
 
         if let Ok(game) = self.game.try_borrow() {
             match game.client_get_addr() {
-                None => status = Some("Local game. Not connected to server.".to_string()),
+                None if game.setup_is_active() => {
+                    status = Some(
+                        "Position setup. Click on the board to place or remove tokens.".to_string(),
+                    )
+                }
+                None => {
+                    status = Some(match self.computer.as_ref() {
+                        Some(computer) => format!(
+                            "Local game against the computer playing {}.",
+                            computer.get_side()
+                        ),
+                        None => "Local game. Not connected to server.".to_string(),
+                    })
+                }
                 Some(addr) => match game.client_get_joined_room() {
                     None => status = Some(format!("Connected to '{}' and not in a room.", addr)),
                     Some(room) => {
-                        status = Some(format!("Connected to '{}' in room '{}'.", addr, room))
+                        let mut text = format!("Connected to '{}' in room '{}'.", addr, room);
+                        if let Some(vote) = game.get_room_vote() {
+                            text += &format!(" {} asks to {}", vote.requester, vote.subject);
+                            if let Some(deadline) = vote.deadline {
+                                let remaining = deadline.saturating_duration_since(Instant::now());
+                                text += &format!(
+                                    " (passes in {} s without objection)",
+                                    remaining.as_secs()
+                                );
+                            }
+                            text += if vote.answered {
+                                "."
+                            } else {
+                                ". Please agree or object."
+                            };
+                        }
+                        status = Some(text)
                     }
                 },
             }
@@ -290,6 +850,55 @@ impl MainWindow {
         messagebox_info(Some(&self.appwindow), ABOUT_TEXT);
     }
 
+    fn position_show(&self) {
+        let notation = self.game.borrow().get_position_notation();
+
+        let win = gtk::Window::builder()
+            .title("Position notation")
+            .transient_for(&self.appwindow)
+            .modal(true)
+            .default_width(450)
+            .build();
+
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let entry = gtk::Entry::new();
+        entry.set_text(&notation);
+        vbox.append(&entry);
+
+        let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        button_box.set_halign(gtk::Align::End);
+        let set_btn = gtk::Button::with_label("Set position");
+        button_box.append(&set_btn);
+        let close_btn = gtk::Button::with_label("Close");
+        button_box.append(&close_btn);
+        vbox.append(&button_box);
+
+        win.set_child(Some(&vbox));
+
+        let win2 = win.clone();
+        let draw = Rc::clone(&self.draw);
+        set_btn.connect_clicked(move |_| {
+            match draw.borrow_mut().set_position_notation(&entry.text()) {
+                Ok(()) => win2.close(),
+                Err(e) => {
+                    messagebox_error(Some(&win2), &format!("Failed to set position:\n{}", e));
+                }
+            }
+        });
+
+        let win2 = win.clone();
+        close_btn.connect_clicked(move |_| {
+            win2.close();
+        });
+
+        win.show();
+    }
+
     fn record_show(&self) {
         let log = self.game.borrow_mut().get_recorder().get_moves_as_text();
 
@@ -337,6 +946,34 @@ impl MainWindow {
         win.show();
     }
 
+    fn record_load(&self) {
+        let dlg = gtk::FileChooserDialog::new(
+            Some("Load recorded game"),
+            Some(&self.appwindow),
+            gtk::FileChooserAction::Open,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Open", gtk::ResponseType::Accept),
+            ],
+        );
+
+        let draw = Rc::clone(&self.draw);
+        dlg.connect_response(move |dialog, response| {
+            if response == gtk::ResponseType::Accept
+                && let Some(file) = dialog.file()
+                && let Some(path) = file.path()
+                && let Err(e) = draw.borrow_mut().load_record(path.as_path())
+            {
+                messagebox_error(
+                    Some(dialog),
+                    &format!("Failed to load recorded game:\n{}", e),
+                );
+            }
+            dialog.close();
+        });
+        dlg.show();
+    }
+
     fn connect_game(&mut self) {
         let draw = Rc::clone(&self.draw);
         let game = Rc::clone(&self.game);
@@ -425,6 +1062,172 @@ impl MainWindow {
         self.update_status();
     }
 
+    fn join_room(&mut self) {
+        let game = Rc::clone(&self.game);
+        let game_meta_view = Rc::clone(&self.game_meta_view);
+
+        // Create a custom dialog window for the room name and password.
+        let win = gtk::Window::builder()
+            .title("Join room")
+            .transient_for(&self.appwindow)
+            .modal(true)
+            .default_width(400)
+            .default_height(100)
+            .build();
+
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let label = gtk::Label::new(Some(
+            "Join a room by its name.\n\
+             Leave the password empty, if the room is not password protected:",
+        ));
+        vbox.append(&label);
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+        hbox.append(&gtk::Label::new(Some("Room:")));
+        let entry_name = gtk::Entry::new();
+        entry_name.set_hexpand(true);
+        hbox.append(&entry_name);
+
+        hbox.append(&gtk::Label::new(Some("Password:")));
+        let entry_password = gtk::PasswordEntry::new();
+        entry_password.set_show_peek_icon(true);
+        hbox.append(&entry_password);
+        vbox.append(&hbox);
+
+        let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        button_box.set_halign(gtk::Align::End);
+        let cancel_btn = gtk::Button::with_label("Cancel");
+        let ok_btn = gtk::Button::with_label("OK");
+        button_box.append(&cancel_btn);
+        button_box.append(&ok_btn);
+        vbox.append(&button_box);
+
+        win.set_child(Some(&vbox));
+
+        let win2 = win.clone();
+        cancel_btn.connect_clicked(move |_| {
+            win2.close();
+        });
+
+        let win2 = win.clone();
+        ok_btn.connect_clicked(move |_| {
+            let room_name = entry_name.text();
+            let password = entry_password.text();
+
+            let result = game
+                .borrow_mut()
+                .client_join_room(room_name.trim(), password.as_str());
+            if let Err(e) = result {
+                messagebox_error(Some(&win2), &format!("Failed to join room:\n{}", e));
+            } else {
+                game_meta_view.borrow_mut().clear_chat_messages();
+            }
+            win2.close();
+        });
+
+        win.present();
+    }
+
+    fn create_room(&mut self) {
+        let game = Rc::clone(&self.game);
+        let game_meta_view = Rc::clone(&self.game_meta_view);
+
+        // Create a custom dialog window for the room settings.
+        let win = gtk::Window::builder()
+            .title("Create room")
+            .transient_for(&self.appwindow)
+            .modal(true)
+            .default_width(400)
+            .default_height(100)
+            .build();
+
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        vbox.set_margin_top(12);
+        vbox.set_margin_bottom(12);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let label = gtk::Label::new(Some(
+            "Create a new room on the server.\n\
+             Leave the rules empty for the default rules of the server:",
+        ));
+        vbox.append(&label);
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+        hbox.append(&gtk::Label::new(Some("Room:")));
+        let entry_name = gtk::Entry::new();
+        entry_name.set_hexpand(true);
+        hbox.append(&entry_name);
+
+        hbox.append(&gtk::Label::new(Some("Rules:")));
+        let entry_rules = gtk::Entry::new();
+        hbox.append(&entry_rules);
+        vbox.append(&hbox);
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+        hbox.append(&gtk::Label::new(Some("Password (optional):")));
+        let entry_password = gtk::PasswordEntry::new();
+        entry_password.set_hexpand(true);
+        entry_password.set_show_peek_icon(true);
+        hbox.append(&entry_password);
+        vbox.append(&hbox);
+
+        let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        button_box.set_halign(gtk::Align::End);
+        let cancel_btn = gtk::Button::with_label("Cancel");
+        let ok_btn = gtk::Button::with_label("OK");
+        button_box.append(&cancel_btn);
+        button_box.append(&ok_btn);
+        vbox.append(&button_box);
+
+        win.set_child(Some(&vbox));
+
+        let win2 = win.clone();
+        cancel_btn.connect_clicked(move |_| {
+            win2.close();
+        });
+
+        let win2 = win.clone();
+        ok_btn.connect_clicked(move |_| {
+            let room_name = entry_name.text();
+            let rules_name = entry_rules.text();
+            let password = entry_password.text();
+
+            let result = {
+                let mut game = game.borrow_mut();
+                game.client_create_room(room_name.trim(), rules_name.trim(), password.as_str())
+                    .and_then(|_| game.client_join_room(room_name.trim(), password.as_str()))
+            };
+            if let Err(e) = result {
+                messagebox_error(Some(&win2), &format!("Failed to create room:\n{}", e));
+            } else {
+                game_meta_view.borrow_mut().clear_chat_messages();
+            }
+            win2.close();
+        });
+
+        win.present();
+    }
+
+    fn close_room(&mut self) {
+        let result = self.game.borrow_mut().client_close_room();
+        if let Err(e) = result {
+            messagebox_error(
+                Some(&self.appwindow),
+                &format!("Failed to close room:\n{}", e),
+            );
+        }
+        self.game_meta_view.borrow_mut().clear_player_list();
+        self.game_meta_view.borrow_mut().clear_chat_messages();
+
+        self.update_status();
+    }
+
     fn load_game(&mut self) {
         let dlg = gtk::FileChooserDialog::new(
             Some("Load game state"),
@@ -5,10 +5,10 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
-use crate::board::{BOARD_LINES, BoardIterator};
+use crate::board::{BoardType, PosType};
 use crate::coord;
 use crate::coord::{Coord, CoordAxis};
-use crate::game_state::{FieldState, GameState, MoveState, WinState};
+use crate::game_state::{FieldState, GameState, MoveState, Turn, WinState};
 use crate::gtk_helpers::*;
 use crate::print::Print;
 use anyhow as ah;
@@ -87,6 +87,7 @@ pub struct DrawingArea {
     game: Rc<RefCell<GameState>>,
     pending_join: bool,
     moving_token: MovingToken,
+    computer_side: Option<Turn>,
 }
 
 impl DrawingArea {
@@ -121,6 +122,7 @@ impl DrawingArea {
             game,
             pending_join: false,
             moving_token: MovingToken::NoToken,
+            computer_side: None,
         })
     }
 
@@ -166,6 +168,13 @@ impl DrawingArea {
     }
 
     pub fn redraw(&self) {
+        // Make room for the board of the current rules.
+        if let Ok(game) = self.game.try_borrow() {
+            let width = (pos2pix(&coord!(game.board().width() - 1, 0)).0 + XOFFS) as i32;
+            if self.widget.width_request() != width {
+                self.widget.set_width_request(width);
+            }
+        }
         self.widget.queue_draw();
     }
 
@@ -176,6 +185,12 @@ impl DrawingArea {
         }
     }
 
+    /// Set the side played by the computer.
+    /// The tokens of this side can't be moved by the mouse.
+    pub fn set_computer_side(&mut self, computer_side: Option<Turn>) {
+        self.computer_side = computer_side;
+    }
+
     fn draw_background(&self, cairo: &cairo::Context) {
         // Draw background.
         cairo.set_source_rgb(0.3, 0.44, 0.22);
@@ -188,6 +203,39 @@ impl DrawingArea {
         );
         cairo.fill().ok();
 
+        let board = self.game.borrow().board();
+        match board.board_type() {
+            BoardType::Muehle => self.draw_background_barn(cairo),
+            BoardType::Cross | BoardType::Fortress => {
+                // Draw the fortress around all barn positions.
+                let barn: Vec<Coord> = board
+                    .iter_pos()
+                    .filter(|(_, pos_type)| *pos_type == PosType::Barn)
+                    .map(|(coord, _)| coord)
+                    .collect();
+                if let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+                    barn.iter().map(|c| c.x).min(),
+                    barn.iter().map(|c| c.x).max(),
+                    barn.iter().map(|c| c.y).min(),
+                    barn.iter().map(|c| c.y).max(),
+                ) {
+                    cairo.set_source_rgb(0.35, 0.22, 0.15);
+                    let top_left = pos2pix(&coord!(min_x, min_y));
+                    let bottom_right = pos2pix(&coord!(max_x, max_y));
+                    let margin = POSDIST * 0.3;
+                    cairo.rectangle(
+                        top_left.0 - margin,
+                        top_left.1 - margin,
+                        bottom_right.0 - top_left.0 + margin * 2.0,
+                        bottom_right.1 - top_left.1 + margin * 2.0,
+                    );
+                    cairo.fill().ok();
+                }
+            }
+        }
+    }
+
+    fn draw_background_barn(&self, cairo: &cairo::Context) {
         // Draw sky.
         cairo.set_source_rgb(0.0, 0.49, 0.69);
         let pos = pos2pix(&coord!(0, 2));
@@ -223,7 +271,7 @@ impl DrawingArea {
     fn draw_board_lines(&self, cairo: &cairo::Context) {
         cairo.set_source_rgb(0.1, 0.1, 0.1);
         cairo.set_line_width(4.0);
-        for (from, to) in BOARD_LINES.iter() {
+        for (from, to) in self.game.borrow().board().lines().iter() {
             cairo.move_to(pos2pix(from).0, pos2pix(from).1);
             cairo.line_to(pos2pix(to).0, pos2pix(to).1);
         }
@@ -273,7 +321,7 @@ impl DrawingArea {
         let game = self.game.borrow();
 
         // Draw the board tokens.
-        for coord in BoardIterator::new() {
+        for coord in game.board().iter() {
             match game.get_field_state(coord) {
                 FieldState::Unused | FieldState::Empty => (),
                 FieldState::Wolf => {
@@ -303,12 +351,15 @@ impl DrawingArea {
     }
 
     fn draw_game_state(&self, cairo: &cairo::Context) {
-        let win_state = self.game.borrow().get_win_state();
+        let (win_state, reason) = self.game.borrow().get_result();
         if win_state != WinState::Undecided {
             cairo.set_source_rgb(1.0, 0.0, 0.0);
             cairo.set_font_size(40.0);
             cairo.select_font_face("Serif", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
-            let text = format!("{} won!", win_state);
+            let text = match win_state {
+                WinState::Draw => "Draw!".to_string(),
+                _ => format!("{} won!", win_state),
+            };
             if let Ok(extents) = cairo.text_extents(&text) {
                 cairo.move_to(
                     (self.widget.width() as f64 / 2.0) - (extents.width() / 2.0),
@@ -316,6 +367,19 @@ impl DrawingArea {
                 );
                 cairo.show_text(&text).ok();
             }
+
+            // Draw the reason below the result.
+            if let Some(reason) = reason {
+                cairo.set_font_size(20.0);
+                let text = format!("({})", reason);
+                if let Ok(extents) = cairo.text_extents(&text) {
+                    cairo.move_to(
+                        (self.widget.width() as f64 / 2.0) - (extents.width() / 2.0),
+                        (self.widget.height() as f64 / 2.0) + (extents.height() / 2.0) + 40.0,
+                    );
+                    cairo.show_text(&text).ok();
+                }
+            }
         }
     }
 
@@ -346,9 +410,32 @@ impl DrawingArea {
         }
     }
 
+    /// Change a board position in position setup mode.
+    /// The left button cycles through empty, sheep and wolf.
+    /// The right button removes the token.
+    fn setup_click(&mut self, pos: Coord, button: u32) {
+        let mut game = self.game.borrow_mut();
+        let state = match (button, game.get_field_state(pos)) {
+            (1, FieldState::Empty) => FieldState::Sheep,
+            (1, FieldState::Sheep) => FieldState::Wolf,
+            (1, FieldState::Wolf) | (3, _) => FieldState::Empty,
+            _ => return,
+        };
+        if let Err(e) = game.setup_set_field(pos, state) {
+            Print::error(&format!("Position setup failed: {}", e));
+        }
+    }
+
     #[allow(clippy::single_match)]
     #[allow(clippy::collapsible_else_if)]
     fn mousebutton(&mut self, x: f64, y: f64, button: u32, press: bool) {
+        if self.game.borrow().setup_is_active() {
+            if press && let Some(pos) = pix2pos(x, y) {
+                self.setup_click(pos, button);
+                self.redraw();
+            }
+            return;
+        }
         match button {
             1 => {
                 // left button
@@ -357,9 +444,11 @@ impl DrawingArea {
                     if let Some(pos) = pix2pos(x, y) {
                         if press {
                             if game.get_move_state() == MoveState::NoMove {
-                                match game.get_field_state(pos) {
-                                    FieldState::Unused | FieldState::Empty => (),
-                                    FieldState::Wolf | FieldState::Sheep => {
+                                match (game.get_field_state(pos), self.computer_side) {
+                                    (FieldState::Unused | FieldState::Empty, _)
+                                    | (FieldState::Wolf, Some(Turn::Wolf))
+                                    | (FieldState::Sheep, Some(Turn::Sheep)) => (),
+                                    (FieldState::Wolf | FieldState::Sheep, _) => {
                                         game.move_pick(pos).ok();
                                     }
                                 }
@@ -399,10 +488,100 @@ impl DrawingArea {
         self.redraw();
     }
 
+    pub fn setup(&mut self, active: bool) -> ah::Result<()> {
+        let result = if active {
+            self.game.borrow_mut().setup_begin()
+        } else {
+            self.game.borrow_mut().setup_end()
+        };
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
+    pub fn setup_clear(&mut self) -> ah::Result<()> {
+        let result = self.game.borrow_mut().setup_clear();
+        self.redraw();
+        result
+    }
+
+    pub fn end_capture_chain(&mut self) -> ah::Result<()> {
+        let result = self.game.borrow_mut().end_capture_chain();
+        self.redraw();
+        result
+    }
+
+    pub fn resign(&mut self, side: Turn) -> ah::Result<()> {
+        let result = self.game.borrow_mut().resign(side);
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
+    pub fn offer_draw(&mut self, side: Turn) -> ah::Result<()> {
+        let result = self.game.borrow_mut().offer_draw(side);
+        self.redraw();
+        result
+    }
+
+    pub fn answer_draw_offer(&mut self, side: Turn, accept: bool) -> ah::Result<()> {
+        let result = self.game.borrow_mut().answer_draw_offer(side, accept);
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
+    pub fn request_takeback(&mut self, side: Turn) -> ah::Result<()> {
+        let result = self.game.borrow_mut().request_takeback(side);
+        self.redraw();
+        result
+    }
+
+    pub fn answer_takeback(&mut self, side: Turn, accept: bool) -> ah::Result<()> {
+        let result = self.game.borrow_mut().answer_takeback(side, accept);
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
+    pub fn answer_room_vote(&mut self, accept: bool) -> ah::Result<()> {
+        let result = self.game.borrow_mut().answer_room_vote(accept);
+        self.redraw();
+        result
+    }
+
+    pub fn undo_move(&mut self) -> ah::Result<()> {
+        let result = self.game.borrow_mut().undo();
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
+    pub fn redo_move(&mut self) -> ah::Result<()> {
+        let result = self.game.borrow_mut().redo();
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
     pub fn load_game(&mut self, filename: &Path) -> ah::Result<()> {
         self.game.borrow_mut().load_game(filename)
     }
 
+    pub fn load_record(&mut self, filename: &Path) -> ah::Result<()> {
+        let result = self.game.borrow_mut().load_record(filename);
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
+    pub fn set_position_notation(&mut self, text: &str) -> ah::Result<()> {
+        let result = self.game.borrow_mut().set_position_notation(text);
+        self.moving_token = MovingToken::NoToken;
+        self.redraw();
+        result
+    }
+
     pub fn save_game(&self, filename: &Path) -> ah::Result<()> {
         self.game.borrow().save_game(filename)
     }
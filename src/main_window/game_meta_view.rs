@@ -9,6 +9,7 @@ use crate::game_state::GameState;
 use crate::gtk_helpers::*;
 use crate::player::{PlayerList, PlayerMode};
 use crate::print::Print;
+use anyhow as ah;
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -17,7 +18,7 @@ pub struct GameMetaView {
     roomlist_model: gtk::ListStore,
     playerlist_model: gtk::ListStore,
     displayed_playerlist: PlayerList,
-    displayed_roomlist: Vec<String>,
+    displayed_roomlist: Vec<(String, String, bool)>,
     player_name_entry: gtk::Entry,
     player_mode_combo: gtk::ComboBoxText,
     player_name_editing: bool,
@@ -36,15 +37,20 @@ impl GameMetaView {
         chat_say_entry: gtk::Entry,
     ) -> GameMetaView {
         // Room list
-        for i in 0..2 {
+        for i in 0..4 {
             let column = gtk::TreeViewColumn::new();
             let cell = gtk::CellRendererText::new();
             CellLayoutExt::pack_start(&column, &cell, true);
             column.add_attribute(&cell, "text", i);
-            column.set_title(["Room name", "joined"][i as usize]);
+            column.set_title(["Room name", "Rules", "Password", "joined"][i as usize]);
             room_tree_view.append_column(&column);
         }
-        let roomlist_model = gtk::ListStore::new(&[String::static_type(), String::static_type()]);
+        let roomlist_model = gtk::ListStore::new(&[
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+        ]);
         room_tree_view.set_model(Some(&roomlist_model));
 
         // Player list
@@ -157,9 +163,7 @@ impl GameMetaView {
     pub fn connect_room_tree_signal(gmv: &Rc<RefCell<GameMetaView>>, room_tree: &gtk::TreeView) {
         let gmv2 = Rc::clone(gmv);
         room_tree.connect_row_activated(move |_tree_view, path, _column| {
-            if let Ok(mut g) = gmv2.try_borrow_mut() {
-                g.handle_join_room_req(path);
-            }
+            GameMetaView::handle_join_room_req(&gmv2, path);
         });
     }
 
@@ -213,10 +217,10 @@ impl GameMetaView {
         }
     }
 
-    pub fn update_room_list(&mut self, room_list: &Vec<String>) {
+    pub fn update_room_list(&mut self, room_list: &Vec<(String, String, bool)>) {
         if self.displayed_roomlist != *room_list {
             self.roomlist_model.clear();
-            for room_name in room_list {
+            for (room_name, rules_name, protected) in room_list {
                 let is_joined_room = match self.game.borrow().client_get_joined_room() {
                     Some(r) => r == room_name,
                     None => false,
@@ -226,7 +230,9 @@ impl GameMetaView {
                     None,
                     &[
                         (0, &room_name),
-                        (1, &if is_joined_room { "<---" } else { "" }),
+                        (1, &rules_name),
+                        (2, &if *protected { "yes" } else { "" }),
+                        (3, &if is_joined_room { "<---" } else { "" }),
                     ],
                 );
             }
@@ -234,27 +240,63 @@ impl GameMetaView {
         }
     }
 
-    fn handle_join_room_req(&mut self, tree_path: &gtk::TreePath) {
+    fn handle_join_room_req(gmv: &Rc<RefCell<GameMetaView>>, tree_path: &gtk::TreePath) {
         let index = tree_path.indices()[0];
-        if (index as usize) < self.displayed_roomlist.len() {
-            let room_name = &self.displayed_roomlist[index as usize].to_string();
+        let (room_name, protected) = {
+            let Ok(g) = gmv.try_borrow() else {
+                return;
+            };
+            let Some((room_name, _, protected)) = g.displayed_roomlist.get(index as usize) else {
+                return;
+            };
+            if let Some(joined_room) = g.game.borrow().client_get_joined_room()
+                && joined_room == room_name
             {
-                let mut game = self.game.borrow_mut();
+                return;
+            }
+            (room_name.clone(), *protected)
+        };
+
+        if protected {
+            Self::join_protected_room(gmv, &room_name);
+        } else if let Ok(mut g) = gmv.try_borrow_mut()
+            && let Err(e) = g.join_room(&room_name, "")
+        {
+            Print::error(&format!("Failed to join room: {}", e));
+        }
+    }
 
-                if let Some(joined_room) = game.client_get_joined_room()
-                    && joined_room == room_name
+    /// Ask for the password of a room and join it.
+    pub fn join_protected_room(gmv: &Rc<RefCell<GameMetaView>>, room_name: &str) {
+        let parent = {
+            let Ok(g) = gmv.try_borrow() else {
+                return;
+            };
+            g.chat_text
+                .root()
+                .and_then(|root| root.downcast::<gtk::Window>().ok())
+        };
+        let gmv2 = Rc::clone(gmv);
+        let parent2 = parent.clone();
+        let room_name2 = room_name.to_string();
+        password_dialog(
+            parent.as_ref(),
+            &format!("Room '{}' needs a password:", room_name),
+            move |password| {
+                if let Ok(mut g) = gmv2.try_borrow_mut()
+                    && let Err(e) = g.join_room(&room_name2, password)
                 {
-                    return;
+                    messagebox_error(parent2.as_ref(), &format!("Failed to join room:\n{}", e));
                 }
+            },
+        );
+    }
 
-                let result = game.client_join_room(room_name);
-                if let Err(e) = result {
-                    Print::error(&format!("Failed to join room: {}", e));
-                }
-            }
-            self.clear_chat_messages();
-            self.displayed_roomlist.clear();
-        }
+    fn join_room(&mut self, room_name: &str, password: &str) -> ah::Result<()> {
+        let result = self.game.borrow_mut().client_join_room(room_name, password);
+        self.clear_chat_messages();
+        self.displayed_roomlist.clear();
+        result
     }
 
     fn playername_changed(&mut self) {
@@ -6,9 +6,10 @@
 //
 
 use crate::net::protocol::{
-    MSG_BUFFER_SIZE, Message, MsgJoin, MsgLeave, MsgMove, MsgNop, MsgPing, MsgRecord,
-    MsgReqGameState, MsgReqPlayerList, MsgReqRecord, MsgReqRoomList, MsgReset, MsgSay, MsgType,
-    buffer_skip, message_from_bytes, net_sync,
+    MSG_BUFFER_SIZE, Message, MsgCloseRoom, MsgCreateRoom, MsgDrawOffer, MsgJoin, MsgLeave,
+    MsgMove, MsgNop, MsgPing, MsgRecord, MsgReqGameState, MsgReqPlayerList, MsgReqRecord,
+    MsgReqRoomList, MsgReset, MsgResign, MsgSay, MsgTakeback, MsgType, MsgVote, buffer_skip,
+    message_from_bytes, net_sync,
 };
 use crate::player::{PlayerMode, player_mode_to_num};
 use crate::print::Print;
@@ -148,11 +149,17 @@ impl Client {
         room_name: &str,
         player_name: &str,
         player_mode: PlayerMode,
+        password: &str,
     ) -> ah::Result<()> {
         self.send_msg_wait_for_ok(
             "join",
             3.0,
-            &mut MsgJoin::new(room_name, player_name, player_mode_to_num(player_mode))?,
+            &mut MsgJoin::new(
+                room_name,
+                player_name,
+                player_mode_to_num(player_mode),
+                password,
+            )?,
         )?;
         Ok(())
     }
@@ -163,6 +170,27 @@ impl Client {
         Ok(())
     }
 
+    /// Send a CreateRoom message to the server and wait for the result.
+    pub fn send_create_room(
+        &mut self,
+        room_name: &str,
+        rules_name: &str,
+        password: &str,
+    ) -> ah::Result<()> {
+        self.send_msg_wait_for_ok(
+            "create room",
+            3.0,
+            &mut MsgCreateRoom::new(room_name, rules_name, password)?,
+        )?;
+        Ok(())
+    }
+
+    /// Send a CloseRoom message to the server and wait for the result.
+    pub fn send_close_room(&mut self, room_name: &str) -> ah::Result<()> {
+        self.send_msg_wait_for_ok("close room", 3.0, &mut MsgCloseRoom::new(room_name)?)?;
+        Ok(())
+    }
+
     /// Send a Reset message to the server and wait for the result.
     pub fn send_reset(&mut self) -> ah::Result<()> {
         self.send_msg_wait_for_ok("reset", 3.0, &mut MsgReset::new())?;
@@ -249,6 +277,30 @@ impl Client {
         Ok(())
     }
 
+    /// Send a Resign message to the server and wait for the result.
+    pub fn send_resign(&mut self, turn: u32) -> ah::Result<()> {
+        self.send_msg_wait_for_ok("resign", 1.0, &mut MsgResign::new(turn, "")?)?;
+        Ok(())
+    }
+
+    /// Send a DrawOffer message to the server and wait for the result.
+    pub fn send_draw_offer(&mut self, action: u32, turn: u32) -> ah::Result<()> {
+        self.send_msg_wait_for_ok("draw offer", 1.0, &mut MsgDrawOffer::new(action, turn, "")?)?;
+        Ok(())
+    }
+
+    /// Send a Takeback message to the server and wait for the result.
+    pub fn send_takeback(&mut self, action: u32, turn: u32) -> ah::Result<()> {
+        self.send_msg_wait_for_ok("takeback", 1.0, &mut MsgTakeback::new(action, turn, "")?)?;
+        Ok(())
+    }
+
+    /// Send a Vote message to the server and wait for the result.
+    pub fn send_vote(&mut self, action: u32, subject: u32) -> ah::Result<()> {
+        self.send_msg_wait_for_ok("vote", 1.0, &mut MsgVote::new(action, subject, 0, "")?)?;
+        Ok(())
+    }
+
     /// Poll the received messages.
     pub fn poll(&mut self) -> Option<Vec<Box<dyn Message>>> {
         let mut rx_queue = match self.rx_queue.take() {
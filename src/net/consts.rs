@@ -5,7 +5,13 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
+use std::time::Duration;
+
 pub const MAX_PLAYERS: usize = 1024;
 pub const MAX_ROOMS: usize = 1024 * 4;
+/// The default maximum number of rooms that the clients from one address may create.
+pub const DEFAULT_MAX_CREATED_ROOMS: usize = 4;
+/// The default time after which an empty room that has been created by a client is closed.
+pub const DEFAULT_ABANDONED_ROOM_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
 // vim: ts=4 sw=4 expandtab
@@ -17,8 +17,13 @@ pub const MSG_PLAYERMODE_WOLF: u32 = 1;
 pub const MSG_PLAYERMODE_SHEEP: u32 = 2;
 pub const MSG_PLAYERMODE_BOTH: u32 = 3;
 
-const MSG_MAXROOMNAME: usize = 64;
+pub const MSG_MAXROOMNAME: usize = 64;
 const MSG_MAXPLAYERNAME: usize = 64;
+const MSG_MAXRULESNAME: usize = 64;
+pub const MSG_MAXPASSWORD: usize = 64;
+
+/// The room needs a password to join.
+pub const MSG_ROOM_FLAG_PASSWORD: u32 = 1 << 0;
 
 const MSG_MAGIC: u32 = 0xAA0E1F37;
 
@@ -39,6 +44,12 @@ const MSG_ID_MOVE: u32 = 13;
 const MSG_ID_SAY: u32 = 14;
 const MSG_ID_REQRECORD: u32 = 15;
 const MSG_ID_RECORD: u32 = 16;
+const MSG_ID_RESIGN: u32 = 17;
+const MSG_ID_DRAWOFFER: u32 = 18;
+const MSG_ID_TAKEBACK: u32 = 19;
+const MSG_ID_VOTE: u32 = 20;
+const MSG_ID_CREATEROOM: u32 = 21;
+const MSG_ID_CLOSEROOM: u32 = 22;
 
 type FieldsArray = [[u32; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
 
@@ -63,6 +74,12 @@ pub enum MsgType<'a> {
     Say(&'a MsgSay),
     ReqRecord(&'a MsgReqRecord),
     Record(&'a MsgRecord),
+    Resign(&'a MsgResign),
+    DrawOffer(&'a MsgDrawOffer),
+    Takeback(&'a MsgTakeback),
+    Vote(&'a MsgVote),
+    CreateRoom(&'a MsgCreateRoom),
+    CloseRoom(&'a MsgCloseRoom),
 }
 
 pub trait Message {
@@ -132,6 +149,12 @@ pub fn message_from_bytes(data: &[u8]) -> ah::Result<(usize, Option<Box<dyn Mess
         MSG_ID_SAY => MsgSay::from_bytes(header, &data[offset..])?,
         MSG_ID_REQRECORD => MsgReqRecord::from_bytes(header, &data[offset..])?,
         MSG_ID_RECORD => MsgRecord::from_bytes(header, &data[offset..])?,
+        MSG_ID_RESIGN => MsgResign::from_bytes(header, &data[offset..])?,
+        MSG_ID_DRAWOFFER => MsgDrawOffer::from_bytes(header, &data[offset..])?,
+        MSG_ID_TAKEBACK => MsgTakeback::from_bytes(header, &data[offset..])?,
+        MSG_ID_VOTE => MsgVote::from_bytes(header, &data[offset..])?,
+        MSG_ID_CREATEROOM => MsgCreateRoom::from_bytes(header, &data[offset..])?,
+        MSG_ID_CLOSEROOM => MsgCloseRoom::from_bytes(header, &data[offset..])?,
         _ => {
             return Err(ah::format_err!(
                 "from_bytes: Unknown ID ({}).",
@@ -436,17 +459,29 @@ pub struct MsgJoin {
     player_name_len: u32,
     player_name: [u8; MSG_MAXPLAYERNAME],
     player_mode: u32,
+    password_len: u32,
+    password: [u8; MSG_MAXPASSWORD],
 }
 
-const MSG_JOIN_SIZE: u32 =
+const MSG_JOIN_BASE_SIZE: u32 =
     MSG_HEADER_SIZE + 4 + MSG_MAXROOMNAME as u32 + 4 + MSG_MAXPLAYERNAME as u32 + 4;
+const MSG_JOIN_SIZE: u32 = MSG_JOIN_BASE_SIZE + 4 + MSG_MAXPASSWORD as u32;
 
 impl MsgJoin {
-    pub fn new(room_name: &str, player_name: &str, player_mode: u32) -> ah::Result<MsgJoin> {
+    /// Create a new join message.
+    /// An empty password joins a room that is not password protected.
+    pub fn new(
+        room_name: &str,
+        player_name: &str,
+        player_mode: u32,
+        password: &str,
+    ) -> ah::Result<MsgJoin> {
         let mut room_name_bytes = [0; MSG_MAXROOMNAME];
         let room_name_len = room_name.to_net(&mut room_name_bytes, false)? as u32;
         let mut player_name_bytes = [0; MSG_MAXPLAYERNAME];
         let player_name_len = player_name.to_net(&mut player_name_bytes, false)? as u32;
+        let mut password_bytes = [0; MSG_MAXPASSWORD];
+        let password_len = password.to_net(&mut password_bytes, false)? as u32;
         Ok(MsgJoin {
             header: MsgHeader::new(MSG_MAGIC, MSG_JOIN_SIZE, MSG_ID_JOIN, 0),
             room_name_len,
@@ -454,11 +489,13 @@ impl MsgJoin {
             player_name_len,
             player_name: player_name_bytes,
             player_mode,
+            password_len,
+            password: password_bytes,
         })
     }
 
     pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
-        if data.len() >= (MSG_JOIN_SIZE - MSG_HEADER_SIZE) as usize {
+        if data.len() >= (MSG_JOIN_BASE_SIZE - MSG_HEADER_SIZE) as usize {
             let offset = 0;
 
             let (room_name_len, room_name, offset) = extract_str!(MSG_MAXROOMNAME, data, offset);
@@ -466,6 +503,17 @@ impl MsgJoin {
                 extract_str!(MSG_MAXPLAYERNAME, data, offset);
             let player_mode = u32::from_net(&data[offset..])?;
             offset += 4;
+            assert_eq!(offset, (MSG_JOIN_BASE_SIZE - MSG_HEADER_SIZE) as usize);
+
+            // The password.
+            // Messages from older clients don't have it.
+            let (password_len, password, offset) = if header.get_size() >= MSG_JOIN_SIZE
+                && data.len() >= (MSG_JOIN_SIZE - MSG_HEADER_SIZE) as usize
+            {
+                extract_str!(MSG_MAXPASSWORD, data, offset)
+            } else {
+                (0, [0; MSG_MAXPASSWORD], offset)
+            };
 
             let msg = MsgJoin {
                 header,
@@ -474,8 +522,9 @@ impl MsgJoin {
                 player_name_len,
                 player_name,
                 player_mode,
+                password_len,
+                password,
             };
-            assert_eq!(offset, (MSG_JOIN_SIZE - MSG_HEADER_SIZE) as usize);
             Ok((offset, Box::new(msg)))
         } else {
             Err(ah::format_err!("MsgJoin: Not enough data."))
@@ -493,6 +542,12 @@ impl MsgJoin {
     pub fn get_player_mode(&self) -> u32 {
         self.player_mode
     }
+
+    /// Get the password to join the room.
+    /// Returns an empty string, if the client did not send one.
+    pub fn get_password(&self) -> ah::Result<String> {
+        String::from_net(&self.password, self.password_len as usize, false)
+    }
 }
 
 impl Message for MsgJoin {
@@ -506,6 +561,8 @@ impl Message for MsgJoin {
         data.extend_from_slice(&self.player_name_len.to_net());
         data.extend_from_slice(&self.player_name);
         data.extend_from_slice(&self.player_mode.to_net());
+        data.extend_from_slice(&self.password_len.to_net());
+        data.extend_from_slice(&self.password);
         assert_eq!(data.len(), MSG_JOIN_SIZE as usize);
         data
     }
@@ -523,10 +580,23 @@ pub struct MsgGameState {
     moving_x: u32,
     moving_y: u32,
     turn: u32,
+    chain_valid: u32,
+    chain_x: u32,
+    chain_y: u32,
+    result: u32,
+    reason: u32,
 }
 
-const MSG_GAME_STATE_SIZE: u32 =
-    MSG_HEADER_SIZE + (BOARD_WIDTH as u32 * BOARD_HEIGHT as u32 * 4) + (4 * 4);
+/// The number of board columns in the original message layout.
+/// The remaining columns are appended after the original message payload.
+const MSG_GAME_STATE_BASE_WIDTH: usize = 5;
+
+const MSG_GAME_STATE_BASE_SIZE: u32 =
+    MSG_HEADER_SIZE + (MSG_GAME_STATE_BASE_WIDTH as u32 * BOARD_HEIGHT as u32 * 4) + (4 * 4);
+const MSG_GAME_STATE_WIDE_SIZE: u32 = MSG_GAME_STATE_BASE_SIZE
+    + ((BOARD_WIDTH as u32 - MSG_GAME_STATE_BASE_WIDTH as u32) * BOARD_HEIGHT as u32 * 4);
+const MSG_GAME_STATE_CHAIN_SIZE: u32 = MSG_GAME_STATE_WIDE_SIZE + (3 * 4);
+const MSG_GAME_STATE_SIZE: u32 = MSG_GAME_STATE_CHAIN_SIZE + (2 * 4);
 
 const MSG_FIELD_INVALID: u32 = 0;
 
@@ -537,7 +607,10 @@ impl MsgGameState {
         moving_x: u32,
         moving_y: u32,
         turn: u32,
+        capture_chain: Option<(u32, u32)>,
+        result: (u32, u32),
     ) -> MsgGameState {
+        let (chain_x, chain_y) = capture_chain.unwrap_or((0, 0));
         MsgGameState {
             header: MsgHeader::new(MSG_MAGIC, MSG_GAME_STATE_SIZE, MSG_ID_GAMESTATE, 0),
             fields,
@@ -545,17 +618,22 @@ impl MsgGameState {
             moving_x,
             moving_y,
             turn,
+            chain_valid: capture_chain.is_some() as u32,
+            chain_x,
+            chain_y,
+            result: result.0,
+            reason: result.1,
         }
     }
 
     #[allow(clippy::needless_range_loop)]
     pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
-        if data.len() >= (MSG_GAME_STATE_SIZE - MSG_HEADER_SIZE) as usize {
+        if data.len() >= (MSG_GAME_STATE_BASE_SIZE - MSG_HEADER_SIZE) as usize {
             let mut offset = 0;
 
             let mut fields = [[MSG_FIELD_INVALID; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
             for y in 0..(BOARD_HEIGHT as usize) {
-                for x in 0..(BOARD_WIDTH as usize) {
+                for x in 0..MSG_GAME_STATE_BASE_WIDTH {
                     fields[y][x] = u32::from_net(&data[offset..])?;
                     offset += 4;
                 }
@@ -568,6 +646,49 @@ impl MsgGameState {
             offset += 4;
             let turn = u32::from_net(&data[offset..])?;
             offset += 4;
+            assert_eq!(
+                offset,
+                (MSG_GAME_STATE_BASE_SIZE - MSG_HEADER_SIZE) as usize
+            );
+
+            // The columns of wider boards.
+            // Messages from older peers don't have them.
+            if header.get_size() >= MSG_GAME_STATE_WIDE_SIZE
+                && data.len() >= (MSG_GAME_STATE_WIDE_SIZE - MSG_HEADER_SIZE) as usize
+            {
+                for y in 0..(BOARD_HEIGHT as usize) {
+                    for x in MSG_GAME_STATE_BASE_WIDTH..(BOARD_WIDTH as usize) {
+                        fields[y][x] = u32::from_net(&data[offset..])?;
+                        offset += 4;
+                    }
+                }
+            }
+
+            // The wolf that continues a capture chain.
+            // Messages from older peers don't have it.
+            let (mut chain_valid, mut chain_x, mut chain_y) = (0, 0, 0);
+            if header.get_size() >= MSG_GAME_STATE_CHAIN_SIZE
+                && data.len() >= (MSG_GAME_STATE_CHAIN_SIZE - MSG_HEADER_SIZE) as usize
+            {
+                chain_valid = u32::from_net(&data[offset..])?;
+                offset += 4;
+                chain_x = u32::from_net(&data[offset..])?;
+                offset += 4;
+                chain_y = u32::from_net(&data[offset..])?;
+                offset += 4;
+            }
+
+            // The game result and its reason.
+            // Messages from older peers don't have it.
+            let (mut result, mut reason) = (0, 0);
+            if header.get_size() >= MSG_GAME_STATE_SIZE
+                && data.len() >= (MSG_GAME_STATE_SIZE - MSG_HEADER_SIZE) as usize
+            {
+                result = u32::from_net(&data[offset..])?;
+                offset += 4;
+                reason = u32::from_net(&data[offset..])?;
+                offset += 4;
+            }
 
             let msg = MsgGameState {
                 header,
@@ -576,8 +697,12 @@ impl MsgGameState {
                 moving_x,
                 moving_y,
                 turn,
+                chain_valid,
+                chain_x,
+                chain_y,
+                result,
+                reason,
             };
-            assert_eq!(offset, (MSG_GAME_STATE_SIZE - MSG_HEADER_SIZE) as usize);
             Ok((offset, Box::new(msg)))
         } else {
             Err(ah::format_err!("MsgGameState: Not enough data."))
@@ -595,6 +720,18 @@ impl MsgGameState {
     pub fn get_turn(&self) -> u32 {
         self.turn
     }
+
+    pub fn get_result(&self) -> (u32, u32) {
+        (self.result, self.reason)
+    }
+
+    pub fn get_capture_chain(&self) -> Option<(u32, u32)> {
+        if self.chain_valid != 0 {
+            Some((self.chain_x, self.chain_y))
+        } else {
+            None
+        }
+    }
 }
 
 impl Message for MsgGameState {
@@ -604,7 +741,7 @@ impl Message for MsgGameState {
         let mut data = Vec::with_capacity(MSG_GAME_STATE_SIZE as usize);
         self.header.to_bytes(&mut data);
         for y in 0..(BOARD_HEIGHT as usize) {
-            for x in 0..(BOARD_WIDTH as usize) {
+            for x in 0..MSG_GAME_STATE_BASE_WIDTH {
                 data.extend_from_slice(&self.fields[y][x].to_net());
             }
         }
@@ -612,6 +749,16 @@ impl Message for MsgGameState {
         data.extend_from_slice(&self.moving_x.to_net());
         data.extend_from_slice(&self.moving_y.to_net());
         data.extend_from_slice(&self.turn.to_net());
+        for y in 0..(BOARD_HEIGHT as usize) {
+            for x in MSG_GAME_STATE_BASE_WIDTH..(BOARD_WIDTH as usize) {
+                data.extend_from_slice(&self.fields[y][x].to_net());
+            }
+        }
+        data.extend_from_slice(&self.chain_valid.to_net());
+        data.extend_from_slice(&self.chain_x.to_net());
+        data.extend_from_slice(&self.chain_y.to_net());
+        data.extend_from_slice(&self.result.to_net());
+        data.extend_from_slice(&self.reason.to_net());
         assert_eq!(data.len(), MSG_GAME_STATE_SIZE as usize);
         data
     }
@@ -731,25 +878,41 @@ pub struct MsgRoomList {
     index: u32,
     room_name_len: u32,
     room_name: [u8; MSG_MAXROOMNAME],
+    rules_name_len: u32,
+    rules_name: [u8; MSG_MAXRULESNAME],
+    flags: u32,
 }
 
-const MSG_ROOM_LIST_SIZE: u32 = MSG_HEADER_SIZE + (3 * 4) + MSG_MAXROOMNAME as u32;
+const MSG_ROOM_LIST_BASE_SIZE: u32 = MSG_HEADER_SIZE + (3 * 4) + MSG_MAXROOMNAME as u32;
+const MSG_ROOM_LIST_RULES_SIZE: u32 = MSG_ROOM_LIST_BASE_SIZE + 4 + MSG_MAXRULESNAME as u32;
+const MSG_ROOM_LIST_SIZE: u32 = MSG_ROOM_LIST_RULES_SIZE + 4;
 
 impl MsgRoomList {
-    pub fn new(total_count: u32, index: u32, room_name: &str) -> ah::Result<MsgRoomList> {
+    pub fn new(
+        total_count: u32,
+        index: u32,
+        room_name: &str,
+        rules_name: &str,
+        flags: u32,
+    ) -> ah::Result<MsgRoomList> {
         let mut room_name_bytes = [0; MSG_MAXROOMNAME];
         let room_name_len = room_name.to_net(&mut room_name_bytes, false)? as u32;
+        let mut rules_name_bytes = [0; MSG_MAXRULESNAME];
+        let rules_name_len = rules_name.to_net(&mut rules_name_bytes, false)? as u32;
         Ok(MsgRoomList {
             header: MsgHeader::new(MSG_MAGIC, MSG_ROOM_LIST_SIZE, MSG_ID_ROOMLIST, 0),
             total_count,
             index,
             room_name_len,
             room_name: room_name_bytes,
+            rules_name_len,
+            rules_name: rules_name_bytes,
+            flags,
         })
     }
 
     pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
-        if data.len() >= (MSG_ROOM_LIST_SIZE - MSG_HEADER_SIZE) as usize {
+        if data.len() >= (MSG_ROOM_LIST_BASE_SIZE - MSG_HEADER_SIZE) as usize {
             let mut offset = 0;
 
             let total_count = u32::from_net(&data[offset..])?;
@@ -757,6 +920,30 @@ impl MsgRoomList {
             let index = u32::from_net(&data[offset..])?;
             offset += 4;
             let (room_name_len, room_name, offset) = extract_str!(MSG_MAXROOMNAME, data, offset);
+            assert_eq!(offset, (MSG_ROOM_LIST_BASE_SIZE - MSG_HEADER_SIZE) as usize);
+
+            // The rules name.
+            // Messages from older servers don't have it.
+            let (rules_name_len, rules_name, mut offset) = if header.get_size()
+                >= MSG_ROOM_LIST_RULES_SIZE
+                && data.len() >= (MSG_ROOM_LIST_RULES_SIZE - MSG_HEADER_SIZE) as usize
+            {
+                extract_str!(MSG_MAXRULESNAME, data, offset)
+            } else {
+                (0, [0; MSG_MAXRULESNAME], offset)
+            };
+
+            // The room flags.
+            // Messages from older servers don't have them.
+            let flags = if header.get_size() >= MSG_ROOM_LIST_SIZE
+                && data.len() >= (MSG_ROOM_LIST_SIZE - MSG_HEADER_SIZE) as usize
+            {
+                let flags = u32::from_net(&data[offset..])?;
+                offset += 4;
+                flags
+            } else {
+                0
+            };
 
             let msg = MsgRoomList {
                 header,
@@ -764,8 +951,10 @@ impl MsgRoomList {
                 index,
                 room_name_len,
                 room_name,
+                rules_name_len,
+                rules_name,
+                flags,
             };
-            assert_eq!(offset, (MSG_ROOM_LIST_SIZE - MSG_HEADER_SIZE) as usize);
             Ok((offset, Box::new(msg)))
         } else {
             Err(ah::format_err!("MsgRoomList: Not enough data."))
@@ -783,6 +972,17 @@ impl MsgRoomList {
     pub fn get_room_name(&self) -> ah::Result<String> {
         String::from_net(&self.room_name, self.room_name_len as usize, false)
     }
+
+    /// Get the name of the rule variant played in the room.
+    /// Returns an empty string, if the server did not send it.
+    pub fn get_rules_name(&self) -> ah::Result<String> {
+        String::from_net(&self.rules_name, self.rules_name_len as usize, false)
+    }
+
+    /// Get the MSG_ROOM_FLAG_... bits of the room.
+    pub fn get_flags(&self) -> u32 {
+        self.flags
+    }
 }
 
 impl Message for MsgRoomList {
@@ -795,6 +995,9 @@ impl Message for MsgRoomList {
         data.extend_from_slice(&self.index.to_net());
         data.extend_from_slice(&self.room_name_len.to_net());
         data.extend_from_slice(&self.room_name);
+        data.extend_from_slice(&self.rules_name_len.to_net());
+        data.extend_from_slice(&self.rules_name);
+        data.extend_from_slice(&self.flags.to_net());
         assert_eq!(data.len(), MSG_ROOM_LIST_SIZE as usize);
         data
     }
@@ -915,6 +1118,7 @@ pub const MSG_MOVE_ACTION_PICK: u32 = 0;
 pub const MSG_MOVE_ACTION_MOVE: u32 = 1;
 pub const MSG_MOVE_ACTION_PUT: u32 = 2;
 pub const MSG_MOVE_ACTION_ABORT: u32 = 3;
+pub const MSG_MOVE_ACTION_END_CHAIN: u32 = 4;
 
 pub const MSG_MOVE_TOKEN_CURRENT: u32 = 0;
 pub const MSG_MOVE_TOKEN_WOLF: u32 = 1;
@@ -1067,4 +1271,724 @@ impl Message for MsgSay {
     }
 }
 
+//////////////////////////////////////////////////////////////////////////////
+// MsgResign
+//////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct MsgResign {
+    header: MsgHeader,
+    turn: u32,
+    player_name_len: u32,
+    player_name: [u8; MSG_MAXPLAYERNAME],
+}
+
+const MSG_RESIGN_SIZE: u32 = MSG_HEADER_SIZE + 4 + 4 + MSG_MAXPLAYERNAME as u32;
+
+impl MsgResign {
+    pub fn new(turn: u32, player_name: &str) -> ah::Result<MsgResign> {
+        let mut player_name_bytes = [0; MSG_MAXPLAYERNAME];
+        let player_name_len = player_name.to_net(&mut player_name_bytes, true)?;
+        Ok(MsgResign {
+            header: MsgHeader::new(MSG_MAGIC, MSG_RESIGN_SIZE, MSG_ID_RESIGN, 0),
+            turn,
+            player_name_len: player_name_len as u32,
+            player_name: player_name_bytes,
+        })
+    }
+
+    pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
+        if data.len() >= (MSG_RESIGN_SIZE - MSG_HEADER_SIZE) as usize {
+            let mut offset = 0;
+
+            let turn = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let (player_name_len, player_name, offset) =
+                extract_str!(MSG_MAXPLAYERNAME, data, offset);
+
+            let msg = MsgResign {
+                header,
+                turn,
+                player_name_len,
+                player_name,
+            };
+            assert_eq!(offset, (MSG_RESIGN_SIZE - MSG_HEADER_SIZE) as usize);
+            Ok((offset, Box::new(msg)))
+        } else {
+            Err(ah::format_err!("MsgResign: Not enough data."))
+        }
+    }
+
+    /// Get the side that resigns.
+    pub fn get_turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn set_player_name(&mut self, player_name: &str) -> ah::Result<()> {
+        self.player_name_len = player_name.to_net(&mut self.player_name, true)? as u32;
+        Ok(())
+    }
+
+    pub fn get_player_name(&self) -> String {
+        match String::from_net(&self.player_name, self.player_name_len as usize, true) {
+            Ok(m) => m,
+            Err(_) => "Failed to parse MsgResign.".to_string(),
+        }
+    }
+}
+
+impl Message for MsgResign {
+    msg_trait_define_common!(Resign);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MSG_RESIGN_SIZE as usize);
+        self.header.to_bytes(&mut data);
+        data.extend_from_slice(&self.turn.to_net());
+        data.extend_from_slice(&self.player_name_len.to_net());
+        data.extend_from_slice(&self.player_name);
+        assert_eq!(data.len(), MSG_RESIGN_SIZE as usize);
+        data
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// MsgDrawOffer
+//////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct MsgDrawOffer {
+    header: MsgHeader,
+    action: u32,
+    turn: u32,
+    player_name_len: u32,
+    player_name: [u8; MSG_MAXPLAYERNAME],
+}
+
+const MSG_DRAWOFFER_SIZE: u32 = MSG_HEADER_SIZE + (3 * 4) + MSG_MAXPLAYERNAME as u32;
+
+pub const MSG_DRAWOFFER_ACTION_OFFER: u32 = 0;
+pub const MSG_DRAWOFFER_ACTION_ACCEPT: u32 = 1;
+pub const MSG_DRAWOFFER_ACTION_DECLINE: u32 = 2;
+
+impl MsgDrawOffer {
+    pub fn new(action: u32, turn: u32, player_name: &str) -> ah::Result<MsgDrawOffer> {
+        let mut player_name_bytes = [0; MSG_MAXPLAYERNAME];
+        let player_name_len = player_name.to_net(&mut player_name_bytes, true)?;
+        Ok(MsgDrawOffer {
+            header: MsgHeader::new(MSG_MAGIC, MSG_DRAWOFFER_SIZE, MSG_ID_DRAWOFFER, 0),
+            action,
+            turn,
+            player_name_len: player_name_len as u32,
+            player_name: player_name_bytes,
+        })
+    }
+
+    pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
+        if data.len() >= (MSG_DRAWOFFER_SIZE - MSG_HEADER_SIZE) as usize {
+            let mut offset = 0;
+
+            let action = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let turn = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let (player_name_len, player_name, offset) =
+                extract_str!(MSG_MAXPLAYERNAME, data, offset);
+
+            let msg = MsgDrawOffer {
+                header,
+                action,
+                turn,
+                player_name_len,
+                player_name,
+            };
+            assert_eq!(offset, (MSG_DRAWOFFER_SIZE - MSG_HEADER_SIZE) as usize);
+            Ok((offset, Box::new(msg)))
+        } else {
+            Err(ah::format_err!("MsgDrawOffer: Not enough data."))
+        }
+    }
+
+    /// Get the action and the side that offers, accepts or declines the draw.
+    pub fn get_action(&self) -> (u32, u32) {
+        (self.action, self.turn)
+    }
+
+    pub fn set_player_name(&mut self, player_name: &str) -> ah::Result<()> {
+        self.player_name_len = player_name.to_net(&mut self.player_name, true)? as u32;
+        Ok(())
+    }
+
+    pub fn get_player_name(&self) -> String {
+        match String::from_net(&self.player_name, self.player_name_len as usize, true) {
+            Ok(m) => m,
+            Err(_) => "Failed to parse MsgDrawOffer.".to_string(),
+        }
+    }
+}
+
+impl Message for MsgDrawOffer {
+    msg_trait_define_common!(DrawOffer);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MSG_DRAWOFFER_SIZE as usize);
+        self.header.to_bytes(&mut data);
+        data.extend_from_slice(&self.action.to_net());
+        data.extend_from_slice(&self.turn.to_net());
+        data.extend_from_slice(&self.player_name_len.to_net());
+        data.extend_from_slice(&self.player_name);
+        assert_eq!(data.len(), MSG_DRAWOFFER_SIZE as usize);
+        data
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// MsgTakeback
+//////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct MsgTakeback {
+    header: MsgHeader,
+    action: u32,
+    turn: u32,
+    player_name_len: u32,
+    player_name: [u8; MSG_MAXPLAYERNAME],
+}
+
+const MSG_TAKEBACK_SIZE: u32 = MSG_HEADER_SIZE + (3 * 4) + MSG_MAXPLAYERNAME as u32;
+
+pub const MSG_TAKEBACK_ACTION_REQUEST: u32 = 0;
+pub const MSG_TAKEBACK_ACTION_ACCEPT: u32 = 1;
+pub const MSG_TAKEBACK_ACTION_DECLINE: u32 = 2;
+
+impl MsgTakeback {
+    pub fn new(action: u32, turn: u32, player_name: &str) -> ah::Result<MsgTakeback> {
+        let mut player_name_bytes = [0; MSG_MAXPLAYERNAME];
+        let player_name_len = player_name.to_net(&mut player_name_bytes, true)?;
+        Ok(MsgTakeback {
+            header: MsgHeader::new(MSG_MAGIC, MSG_TAKEBACK_SIZE, MSG_ID_TAKEBACK, 0),
+            action,
+            turn,
+            player_name_len: player_name_len as u32,
+            player_name: player_name_bytes,
+        })
+    }
+
+    pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
+        if data.len() >= (MSG_TAKEBACK_SIZE - MSG_HEADER_SIZE) as usize {
+            let mut offset = 0;
+
+            let action = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let turn = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let (player_name_len, player_name, offset) =
+                extract_str!(MSG_MAXPLAYERNAME, data, offset);
+
+            let msg = MsgTakeback {
+                header,
+                action,
+                turn,
+                player_name_len,
+                player_name,
+            };
+            assert_eq!(offset, (MSG_TAKEBACK_SIZE - MSG_HEADER_SIZE) as usize);
+            Ok((offset, Box::new(msg)))
+        } else {
+            Err(ah::format_err!("MsgTakeback: Not enough data."))
+        }
+    }
+
+    /// Get the action and the side that requests, accepts or declines the takeback.
+    pub fn get_action(&self) -> (u32, u32) {
+        (self.action, self.turn)
+    }
+
+    pub fn set_player_name(&mut self, player_name: &str) -> ah::Result<()> {
+        self.player_name_len = player_name.to_net(&mut self.player_name, true)? as u32;
+        Ok(())
+    }
+
+    pub fn get_player_name(&self) -> String {
+        match String::from_net(&self.player_name, self.player_name_len as usize, true) {
+            Ok(m) => m,
+            Err(_) => "Failed to parse MsgTakeback.".to_string(),
+        }
+    }
+}
+
+impl Message for MsgTakeback {
+    msg_trait_define_common!(Takeback);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MSG_TAKEBACK_SIZE as usize);
+        self.header.to_bytes(&mut data);
+        data.extend_from_slice(&self.action.to_net());
+        data.extend_from_slice(&self.turn.to_net());
+        data.extend_from_slice(&self.player_name_len.to_net());
+        data.extend_from_slice(&self.player_name);
+        assert_eq!(data.len(), MSG_TAKEBACK_SIZE as usize);
+        data
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// MsgVote
+//////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct MsgVote {
+    header: MsgHeader,
+    action: u32,
+    subject: u32,
+    timeout: u32,
+    player_name_len: u32,
+    player_name: [u8; MSG_MAXPLAYERNAME],
+}
+
+const MSG_VOTE_SIZE: u32 = MSG_HEADER_SIZE + (4 * 4) + MSG_MAXPLAYERNAME as u32;
+
+pub const MSG_VOTE_ACTION_REQUEST: u32 = 0;
+pub const MSG_VOTE_ACTION_ACCEPT: u32 = 1;
+pub const MSG_VOTE_ACTION_DECLINE: u32 = 2;
+pub const MSG_VOTE_ACTION_PASSED: u32 = 3;
+pub const MSG_VOTE_ACTION_FAILED: u32 = 4;
+
+pub const MSG_VOTE_SUBJECT_LOAD: u32 = 0;
+pub const MSG_VOTE_SUBJECT_RESET: u32 = 1;
+
+impl MsgVote {
+    /// Create a new vote message.
+    /// timeout is the number of seconds after which a vote request
+    /// passes without objection, or 0 if it never passes without answers.
+    pub fn new(action: u32, subject: u32, timeout: u32, player_name: &str) -> ah::Result<MsgVote> {
+        let mut player_name_bytes = [0; MSG_MAXPLAYERNAME];
+        let player_name_len = player_name.to_net(&mut player_name_bytes, true)?;
+        Ok(MsgVote {
+            header: MsgHeader::new(MSG_MAGIC, MSG_VOTE_SIZE, MSG_ID_VOTE, 0),
+            action,
+            subject,
+            timeout,
+            player_name_len: player_name_len as u32,
+            player_name: player_name_bytes,
+        })
+    }
+
+    pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
+        if data.len() >= (MSG_VOTE_SIZE - MSG_HEADER_SIZE) as usize {
+            let mut offset = 0;
+
+            let action = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let subject = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let timeout = u32::from_net(&data[offset..])?;
+            offset += 4;
+            let (player_name_len, player_name, offset) =
+                extract_str!(MSG_MAXPLAYERNAME, data, offset);
+
+            let msg = MsgVote {
+                header,
+                action,
+                subject,
+                timeout,
+                player_name_len,
+                player_name,
+            };
+            assert_eq!(offset, (MSG_VOTE_SIZE - MSG_HEADER_SIZE) as usize);
+            Ok((offset, Box::new(msg)))
+        } else {
+            Err(ah::format_err!("MsgVote: Not enough data."))
+        }
+    }
+
+    /// Get the action and the subject of the vote.
+    pub fn get_action(&self) -> (u32, u32) {
+        (self.action, self.subject)
+    }
+
+    /// Get the timeout of the vote request, in seconds.
+    pub fn get_timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    pub fn set_player_name(&mut self, player_name: &str) -> ah::Result<()> {
+        self.player_name_len = player_name.to_net(&mut self.player_name, true)? as u32;
+        Ok(())
+    }
+
+    pub fn get_player_name(&self) -> String {
+        match String::from_net(&self.player_name, self.player_name_len as usize, true) {
+            Ok(m) => m,
+            Err(_) => "Failed to parse MsgVote.".to_string(),
+        }
+    }
+}
+
+impl Message for MsgVote {
+    msg_trait_define_common!(Vote);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MSG_VOTE_SIZE as usize);
+        self.header.to_bytes(&mut data);
+        data.extend_from_slice(&self.action.to_net());
+        data.extend_from_slice(&self.subject.to_net());
+        data.extend_from_slice(&self.timeout.to_net());
+        data.extend_from_slice(&self.player_name_len.to_net());
+        data.extend_from_slice(&self.player_name);
+        assert_eq!(data.len(), MSG_VOTE_SIZE as usize);
+        data
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// MsgCreateRoom
+//////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct MsgCreateRoom {
+    header: MsgHeader,
+    room_name_len: u32,
+    room_name: [u8; MSG_MAXROOMNAME],
+    rules_name_len: u32,
+    rules_name: [u8; MSG_MAXRULESNAME],
+    password_len: u32,
+    password: [u8; MSG_MAXPASSWORD],
+}
+
+const MSG_CREATE_ROOM_SIZE: u32 = MSG_HEADER_SIZE
+    + 4
+    + MSG_MAXROOMNAME as u32
+    + 4
+    + MSG_MAXRULESNAME as u32
+    + 4
+    + MSG_MAXPASSWORD as u32;
+
+impl MsgCreateRoom {
+    /// Create a new room creation message.
+    /// An empty rules_name selects the default rules of the server.
+    /// An empty password creates a room that is not password protected.
+    pub fn new(room_name: &str, rules_name: &str, password: &str) -> ah::Result<MsgCreateRoom> {
+        let mut room_name_bytes = [0; MSG_MAXROOMNAME];
+        let room_name_len = room_name.to_net(&mut room_name_bytes, false)? as u32;
+        let mut rules_name_bytes = [0; MSG_MAXRULESNAME];
+        let rules_name_len = rules_name.to_net(&mut rules_name_bytes, false)? as u32;
+        let mut password_bytes = [0; MSG_MAXPASSWORD];
+        let password_len = password.to_net(&mut password_bytes, false)? as u32;
+        Ok(MsgCreateRoom {
+            header: MsgHeader::new(MSG_MAGIC, MSG_CREATE_ROOM_SIZE, MSG_ID_CREATEROOM, 0),
+            room_name_len,
+            room_name: room_name_bytes,
+            rules_name_len,
+            rules_name: rules_name_bytes,
+            password_len,
+            password: password_bytes,
+        })
+    }
+
+    pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
+        if data.len() >= (MSG_CREATE_ROOM_SIZE - MSG_HEADER_SIZE) as usize {
+            let offset = 0;
+
+            let (room_name_len, room_name, offset) = extract_str!(MSG_MAXROOMNAME, data, offset);
+            let (rules_name_len, rules_name, offset) = extract_str!(MSG_MAXRULESNAME, data, offset);
+            let (password_len, password, offset) = extract_str!(MSG_MAXPASSWORD, data, offset);
+
+            let msg = MsgCreateRoom {
+                header,
+                room_name_len,
+                room_name,
+                rules_name_len,
+                rules_name,
+                password_len,
+                password,
+            };
+            assert_eq!(offset, (MSG_CREATE_ROOM_SIZE - MSG_HEADER_SIZE) as usize);
+            Ok((offset, Box::new(msg)))
+        } else {
+            Err(ah::format_err!("MsgCreateRoom: Not enough data."))
+        }
+    }
+
+    pub fn get_room_name(&self) -> ah::Result<String> {
+        String::from_net(&self.room_name, self.room_name_len as usize, false)
+    }
+
+    pub fn get_rules_name(&self) -> ah::Result<String> {
+        String::from_net(&self.rules_name, self.rules_name_len as usize, false)
+    }
+
+    pub fn get_password(&self) -> ah::Result<String> {
+        String::from_net(&self.password, self.password_len as usize, false)
+    }
+}
+
+impl Message for MsgCreateRoom {
+    msg_trait_define_common!(CreateRoom);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MSG_CREATE_ROOM_SIZE as usize);
+        self.header.to_bytes(&mut data);
+        data.extend_from_slice(&self.room_name_len.to_net());
+        data.extend_from_slice(&self.room_name);
+        data.extend_from_slice(&self.rules_name_len.to_net());
+        data.extend_from_slice(&self.rules_name);
+        data.extend_from_slice(&self.password_len.to_net());
+        data.extend_from_slice(&self.password);
+        assert_eq!(data.len(), MSG_CREATE_ROOM_SIZE as usize);
+        data
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// MsgCloseRoom
+//////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug)]
+pub struct MsgCloseRoom {
+    header: MsgHeader,
+    room_name_len: u32,
+    room_name: [u8; MSG_MAXROOMNAME],
+}
+
+const MSG_CLOSE_ROOM_SIZE: u32 = MSG_HEADER_SIZE + 4 + MSG_MAXROOMNAME as u32;
+
+impl MsgCloseRoom {
+    pub fn new(room_name: &str) -> ah::Result<MsgCloseRoom> {
+        let mut room_name_bytes = [0; MSG_MAXROOMNAME];
+        let room_name_len = room_name.to_net(&mut room_name_bytes, false)? as u32;
+        Ok(MsgCloseRoom {
+            header: MsgHeader::new(MSG_MAGIC, MSG_CLOSE_ROOM_SIZE, MSG_ID_CLOSEROOM, 0),
+            room_name_len,
+            room_name: room_name_bytes,
+        })
+    }
+
+    pub fn from_bytes(header: MsgHeader, data: &[u8]) -> ah::Result<(usize, Box<dyn Message>)> {
+        if data.len() >= (MSG_CLOSE_ROOM_SIZE - MSG_HEADER_SIZE) as usize {
+            let offset = 0;
+
+            let (room_name_len, room_name, offset) = extract_str!(MSG_MAXROOMNAME, data, offset);
+
+            let msg = MsgCloseRoom {
+                header,
+                room_name_len,
+                room_name,
+            };
+            assert_eq!(offset, (MSG_CLOSE_ROOM_SIZE - MSG_HEADER_SIZE) as usize);
+            Ok((offset, Box::new(msg)))
+        } else {
+            Err(ah::format_err!("MsgCloseRoom: Not enough data."))
+        }
+    }
+
+    pub fn get_room_name(&self) -> ah::Result<String> {
+        String::from_net(&self.room_name, self.room_name_len as usize, false)
+    }
+}
+
+impl Message for MsgCloseRoom {
+    msg_trait_define_common!(CloseRoom);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MSG_CLOSE_ROOM_SIZE as usize);
+        self.header.to_bytes(&mut data);
+        data.extend_from_slice(&self.room_name_len.to_net());
+        data.extend_from_slice(&self.room_name);
+        assert_eq!(data.len(), MSG_CLOSE_ROOM_SIZE as usize);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse one message that fills the whole buffer.
+    fn parse(data: &[u8]) -> Box<dyn Message> {
+        let (size, msg) = message_from_bytes(data).unwrap();
+        assert_eq!(size, data.len());
+        msg.unwrap()
+    }
+
+    /// Cut a message down to the layout of an older peer.
+    fn truncate(mut data: Vec<u8>, size: u32) -> Vec<u8> {
+        data.truncate(size as usize);
+        data[4..8].copy_from_slice(&size.to_net());
+        data
+    }
+
+    #[test]
+    fn test_join() {
+        let msg = MsgJoin::new("room", "player", MSG_PLAYERMODE_WOLF, "secret").unwrap();
+        let data = msg.to_bytes();
+        let msg = parse(&data);
+        let MsgType::Join(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_room_name().unwrap(), "room");
+        assert_eq!(m.get_player_name().unwrap(), "player");
+        assert_eq!(m.get_player_mode(), MSG_PLAYERMODE_WOLF);
+        assert_eq!(m.get_password().unwrap(), "secret");
+
+        // Without the password.
+        let data = truncate(data, MSG_JOIN_BASE_SIZE);
+        let msg = parse(&data);
+        let MsgType::Join(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_room_name().unwrap(), "room");
+        assert_eq!(m.get_password().unwrap(), "");
+    }
+
+    #[test]
+    fn test_room_list() {
+        let msg = MsgRoomList::new(3, 1, "room", "asalto", MSG_ROOM_FLAG_PASSWORD).unwrap();
+        let data = msg.to_bytes();
+        let msg = parse(&data);
+        let MsgType::RoomList(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_total_count(), 3);
+        assert_eq!(m.get_index(), 1);
+        assert_eq!(m.get_room_name().unwrap(), "room");
+        assert_eq!(m.get_rules_name().unwrap(), "asalto");
+        assert_eq!(m.get_flags(), MSG_ROOM_FLAG_PASSWORD);
+
+        // Without the flags.
+        let old = truncate(data.clone(), MSG_ROOM_LIST_RULES_SIZE);
+        let msg = parse(&old);
+        let MsgType::RoomList(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_rules_name().unwrap(), "asalto");
+        assert_eq!(m.get_flags(), 0);
+
+        // Without the rules name and the flags.
+        let old = truncate(data, MSG_ROOM_LIST_BASE_SIZE);
+        let msg = parse(&old);
+        let MsgType::RoomList(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_room_name().unwrap(), "room");
+        assert_eq!(m.get_rules_name().unwrap(), "");
+        assert_eq!(m.get_flags(), 0);
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut data = vec![0xFF, 0x00, 0x12];
+        data.extend_from_slice(&MsgReset::new().to_bytes());
+        assert!(message_from_bytes(&data).is_err());
+        let skip = net_sync(&data).unwrap();
+        assert_eq!(skip, 3);
+        let data = buffer_skip(data, skip);
+        assert!(matches!(parse(&data).get_message(), MsgType::Reset(_)));
+
+        // Incomplete messages are not parsed.
+        let (size, msg) = message_from_bytes(&data[..data.len() - 1]).unwrap();
+        assert_eq!(size, 0);
+        assert!(msg.is_none());
+    }
+
+    #[test]
+    fn test_game_state() {
+        let mut fields = [[1; BOARD_WIDTH as usize]; BOARD_HEIGHT as usize];
+        fields[3][6] = 2;
+        let msg = MsgGameState::new(fields, 1, 2, 3, 1, Some((4, 5)), (3, 6));
+        let data = msg.to_bytes();
+        let msg = parse(&data);
+        let MsgType::GameState(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_fields(), &fields);
+        assert_eq!(m.get_moving(), (1, 2, 3));
+        assert_eq!(m.get_turn(), 1);
+        assert_eq!(m.get_capture_chain(), Some((4, 5)));
+        assert_eq!(m.get_result(), (3, 6));
+
+        // Without the result reason.
+        let old = truncate(data.clone(), MSG_GAME_STATE_CHAIN_SIZE);
+        let msg = parse(&old);
+        let MsgType::GameState(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_capture_chain(), Some((4, 5)));
+        assert_eq!(m.get_result(), (0, 0));
+
+        // Without the capture chain.
+        let old = truncate(data.clone(), MSG_GAME_STATE_WIDE_SIZE);
+        let msg = parse(&old);
+        let MsgType::GameState(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_fields(), &fields);
+        assert_eq!(m.get_capture_chain(), None);
+
+        // Without the columns of the wider boards.
+        let old = truncate(data, MSG_GAME_STATE_BASE_SIZE);
+        let msg = parse(&old);
+        let MsgType::GameState(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_fields()[3][4], 1);
+        assert_eq!(m.get_fields()[3][6], MSG_FIELD_INVALID);
+        assert_eq!(m.get_turn(), 1);
+    }
+
+    #[test]
+    fn test_offers() {
+        let data = MsgResign::new(1, "player").unwrap().to_bytes();
+        let msg = parse(&data);
+        let MsgType::Resign(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_turn(), 1);
+        assert_eq!(m.get_player_name(), "player");
+
+        let data = MsgDrawOffer::new(MSG_DRAWOFFER_ACTION_ACCEPT, 2, "player")
+            .unwrap()
+            .to_bytes();
+        let msg = parse(&data);
+        let MsgType::DrawOffer(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_action(), (MSG_DRAWOFFER_ACTION_ACCEPT, 2));
+        assert_eq!(m.get_player_name(), "player");
+
+        let data = MsgTakeback::new(MSG_TAKEBACK_ACTION_DECLINE, 1, "player")
+            .unwrap()
+            .to_bytes();
+        let msg = parse(&data);
+        let MsgType::Takeback(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_action(), (MSG_TAKEBACK_ACTION_DECLINE, 1));
+        assert_eq!(m.get_player_name(), "player");
+    }
+
+    #[test]
+    fn test_rooms() {
+        let data = MsgCreateRoom::new("room", "huffing", "secret")
+            .unwrap()
+            .to_bytes();
+        let msg = parse(&data);
+        let MsgType::CreateRoom(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_room_name().unwrap(), "room");
+        assert_eq!(m.get_rules_name().unwrap(), "huffing");
+        assert_eq!(m.get_password().unwrap(), "secret");
+
+        let data = MsgCloseRoom::new("room").unwrap().to_bytes();
+        let msg = parse(&data);
+        let MsgType::CloseRoom(m) = msg.get_message() else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(m.get_room_name().unwrap(), "room");
+
+        let password = "x".repeat(MSG_MAXPASSWORD + 1);
+        assert!(MsgCreateRoom::new("room", "standard", &password).is_err());
+    }
+}
+
 // vim: ts=4 sw=4 expandtab
@@ -5,16 +5,24 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
+mod config;
 mod multicast;
 mod room;
 
+pub use crate::net::server::config::{ConfigFile, RoomConfig, ServerConfig};
+pub use crate::net::server::room::{LoadPolicy, ResetPolicy, SeatPolicy};
+
+use crate::game_state::{RuleSet, Turn, num_to_turn, num_to_vote_subject};
 use crate::net::{
-    consts::MAX_ROOMS,
+    consts::{DEFAULT_ABANDONED_ROOM_TIMEOUT, DEFAULT_MAX_CREATED_ROOMS, MAX_ROOMS},
     protocol::{
-        MSG_BUFFER_SIZE, MSG_RESULT_NOK, MSG_RESULT_OK, Message, MsgPlayerList, MsgPong, MsgRecord,
-        MsgResult, MsgRoomList, MsgType, buffer_skip, message_from_bytes, net_sync,
+        MSG_BUFFER_SIZE, MSG_RESULT_NOK, MSG_RESULT_OK, MSG_ROOM_FLAG_PASSWORD,
+        MSG_VOTE_ACTION_ACCEPT, MSG_VOTE_ACTION_DECLINE, Message, MsgPlayerList, MsgPong,
+        MsgRecord, MsgReqRoomList, MsgResult, MsgRoomList, MsgType, MsgVote, buffer_skip,
+        message_from_bytes, net_sync,
     },
     server::{
+        config::{check_room_name, check_room_password},
         multicast::{MulticastPacket, MulticastRouter, MulticastSubscriber, MulticastSync},
         room::ServerRoom,
     },
@@ -25,16 +33,127 @@ use anyhow as ah;
 use itertools::Itertools;
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const DEBUG_RAW: bool = false;
 
+/// The interval at which the config file is checked for modifications.
+const CONFIG_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The interval at which the vote timeouts and the clocks of the rooms are checked.
+const ROOM_TIMER_INTERVAL: Duration = Duration::from_millis(100);
+
 type ServerRoomMap = HashMap<String, ServerRoom>;
 
+/// Generate the room list for a client.
+/// Unlisted rooms are only included, if the client has joined them.
+fn gen_room_list_msgs(
+    rooms: &ServerRoomMap,
+    joined_room: Option<&str>,
+) -> ah::Result<Vec<MsgRoomList>> {
+    let listed_rooms: Vec<&ServerRoom> = rooms
+        .values()
+        .filter(|room| !room.get_config().unlisted || Some(room.get_name()) == joined_room)
+        .sorted_by(|a, b| a.get_name().cmp(b.get_name()))
+        .collect();
+    let mut messages = vec![];
+    for (i, room) in listed_rooms.iter().enumerate() {
+        let mut flags = 0;
+        if room.get_config().password.is_some() {
+            flags |= MSG_ROOM_FLAG_PASSWORD;
+        }
+        messages.push(MsgRoomList::new(
+            listed_rooms.len() as u32,
+            i as u32,
+            room.get_name(),
+            &room.get_rules_name(),
+            flags,
+        )?);
+    }
+    Ok(messages)
+}
+
+/// Tell all server instances to send the changed room list to their clients.
+fn broadcast_room_list(mc_sub: &MulticastSubscriber) {
+    mc_sub.send_broadcast(MulticastPacket {
+        data: MsgReqRoomList::new().to_bytes(),
+        meta_data: vec![],
+        include_self: true,
+        sync: MulticastSync::NoSync,
+    });
+}
+
+/// Send a message to the server instances.
+/// If a room is given, the message is only sent to the clients in the room.
+fn send_broadcast(
+    mc_sub: &MulticastSubscriber,
+    msg: &impl Message,
+    room: Option<&ServerRoom>,
+    include_self: bool,
+    sync: MulticastSync,
+) {
+    mc_sub.send_broadcast(MulticastPacket {
+        data: msg.to_bytes(),
+        meta_data: if let Some(room) = room {
+            room.get_name().as_bytes().to_vec()
+        } else {
+            vec![]
+        },
+        include_self,
+        sync,
+    });
+}
+
+fn broadcast_game_state(
+    mc_sub: &MulticastSubscriber,
+    room: &mut ServerRoom,
+    player_mode: PlayerMode,
+) {
+    room.update_clock();
+    let game_state = room.get_game_state(player_mode).make_state_message();
+    send_broadcast(mc_sub, &game_state, Some(room), true, MulticastSync::NoSync);
+}
+
+/// Tell the other clients, if a draw offer or a takeback request has been dropped.
+fn broadcast_dropped_offers(
+    mc_sub: &MulticastSubscriber,
+    room: &mut ServerRoom,
+    player_name: &str,
+    player_mode: PlayerMode,
+    (draw_offer, takeback_request): (Option<Turn>, Option<Turn>),
+) -> ah::Result<()> {
+    let game_state = room.get_game_state(player_mode);
+    let draw_msg = game_state.server_make_dropped_draw_offer_msg(draw_offer, player_name)?;
+    let takeback_msg =
+        game_state.server_make_dropped_takeback_msg(takeback_request, player_name)?;
+    if let Some(msg) = draw_msg {
+        send_broadcast(mc_sub, &msg, Some(room), false, MulticastSync::NoSync);
+    }
+    if let Some(msg) = takeback_msg {
+        send_broadcast(mc_sub, &msg, Some(room), false, MulticastSync::NoSync);
+    }
+    Ok(())
+}
+
+/// Tell the clients about the outcome of a vote and the resulting game state.
+fn broadcast_vote_outcome(
+    mc_sub: &MulticastSubscriber,
+    room: &mut ServerRoom,
+    player_name: &str,
+    player_mode: PlayerMode,
+    outcome: &MsgVote,
+    offers: (Option<Turn>, Option<Turn>),
+) -> ah::Result<()> {
+    send_broadcast(mc_sub, outcome, Some(room), true, MulticastSync::NoSync);
+    broadcast_dropped_offers(mc_sub, room, player_name, player_mode, offers)?;
+    broadcast_game_state(mc_sub, room, player_mode);
+    Ok(())
+}
+
 /// Server instance thread corresponding to one connected client.
 struct ServerInstance<'a> {
     stream: &'a mut TcpStream,
@@ -42,6 +161,10 @@ struct ServerInstance<'a> {
     sequence: u32,
     peer_addr: SocketAddr,
     rooms: Arc<Mutex<ServerRoomMap>>,
+    /// The settings of the rooms that are created by the client.
+    room_defaults: RoomConfig,
+    /// The maximum number of rooms that the clients from one address may create.
+    max_created_rooms: Arc<AtomicUsize>,
     joined_room: Option<String>,
     player_name: Option<String>,
     player_mode: PlayerMode,
@@ -53,7 +176,15 @@ macro_rules! do_leave {
             if let Some(room_name) = $self.joined_room.take() {
                 match $rooms.get_mut(&room_name) {
                     Some(mut room) => {
-                        room.remove_player(&player_name);
+                        let offers = room.get_offers();
+                        if let Some(outcome) = room.remove_player(&player_name) {
+                            // The leave has decided the pending vote.
+                            if let Err(e) =
+                                $self.broadcast_vote_outcome(&mut room, &outcome, offers)
+                            {
+                                Print::error(&format!("Failed to broadcast vote outcome: {}", e));
+                            }
+                        }
                         if let Err(e) = $self.broadcast_player_list(&mut room, false) {
                             Print::error(&format!("Failed to broadcast player list: {}", e));
                         }
@@ -75,6 +206,8 @@ impl<'a> ServerInstance<'a> {
         stream: &'a mut TcpStream,
         mc_sub: MulticastSubscriber,
         rooms: Arc<Mutex<ServerRoomMap>>,
+        room_defaults: RoomConfig,
+        max_created_rooms: Arc<AtomicUsize>,
     ) -> ah::Result<ServerInstance<'a>> {
         let peer_addr = stream.peer_addr()?;
 
@@ -88,14 +221,14 @@ impl<'a> ServerInstance<'a> {
             sequence: 0,
             peer_addr,
             rooms,
+            room_defaults,
+            max_created_rooms,
             joined_room: None,
             player_name: None,
             player_mode: PlayerMode::Spectator,
         };
 
-        for reply in &mut self_.gen_room_list_msgs()? {
-            self_.send_msg(reply)?;
-        }
+        self_.send_room_list()?;
 
         Ok(self_)
     }
@@ -115,6 +248,16 @@ impl<'a> ServerInstance<'a> {
         Ok(())
     }
 
+    /// Send the room list to the client.
+    fn send_room_list(&mut self) -> ah::Result<()> {
+        let mut replies =
+            gen_room_list_msgs(&self.rooms.lock().unwrap(), self.joined_room.as_deref())?;
+        for reply in &mut replies {
+            self.send_msg(reply)?;
+        }
+        Ok(())
+    }
+
     fn send_broadcast(
         &self,
         msg: &impl Message,
@@ -122,21 +265,37 @@ impl<'a> ServerInstance<'a> {
         include_self: bool,
         sync: MulticastSync,
     ) {
-        self.mc_sub.send_broadcast(MulticastPacket {
-            data: msg.to_bytes(),
-            meta_data: if let Some(room) = room {
-                room.get_name().as_bytes().to_vec()
-            } else {
-                vec![]
-            },
-            include_self,
-            sync,
-        });
+        send_broadcast(&self.mc_sub, msg, room, include_self, sync);
     }
 
     fn broadcast_game_state(&self, room: &mut ServerRoom) {
-        let game_state = room.get_game_state(self.player_mode).make_state_message();
-        self.send_broadcast(&game_state, Some(room), true, MulticastSync::NoSync);
+        broadcast_game_state(&self.mc_sub, room, self.player_mode);
+    }
+
+    fn broadcast_dropped_offers(
+        &self,
+        room: &mut ServerRoom,
+        offers: (Option<Turn>, Option<Turn>),
+    ) -> ah::Result<()> {
+        let player_name = self.player_name.as_deref().unwrap_or_default();
+        broadcast_dropped_offers(&self.mc_sub, room, player_name, self.player_mode, offers)
+    }
+
+    fn broadcast_vote_outcome(
+        &self,
+        room: &mut ServerRoom,
+        outcome: &MsgVote,
+        offers: (Option<Turn>, Option<Turn>),
+    ) -> ah::Result<()> {
+        let player_name = self.player_name.as_deref().unwrap_or_default();
+        broadcast_vote_outcome(
+            &self.mc_sub,
+            room,
+            player_name,
+            self.player_mode,
+            outcome,
+            offers,
+        )
     }
 
     fn broadcast_player_list(&self, room: &mut ServerRoom, include_self: bool) -> ah::Result<()> {
@@ -171,25 +330,6 @@ impl<'a> ServerInstance<'a> {
         Ok(messages)
     }
 
-    fn gen_room_list_msgs(&self) -> ah::Result<Vec<MsgRoomList>> {
-        let mut room_names = vec![];
-        {
-            let rooms = self.rooms.lock().unwrap();
-            for (_room_name, room) in rooms.iter().sorted() {
-                room_names.push(room.get_name().to_string());
-            }
-        }
-        let mut messages = vec![];
-        for (i, room_name) in room_names.iter().enumerate() {
-            messages.push(MsgRoomList::new(
-                room_names.len() as u32,
-                i as u32,
-                room_name,
-            )?);
-        }
-        Ok(messages)
-    }
-
     fn handle_rx_room_message(&mut self, msg_type: &mut MsgType) -> ah::Result<()> {
         let mut rooms = self.rooms.lock().unwrap();
 
@@ -209,10 +349,25 @@ impl<'a> ServerInstance<'a> {
 
         match msg_type {
             MsgType::Reset(msg) => {
-                room.get_game_state(self.player_mode).reset_game(false);
+                let offers = room.get_offers();
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                let err = match room.reset_game(player_name, self.player_mode) {
+                    Ok(Some(vote_msg)) => {
+                        // The other players have to agree first.
+                        self.send_broadcast(&vote_msg, Some(room), true, MulticastSync::NoSync);
+                        None
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some(format!("{}", e)),
+                };
+                self.broadcast_dropped_offers(room, offers)?;
                 self.broadcast_game_state(room);
                 drop(rooms);
-                self.send_msg(&mut MsgResult::new(*msg, MSG_RESULT_OK, "")?)?;
+                if let Some(e) = err {
+                    self.send_msg(&mut MsgResult::new(*msg, MSG_RESULT_NOK, &e)?)?;
+                } else {
+                    self.send_msg(&mut MsgResult::new(*msg, MSG_RESULT_OK, "")?)?;
+                }
             }
             MsgType::ReqGameState(_msg) => {
                 let mut game_state = room.get_game_state(self.player_mode).make_state_message();
@@ -220,11 +375,14 @@ impl<'a> ServerInstance<'a> {
                 self.send_msg(&mut game_state)?;
             }
             MsgType::GameState(msg) => {
-                let err = match room
-                    .get_game_state(self.player_mode)
-                    .read_state_message(msg, false)
-                {
-                    Ok(_) => None,
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                let err = match room.load_game_state(player_name, self.player_mode, msg) {
+                    Ok(Some(vote_msg)) => {
+                        // The other players have to agree first.
+                        self.send_broadcast(&vote_msg, Some(room), true, MulticastSync::NoSync);
+                        None
+                    }
+                    Ok(None) => None,
                     Err(e) => Some(format!("{}", e)),
                 };
                 self.broadcast_game_state(room);
@@ -271,11 +429,17 @@ impl<'a> ServerInstance<'a> {
                 )?)?;
             }
             MsgType::Move(msg) => {
+                let offers = room.get_offers();
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                let turn = room.get_game_state(self.player_mode).get_turn();
                 match room
-                    .get_game_state(self.player_mode)
-                    .server_handle_rx_msg_move(msg)
-                {
+                    .check_seat(player_name, self.player_mode, turn)
+                    .and_then(|_| {
+                        room.get_game_state(self.player_mode)
+                            .server_handle_rx_msg_move(msg)
+                    }) {
                     Ok(_) => {
+                        self.broadcast_dropped_offers(room, offers)?;
                         self.broadcast_game_state(room);
                         drop(rooms);
                         self.send_msg(&mut MsgResult::new(*msg, MSG_RESULT_OK, "")?)?;
@@ -288,6 +452,116 @@ impl<'a> ServerInstance<'a> {
                     }
                 }
             }
+            MsgType::Resign(msg) => {
+                let mut msg = msg.clone();
+
+                // Override the player name. It might be forged.
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                msg.set_player_name(player_name)?;
+
+                match num_to_turn(msg.get_turn())
+                    .and_then(|side| room.check_seat(player_name, self.player_mode, side))
+                    .and_then(|_| {
+                        room.get_game_state(self.player_mode)
+                            .server_handle_rx_msg_resign(&msg)
+                    }) {
+                    Ok(_) => {
+                        self.send_broadcast(&msg, Some(room), false, MulticastSync::NoSync);
+                        self.broadcast_game_state(room);
+                        drop(rooms);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_OK, "")?)?;
+                    }
+                    Err(e) => {
+                        drop(rooms);
+                        let text = format!("resign error: {}", e);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_NOK, &text)?)?;
+                        return Err(ah::format_err!("{}", text));
+                    }
+                }
+            }
+            MsgType::DrawOffer(msg) => {
+                let mut msg = msg.clone();
+
+                // Override the player name. It might be forged.
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                msg.set_player_name(player_name)?;
+
+                let (_, turn) = msg.get_action();
+                match num_to_turn(turn)
+                    .and_then(|side| room.check_seat(player_name, self.player_mode, side))
+                    .and_then(|_| {
+                        room.get_game_state(self.player_mode)
+                            .server_handle_rx_msg_draw_offer(&msg)
+                    }) {
+                    Ok(_) => {
+                        self.send_broadcast(&msg, Some(room), false, MulticastSync::NoSync);
+                        self.broadcast_game_state(room);
+                        drop(rooms);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_OK, "")?)?;
+                    }
+                    Err(e) => {
+                        drop(rooms);
+                        let text = format!("draw offer error: {}", e);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_NOK, &text)?)?;
+                        return Err(ah::format_err!("{}", text));
+                    }
+                }
+            }
+            MsgType::Takeback(msg) => {
+                let mut msg = msg.clone();
+
+                // Override the player name. It might be forged.
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                msg.set_player_name(player_name)?;
+
+                match room.handle_takeback(player_name, self.player_mode, &msg) {
+                    Ok(_) => {
+                        self.send_broadcast(&msg, Some(room), false, MulticastSync::NoSync);
+                        self.broadcast_game_state(room);
+                        drop(rooms);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_OK, "")?)?;
+                    }
+                    Err(e) => {
+                        drop(rooms);
+                        let text = format!("takeback error: {}", e);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_NOK, &text)?)?;
+                        return Err(ah::format_err!("{}", text));
+                    }
+                }
+            }
+            MsgType::Vote(msg) => {
+                let mut msg = msg.clone();
+
+                // Override the player name. It might be forged.
+                let player_name = self.player_name.as_deref().unwrap_or_default();
+                msg.set_player_name(player_name)?;
+
+                let offers = room.get_offers();
+                let result = match msg.get_action() {
+                    (action @ (MSG_VOTE_ACTION_ACCEPT | MSG_VOTE_ACTION_DECLINE), subject) => {
+                        num_to_vote_subject(subject).and_then(|subject| {
+                            room.answer_vote(player_name, subject, action == MSG_VOTE_ACTION_ACCEPT)
+                        })
+                    }
+                    (action, _) => Err(ah::format_err!("Received invalid vote action: {}", action)),
+                };
+                match result {
+                    Ok(outcome) => {
+                        self.send_broadcast(&msg, Some(room), false, MulticastSync::NoSync);
+                        if let Some(outcome) = outcome {
+                            self.broadcast_vote_outcome(room, &outcome, offers)?;
+                        }
+                        drop(rooms);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_OK, "")?)?;
+                    }
+                    Err(e) => {
+                        drop(rooms);
+                        let text = format!("vote error: {}", e);
+                        self.send_msg(&mut MsgResult::new(&msg, MSG_RESULT_NOK, &text)?)?;
+                        return Err(ah::format_err!("{}", text));
+                    }
+                }
+            }
             MsgType::Say(msg) => {
                 let mut msg = msg.clone();
 
@@ -318,6 +592,7 @@ impl<'a> ServerInstance<'a> {
         room_name: &str,
         player_name: &str,
         player_mode: PlayerMode,
+        password: &str,
     ) -> ah::Result<()> {
         let mut rooms = self.rooms.lock().unwrap();
 
@@ -333,6 +608,8 @@ impl<'a> ServerInstance<'a> {
                     if let Some(old_player_name) = self.player_name.as_ref() {
                         ignore_player = Some(&old_player_name[..]);
                     }
+                } else {
+                    room.check_password(password)?;
                 }
 
                 match room.can_add_player(player_name, player_mode, ignore_player) {
@@ -392,6 +669,113 @@ impl<'a> ServerInstance<'a> {
         do_leave!(self, rooms);
     }
 
+    /// Create a new room on request of the client.
+    /// An empty rules_name selects the default rules of the server.
+    fn do_create_room(
+        &mut self,
+        room_name: &str,
+        rules_name: &str,
+        password: &str,
+    ) -> ah::Result<()> {
+        let mut config = self.room_defaults.clone();
+        config.name = check_room_name(room_name)?;
+        if !rules_name.is_empty() {
+            config.rules = RuleSet::from_name(rules_name)?;
+        }
+        config.password = check_room_password(password)?;
+
+        let mut rooms = self.rooms.lock().unwrap();
+        if rooms.contains_key(&config.name) {
+            return Err(ah::format_err!(
+                "The room '{}' already exists.",
+                config.name
+            ));
+        }
+        if rooms.len() >= MAX_ROOMS {
+            return Err(ah::format_err!(
+                "Maximum number of rooms ({}) exceeded.",
+                MAX_ROOMS
+            ));
+        }
+        let created_rooms = rooms
+            .values()
+            .filter(|room| {
+                room.get_creator()
+                    .is_some_and(|creator| creator == self.peer_addr.ip())
+            })
+            .count();
+        let max_created_rooms = self.max_created_rooms.load(Ordering::Relaxed);
+        if created_rooms >= max_created_rooms {
+            return Err(ah::format_err!(
+                "Maximum number of rooms per client ({}) exceeded.",
+                max_created_rooms
+            ));
+        }
+
+        Print::info(&format!(
+            "{} has opened the room: {}",
+            self.peer_addr, config
+        ));
+        let name = config.name.clone();
+        rooms.insert(name, ServerRoom::new(config, Some(self.peer_addr.ip()))?);
+        broadcast_room_list(&self.mc_sub);
+        Ok(())
+    }
+
+    /// Close a room that has been created by this client.
+    /// The room can only be closed, if no other player is in it.
+    fn do_close_room(&mut self, room_name: &str) -> ah::Result<()> {
+        let mut rooms = self.rooms.lock().unwrap();
+        let room = match rooms.get(room_name) {
+            Some(room) => room,
+            None => {
+                return Err(ah::format_err!("Room '{}' not found.", room_name));
+            }
+        };
+        match room.get_creator() {
+            Some(creator) if creator == self.peer_addr.ip() => (),
+            Some(_) => {
+                return Err(ah::format_err!(
+                    "Only the creator of room '{}' can close it.",
+                    room_name
+                ));
+            }
+            None => {
+                return Err(ah::format_err!(
+                    "Room '{}' has not been created by a client.",
+                    room_name
+                ));
+            }
+        }
+        let joined = self.joined_room.as_deref() == Some(room_name);
+        let own_name = if joined {
+            self.player_name.as_deref()
+        } else {
+            None
+        };
+        if room
+            .get_player_list_ref()
+            .iter()
+            .any(|player| Some(player.name.as_str()) != own_name)
+        {
+            return Err(ah::format_err!(
+                "There are other players in room '{}'.",
+                room_name
+            ));
+        }
+
+        if joined {
+            do_leave!(self, rooms);
+        }
+        rooms.remove(room_name);
+        Print::info(&format!(
+            "{} has closed the room '{}'",
+            self.peer_addr, room_name
+        ));
+        broadcast_room_list(&self.mc_sub);
+        Ok(())
+    }
+
     /// Handle received message.
     fn handle_rx_message(&mut self, mut msg_type: MsgType) -> ah::Result<()> {
         match msg_type {
@@ -406,7 +790,12 @@ impl<'a> ServerInstance<'a> {
                 if let Ok(room_name) = msg.get_room_name() {
                     if let Ok(player_name) = msg.get_player_name() {
                         if let Ok(player_mode) = num_to_player_mode(msg.get_player_mode()) {
-                            result = self.do_join(&room_name, &player_name, player_mode);
+                            if let Ok(password) = msg.get_password() {
+                                result =
+                                    self.do_join(&room_name, &player_name, player_mode, &password);
+                            } else {
+                                result = Err(ah::format_err!("Received invalid password."));
+                            }
                         } else {
                             result = Err(ah::format_err!("Received invalid player mode."));
                         }
@@ -418,6 +807,9 @@ impl<'a> ServerInstance<'a> {
                 }
                 match result {
                     Ok(_) => {
+                        // The joined room may be unlisted.
+                        // The client stores the room list while waiting for the result.
+                        self.send_room_list()?;
                         self.send_msg(&mut MsgResult::new(msg, MSG_RESULT_OK, "")?)?;
                     }
                     Err(e) => {
@@ -428,13 +820,20 @@ impl<'a> ServerInstance<'a> {
                 }
             }
             MsgType::Leave(msg) => {
+                let unlisted = self.joined_room.as_ref().is_some_and(|room_name| {
+                    let rooms = self.rooms.lock().unwrap();
+                    rooms
+                        .get(room_name)
+                        .is_some_and(|room| room.get_config().unlisted)
+                });
                 self.do_leave();
+                if unlisted {
+                    self.send_room_list()?;
+                }
                 self.send_msg(&mut MsgResult::new(msg, MSG_RESULT_OK, "")?)?;
             }
             MsgType::ReqRoomList(_msg) => {
-                for reply in &mut self.gen_room_list_msgs()? {
-                    self.send_msg(reply)?;
-                }
+                self.send_room_list()?;
             }
             MsgType::RoomList(msg) => {
                 self.send_msg(&mut MsgResult::new(
@@ -443,6 +842,46 @@ impl<'a> ServerInstance<'a> {
                     "Cannot change room list.",
                 )?)?;
             }
+            MsgType::CreateRoom(msg) => {
+                let result = match (
+                    msg.get_room_name(),
+                    msg.get_rules_name(),
+                    msg.get_password(),
+                ) {
+                    (Ok(room_name), Ok(rules_name), Ok(password)) => {
+                        self.do_create_room(&room_name, &rules_name, &password)
+                    }
+                    (Err(_), _, _) => Err(ah::format_err!("Received invalid room name.")),
+                    (_, Err(_), _) => Err(ah::format_err!("Received invalid rules name.")),
+                    (_, _, Err(_)) => Err(ah::format_err!("Received invalid password.")),
+                };
+                match result {
+                    Ok(_) => {
+                        self.send_msg(&mut MsgResult::new(msg, MSG_RESULT_OK, "")?)?;
+                    }
+                    Err(e) => {
+                        let text = format!("Create room failed: {}", e);
+                        self.send_msg(&mut MsgResult::new(msg, MSG_RESULT_NOK, &text)?)?;
+                        return Err(ah::format_err!("{}", text));
+                    }
+                }
+            }
+            MsgType::CloseRoom(msg) => {
+                let result = match msg.get_room_name() {
+                    Ok(room_name) => self.do_close_room(&room_name),
+                    Err(_) => Err(ah::format_err!("Received invalid room name.")),
+                };
+                match result {
+                    Ok(_) => {
+                        self.send_msg(&mut MsgResult::new(msg, MSG_RESULT_OK, "")?)?;
+                    }
+                    Err(e) => {
+                        let text = format!("Close room failed: {}", e);
+                        self.send_msg(&mut MsgResult::new(msg, MSG_RESULT_NOK, &text)?)?;
+                        return Err(ah::format_err!("{}", text));
+                    }
+                }
+            }
             MsgType::Reset(_)
             | MsgType::ReqGameState(_)
             | MsgType::GameState(_)
@@ -451,7 +890,11 @@ impl<'a> ServerInstance<'a> {
             | MsgType::ReqRecord(_)
             | MsgType::Record(_)
             | MsgType::Move(_)
-            | MsgType::Say(_) => {
+            | MsgType::Say(_)
+            | MsgType::Resign(_)
+            | MsgType::DrawOffer(_)
+            | MsgType::Takeback(_)
+            | MsgType::Vote(_) => {
                 self.handle_rx_room_message(&mut msg_type)?;
             }
         }
@@ -501,9 +944,13 @@ impl<'a> ServerInstance<'a> {
 
         match msg_type {
             MsgType::Say(msg) => forward_if_joined_room!(msg),
+            MsgType::Resign(msg) => forward_if_joined_room!(msg),
+            MsgType::DrawOffer(msg) => forward_if_joined_room!(msg),
+            MsgType::Takeback(msg) => forward_if_joined_room!(msg),
+            MsgType::Vote(msg) => forward_if_joined_room!(msg),
             MsgType::GameState(msg) => forward_if_joined_room!(msg),
             MsgType::PlayerList(msg) => forward!(msg),
-            MsgType::RoomList(msg) => forward!(msg),
+            MsgType::ReqRoomList(_) => self.send_room_list(),
             other => Err(ah::format_err!(
                 "Received unexpected multicast: {:?}",
                 other
@@ -512,12 +959,10 @@ impl<'a> ServerInstance<'a> {
     }
 
     fn handle_rx_multicast_data(&mut self, pack: &MulticastPacket) -> ah::Result<()> {
-        if let Some(joined_room) = self.joined_room.as_ref() {
-            if pack.meta_data != joined_room.as_bytes() {
-                // We're not in the destination room. Discard it.
-                return Ok(());
-            }
-        } else if !pack.meta_data.is_empty() {
+        // A packet without destination room is sent to all clients.
+        if !pack.meta_data.is_empty()
+            && self.joined_room.as_ref().map(|room| room.as_bytes()) != Some(&pack.meta_data[..])
+        {
             // We're not in the destination room. Discard it.
             return Ok(());
         }
@@ -624,34 +1069,262 @@ impl<'a> ServerInstance<'a> {
 }
 
 pub struct Server {
-    listener: TcpListener,
+    listeners: Vec<TcpListener>,
+    bind: Vec<String>,
+    port: u16,
     max_conns: usize,
-    restrict_player_modes: bool,
     active_conns: Arc<AtomicUsize>,
     rooms: Arc<Mutex<ServerRoomMap>>,
+    room_defaults: RoomConfig,
+    /// The maximum number of rooms that the clients from one address may create.
+    max_created_rooms: Arc<AtomicUsize>,
+    /// The time after which an empty room that has been created by a client is closed.
+    abandoned_room_timeout: Duration,
+    mc_router: MulticastRouter,
+    mc_sub: MulticastSubscriber,
+    config_file: Option<ConfigFile>,
+    /// The settings given on the command line that override the config file.
+    overrides: ServerConfig,
 }
 
 impl Server {
     pub fn new(
-        addr: impl ToSocketAddrs,
+        bind: &[String],
+        port: u16,
         max_conns: u16,
-        restrict_player_modes: bool,
+        room_defaults: RoomConfig,
+        config_file: Option<ConfigFile>,
+        overrides: ServerConfig,
     ) -> ah::Result<Server> {
-        let listener = TcpListener::bind(addr)?;
-        listener.set_nonblocking(true)?;
+        let mut listeners = vec![];
+        for addr in bind {
+            let addr = if addr.contains(':') && !addr.starts_with('[') {
+                // IPv6 address.
+                format!("[{}]:{}", addr, port)
+            } else {
+                format!("{}:{}", addr, port)
+            };
+            Print::info(&format!("Running dedicated server on {} ...", addr));
+            let listener = TcpListener::bind(&addr)
+                .map_err(|e| ah::format_err!("Failed to bind to {}: {}", addr, e))?;
+            listener.set_nonblocking(true)?;
+            listeners.push(listener);
+        }
+
+        let mut mc_router = MulticastRouter::new();
+        let mc_sub = mc_router.new_subscriber();
 
         Ok(Server {
-            listener,
+            listeners,
+            bind: bind.to_vec(),
+            port,
             max_conns: max_conns as usize,
-            restrict_player_modes,
             active_conns: Arc::new(AtomicUsize::new(0)),
             rooms: Arc::new(Mutex::new(HashMap::new())),
+            room_defaults,
+            max_created_rooms: Arc::new(AtomicUsize::new(DEFAULT_MAX_CREATED_ROOMS)),
+            abandoned_room_timeout: DEFAULT_ABANDONED_ROOM_TIMEOUT,
+            mc_router,
+            mc_sub,
+            config_file,
+            overrides,
         })
     }
 
-    pub fn run(&mut self, room_names: &Vec<String>) -> ah::Result<()> {
+    /// Reload the config file, if it has been modified.
+    /// The bind addresses and the port can only be changed by a restart.
+    fn reload_config_file(&mut self) {
+        let config_file = match self.config_file.as_mut() {
+            Some(config_file) if config_file.is_modified() => config_file,
+            Some(_) | None => return,
+        };
+        Print::info(&format!(
+            "Reloading config file '{}' ...",
+            config_file.get_path().display()
+        ));
+        let mut config = match config_file.read() {
+            Ok(config) => config,
+            Err(e) => {
+                Print::error(&format!("{} Keeping the current settings.", e));
+                return;
+            }
+        };
+        config.override_with(&self.overrides);
+
+        if config.bind.as_ref().is_some_and(|bind| *bind != self.bind)
+            || config.port.is_some_and(|port| port != self.port)
         {
-            if room_names.len() > MAX_ROOMS {
+            Print::warning("The bind addresses and the port can only be changed by a restart.");
+        }
+        if let Some(max_connections) = config.max_connections {
+            self.max_conns = max_connections as usize;
+        }
+        if let Some(log_level) = config.log_level {
+            Print::set_level_number(log_level);
+        }
+        self.set_room_limits(&config);
+        if config.rooms.is_empty() {
+            Print::warning("The config file defines no rooms. Keeping the current rooms.");
+        } else {
+            self.update_rooms(config.rooms);
+        }
+    }
+
+    /// Take over the limits of the rooms that are created by clients.
+    fn set_room_limits(&mut self, config: &ServerConfig) {
+        if let Some(max_created_rooms) = config.max_created_rooms {
+            self.max_created_rooms
+                .store(max_created_rooms, Ordering::Relaxed);
+        }
+        if let Some(timeout) = config.abandoned_room_timeout {
+            self.abandoned_room_timeout = timeout;
+        }
+    }
+
+    /// Open, change and close rooms to match the reloaded rooms.
+    /// A removed room stays open as long as there are players in it.
+    /// The rooms that have been created by clients are not touched.
+    fn update_rooms(&self, room_configs: Vec<RoomConfig>) {
+        let mut rooms = self.rooms.lock().unwrap();
+        rooms.retain(|name, room| {
+            if room.get_creator().is_some()
+                || room_configs.iter().any(|config| config.name == *name)
+            {
+                true
+            } else if room.get_player_list_ref().count() > 0 {
+                Print::warning(&format!(
+                    "Room '{}' has been removed, but there are players in it. Keeping it open.",
+                    name
+                ));
+                true
+            } else {
+                Print::info(&format!("Closing room: {}", name));
+                false
+            }
+        });
+        for config in room_configs {
+            let info = config.to_string();
+            if let Some(room) = rooms.get_mut(&config.name) {
+                if room.get_creator().is_some() {
+                    Print::warning(&format!(
+                        "Can't open room '{}': A client has created a room of the same name.",
+                        config.name
+                    ));
+                } else if *room.get_config() != config {
+                    match room.set_config(config) {
+                        Ok(()) => Print::info(&format!("Changing room: {}", info)),
+                        Err(e) => Print::error(&format!("Failed to change room: {}", e)),
+                    }
+                }
+            } else if rooms.len() >= MAX_ROOMS {
+                Print::error(&format!(
+                    "Can't open room '{}': Maximum number of rooms ({}) exceeded.",
+                    config.name, MAX_ROOMS
+                ));
+            } else {
+                let name = config.name.clone();
+                match ServerRoom::new(config, None) {
+                    Ok(room) => {
+                        Print::info(&format!("Opening room: {}", info));
+                        rooms.insert(name, room);
+                    }
+                    Err(e) => Print::error(&format!("Failed to open room: {}", e)),
+                }
+            }
+        }
+        broadcast_room_list(&self.mc_sub);
+    }
+
+    /// Close the rooms that have been created by clients and that have been empty for too long.
+    fn close_abandoned_rooms(&self) {
+        let mut rooms = self.rooms.lock().unwrap();
+        let count = rooms.len();
+        rooms.retain(|name, room| {
+            if room.is_abandoned(self.abandoned_room_timeout) {
+                Print::info(&format!("Closing abandoned room: {}", name));
+                false
+            } else {
+                true
+            }
+        });
+        if rooms.len() != count {
+            broadcast_room_list(&self.mc_sub);
+        }
+    }
+
+    /// Let the pending votes pass, if their timeout has expired,
+    /// and end the games, in which the side to move has run out of time.
+    fn check_room_timers(&self) {
+        let mut rooms = self.rooms.lock().unwrap();
+        for room in rooms.values_mut() {
+            let offers = room.get_offers();
+            if let Some(outcome) = room.check_vote_timeout() {
+                let requester = outcome.get_player_name();
+                if let Err(e) = broadcast_vote_outcome(
+                    &self.mc_sub,
+                    room,
+                    &requester,
+                    PlayerMode::Both,
+                    &outcome,
+                    offers,
+                ) {
+                    Print::error(&format!("Failed to broadcast vote outcome: {}", e));
+                }
+            }
+            if room.check_clock() {
+                broadcast_game_state(&self.mc_sub, room, PlayerMode::Both);
+            }
+        }
+    }
+
+    /// Start a server instance thread for a new connection.
+    fn accept_connection(&mut self, mut stream: TcpStream) {
+        if self.active_conns.fetch_add(1, Ordering::Acquire) < self.max_conns {
+            let mc_sub = self.mc_router.new_subscriber();
+            let thread_rooms = Arc::clone(&self.rooms);
+            let thread_room_defaults = self.room_defaults.clone();
+            let thread_max_created_rooms = Arc::clone(&self.max_created_rooms);
+            let thread_active_conns = Arc::clone(&self.active_conns);
+            thread::spawn(move || {
+                match ServerInstance::new(
+                    &mut stream,
+                    mc_sub,
+                    thread_rooms,
+                    thread_room_defaults,
+                    thread_max_created_rooms,
+                ) {
+                    Ok(mut instance) => {
+                        instance.run_loop();
+                        drop(instance);
+                        Print::debug("Server thread exiting.");
+                    }
+                    Err(e) => {
+                        Print::error(&format!("Could not construct server instance: {}", e));
+                    }
+                };
+                stream.shutdown(Shutdown::Both).ok();
+                thread_active_conns.fetch_sub(1, Ordering::Release);
+            });
+        } else {
+            let peer_addr = match stream.peer_addr() {
+                Ok(peer_addr) => peer_addr.to_string(),
+                Err(_) => "unknown".to_string(),
+            };
+            stream.shutdown(Shutdown::Both).ok();
+            Print::error(&format!(
+                "Rejected connection from '{}': Too many connections.",
+                peer_addr
+            ));
+            self.active_conns.fetch_sub(1, Ordering::Release);
+        }
+    }
+
+    /// Run the server with the given settings and rooms.
+    pub fn run(&mut self, config: ServerConfig) -> ah::Result<()> {
+        self.set_room_limits(&config);
+        {
+            let room_configs = config.rooms;
+            if room_configs.len() > MAX_ROOMS {
                 return Err(ah::format_err!(
                     "Maximum number of rooms ({}) exceeded.",
                     MAX_ROOMS
@@ -659,65 +1332,53 @@ impl Server {
             }
             let mut rooms = self.rooms.lock().unwrap();
             rooms.clear();
-            for name in room_names {
-                Print::info(&format!("Opening room: {}", name));
-                let room = ServerRoom::new(name.to_string(), self.restrict_player_modes)?;
-                rooms.insert(name.to_string(), room);
+            for config in room_configs {
+                if rooms.contains_key(&config.name) {
+                    return Err(ah::format_err!(
+                        "The room '{}' is defined more than once.",
+                        config.name
+                    ));
+                }
+                Print::info(&format!("Opening room: {}", config));
+                let name = config.name.clone();
+                rooms.insert(name, ServerRoom::new(config, None)?);
             }
         }
 
-        let mut mc_router = MulticastRouter::new();
+        let mut last_config_check = Instant::now();
+        let mut last_timer_check = Instant::now();
 
-        for stream in self.listener.incoming() {
-            match stream {
-                Ok(mut stream) => {
-                    if self.active_conns.fetch_add(1, Ordering::Acquire) < self.max_conns {
-                        let mc_sub = mc_router.new_subscriber();
-                        let thread_rooms = Arc::clone(&self.rooms);
-                        let thread_active_conns = Arc::clone(&self.active_conns);
-                        thread::spawn(move || {
-                            match ServerInstance::new(&mut stream, mc_sub, thread_rooms) {
-                                Ok(mut instance) => {
-                                    instance.run_loop();
-                                    drop(instance);
-                                    Print::debug("Server thread exiting.");
-                                }
-                                Err(e) => {
-                                    Print::error(&format!(
-                                        "Could not construct server instance: {}",
-                                        e
-                                    ));
-                                }
-                            };
-                            stream.shutdown(Shutdown::Both).ok();
-                            thread_active_conns.fetch_sub(1, Ordering::Release);
-                        });
-                    } else {
-                        let peer_addr = match stream.peer_addr() {
-                            Ok(peer_addr) => peer_addr.to_string(),
-                            Err(_) => "unknown".to_string(),
-                        };
-                        stream.shutdown(Shutdown::Both).ok();
-                        Print::error(&format!(
-                            "Rejected connection from '{}': Too many connections.",
-                            peer_addr
-                        ));
-                        self.active_conns.fetch_sub(1, Ordering::Release);
+        loop {
+            for index in 0..self.listeners.len() {
+                match self.listeners[index].accept() {
+                    Ok((stream, _peer_addr)) => {
+                        self.accept_connection(stream);
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        // Nothing to do.
+                    }
+                    Err(e) => {
+                        return Err(ah::format_err!("Connection failed: {}", e));
                     }
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // Nothing to do.
-                }
-                Err(e) => {
-                    return Err(ah::format_err!("Connection failed: {}", e));
                 }
             }
 
-            mc_router.run_router();
+            self.mc_router.run_router();
+            // The server itself is only a sender. Discard all received packets.
+            while self.mc_sub.receive().is_some() {}
+
+            if last_config_check.elapsed() >= CONFIG_CHECK_INTERVAL {
+                last_config_check = Instant::now();
+                self.reload_config_file();
+                self.close_abandoned_rooms();
+            }
+            if last_timer_check.elapsed() >= ROOM_TIMER_INTERVAL {
+                last_timer_check = Instant::now();
+                self.check_room_timers();
+            }
 
             thread::sleep(Duration::from_millis(10));
         }
-        Ok(())
     }
 }
 
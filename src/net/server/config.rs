@@ -0,0 +1,661 @@
+// -*- coding: utf-8 -*-
+//
+// Copyright 2021 Michael Buesch <m@bues.ch>
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+
+use crate::game_state::RuleSet;
+use crate::net::{
+    consts::{MAX_PLAYERS, MAX_ROOMS},
+    protocol::{MSG_MAXPASSWORD, MSG_MAXROOMNAME},
+    server::room::{LoadPolicy, ResetPolicy, SeatPolicy},
+};
+use anyhow as ah;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// The settings of a room that can be given in the config file.
+const ROOM_KEYS: [&str; 10] = [
+    "name",
+    "rules",
+    "seat-policy",
+    "load-policy",
+    "reset-policy",
+    "max-players",
+    "spectators",
+    "password",
+    "unlisted",
+    "time-control",
+];
+
+/// The time control of a room.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TimeControl {
+    /// The thinking time of each side for the whole game.
+    pub base: Duration,
+    /// The time added to the thinking time of a side after each of its moves.
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    /// Parse a time control given as "MINUTES+SECONDS", for example "5+3".
+    /// The increment may be omitted.
+    pub fn from_name(name: &str) -> ah::Result<TimeControl> {
+        let (minutes, seconds) = match name.trim().split_once('+') {
+            Some((minutes, seconds)) => (minutes, seconds),
+            None => (name.trim(), "0"),
+        };
+        match (minutes.trim().parse::<u32>(), seconds.trim().parse::<u32>()) {
+            (Ok(minutes), Ok(seconds)) if minutes > 0 => Ok(TimeControl {
+                base: Duration::from_secs(minutes as u64 * 60),
+                increment: Duration::from_secs(seconds as u64),
+            }),
+            _ => Err(ah::format_err!(
+                "Invalid time control '{}'. Must be \"MINUTES+SECONDS\", for example \"5+3\".",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}+{}",
+            self.base.as_secs() / 60,
+            self.increment.as_secs()
+        )
+    }
+}
+
+/// The settings of one server room.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RoomConfig {
+    pub name: String,
+    pub rules: RuleSet,
+    pub seat_policy: SeatPolicy,
+    pub load_policy: LoadPolicy,
+    pub reset_policy: ResetPolicy,
+    /// The maximum number of players in the room, including spectators.
+    pub max_players: usize,
+    /// True, if spectators may join the room.
+    pub spectators: bool,
+    /// The password that is needed to join the room, if any.
+    pub password: Option<String>,
+    /// True, if the room is not shown in the room list.
+    /// The room can still be joined by its name.
+    pub unlisted: bool,
+    /// The time control of the games in the room, if any.
+    pub time_control: Option<TimeControl>,
+}
+
+impl RoomConfig {
+    /// Create the default settings of a room.
+    pub fn new(rules: RuleSet) -> RoomConfig {
+        RoomConfig {
+            name: "default".to_string(),
+            rules,
+            seat_policy: SeatPolicy::Open,
+            load_policy: LoadPolicy::Open,
+            reset_policy: ResetPolicy::Timeout(Duration::from_secs(30)),
+            max_players: MAX_PLAYERS,
+            spectators: true,
+            password: None,
+            unlisted: false,
+            time_control: None,
+        }
+    }
+
+    /// Create the settings of a room from a room given as
+    /// "NAME:RULES:RESET-POLICY:SEAT-POLICY" on the command line.
+    /// Trailing parts may be omitted and empty parts select the settings of self.
+    pub fn with_spec(&self, spec: &str) -> ah::Result<RoomConfig> {
+        let mut config = self.clone();
+        let mut parts = spec.splitn(4, ':');
+        config.name = check_room_name(parts.next().unwrap_or_default())?;
+        if let Some(rules) = parts.next().filter(|p| !p.is_empty()) {
+            config.rules = RuleSet::from_name(rules)?;
+        }
+        if let Some(policy) = parts.next().filter(|p| !p.is_empty()) {
+            config.reset_policy = ResetPolicy::from_name(policy)?;
+        }
+        if let Some(policy) = parts.next().filter(|p| !p.is_empty()) {
+            config.seat_policy = SeatPolicy::from_name(policy)?;
+        }
+        Ok(config)
+    }
+
+    /// Create the settings of a room from a [[room]] table of the config file.
+    /// Missing settings are taken from self.
+    fn with_table(&self, table: &toml::Table) -> ah::Result<RoomConfig> {
+        if let Some(key) = table.keys().find(|key| !ROOM_KEYS.contains(&key.as_str())) {
+            return Err(ah::format_err!("Unknown room setting '{}'.", key));
+        }
+
+        let mut config = self.clone();
+        config.name = match get_str(table, "name")? {
+            Some(name) => check_room_name(name)?,
+            None => return Err(ah::format_err!("The room has no 'name'.")),
+        };
+        let context = |key: &str, e: ah::Error| {
+            ah::format_err!("Room '{}': Invalid '{}': {}", config.name, key, e)
+        };
+        if let Some(rules) = get_str(table, "rules")? {
+            config.rules = RuleSet::from_name(rules).map_err(|e| context("rules", e))?;
+        }
+        if let Some(policy) = get_str(table, "seat-policy")? {
+            config.seat_policy =
+                SeatPolicy::from_name(policy).map_err(|e| context("seat-policy", e))?;
+        }
+        if let Some(policy) = get_str(table, "load-policy")? {
+            config.load_policy =
+                LoadPolicy::from_name(policy).map_err(|e| context("load-policy", e))?;
+        }
+        if let Some(policy) = get_str(table, "reset-policy")? {
+            config.reset_policy =
+                ResetPolicy::from_name(policy).map_err(|e| context("reset-policy", e))?;
+        }
+        if let Some(max_players) = get_int(table, "max-players")? {
+            if max_players < 1 || max_players > MAX_PLAYERS as i64 {
+                return Err(context(
+                    "max-players",
+                    ah::format_err!("Must be between 1 and {}.", MAX_PLAYERS),
+                ));
+            }
+            config.max_players = max_players as usize;
+        }
+        if let Some(spectators) = get_bool(table, "spectators")? {
+            config.spectators = spectators;
+        }
+        if let Some(password) = get_str(table, "password")? {
+            config.password = check_room_password(password).map_err(|e| context("password", e))?;
+        }
+        if let Some(unlisted) = get_bool(table, "unlisted")? {
+            config.unlisted = unlisted;
+        }
+        if let Some(time_control) = get_str(table, "time-control")? {
+            config.time_control = match time_control {
+                "" | "none" => None,
+                time_control => Some(
+                    TimeControl::from_name(time_control).map_err(|e| context("time-control", e))?,
+                ),
+            };
+        }
+        Ok(config)
+    }
+}
+
+impl fmt::Display for RoomConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, reset: {}, seats: {}, max players: {}",
+            self.name, self.rules.name, self.reset_policy, self.seat_policy, self.max_players
+        )?;
+        if !self.spectators {
+            write!(f, ", no spectators")?;
+        }
+        if let Some(time_control) = self.time_control {
+            write!(f, ", time control: {}", time_control)?;
+        }
+        if self.password.is_some() {
+            write!(f, ", password protected")?;
+        }
+        if self.unlisted {
+            write!(f, ", unlisted")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Check if a room name can be used.
+/// Returns the name without leading and trailing white space.
+pub fn check_room_name(name: &str) -> ah::Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ah::format_err!("The room name is empty."));
+    }
+    if name.len() > MSG_MAXROOMNAME {
+        return Err(ah::format_err!(
+            "The room name '{}' is longer than {} bytes.",
+            name,
+            MSG_MAXROOMNAME
+        ));
+    }
+    if name.chars().any(|c| c.is_control() || c == ':') {
+        return Err(ah::format_err!(
+            "The room name '{}' contains a control character or a colon.",
+            name.escape_debug()
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Check if a room password can be used.
+/// Returns None for an empty password.
+pub fn check_room_password(password: &str) -> ah::Result<Option<String>> {
+    if password.len() > MSG_MAXPASSWORD {
+        return Err(ah::format_err!(
+            "The password is longer than {} bytes.",
+            MSG_MAXPASSWORD
+        ));
+    }
+    Ok(Some(password.to_string()).filter(|p| !p.is_empty()))
+}
+
+/// Get an optional string setting.
+fn get_str<'a>(table: &'a toml::Table, key: &str) -> ah::Result<Option<&'a str>> {
+    match table.get(key) {
+        Some(toml::Value::String(value)) => Ok(Some(value)),
+        Some(value) => Err(ah::format_err!(
+            "'{}' must be a string, not {}.",
+            key,
+            value.type_str()
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Get an optional integer setting.
+fn get_int(table: &toml::Table, key: &str) -> ah::Result<Option<i64>> {
+    match table.get(key) {
+        Some(toml::Value::Integer(value)) => Ok(Some(*value)),
+        Some(value) => Err(ah::format_err!(
+            "'{}' must be an integer, not {}.",
+            key,
+            value.type_str()
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Get an optional boolean setting.
+fn get_bool(table: &toml::Table, key: &str) -> ah::Result<Option<bool>> {
+    match table.get(key) {
+        Some(toml::Value::Boolean(value)) => Ok(Some(*value)),
+        Some(value) => Err(ah::format_err!(
+            "'{}' must be true or false, not {}.",
+            key,
+            value.type_str()
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Get an optional integer setting within a range.
+fn get_int_in(table: &toml::Table, key: &str, min: i64, max: i64) -> ah::Result<Option<i64>> {
+    match get_int(table, key)? {
+        Some(value) if value < min || value > max => Err(ah::format_err!(
+            "Invalid '{}': Must be between {} and {}.",
+            key,
+            min,
+            max
+        )),
+        value => Ok(value),
+    }
+}
+
+/// The server settings of a config file or of the command line.
+/// Settings that are not given are None.
+#[derive(Clone, Default, Debug)]
+pub struct ServerConfig {
+    /// The addresses to bind the server to.
+    pub bind: Option<Vec<String>>,
+    pub port: Option<u16>,
+    pub max_connections: Option<u16>,
+    pub log_level: Option<u8>,
+    /// The maximum number of rooms that the clients from one address may create.
+    pub max_created_rooms: Option<usize>,
+    /// The time after which an empty room that has been created by a client is closed.
+    pub abandoned_room_timeout: Option<Duration>,
+    pub rooms: Vec<RoomConfig>,
+}
+
+impl ServerConfig {
+    /// Override the settings with the settings that are given in other.
+    /// A room of other replaces the room of the same name.
+    pub fn override_with(&mut self, other: &ServerConfig) {
+        if other.bind.is_some() {
+            self.bind = other.bind.clone();
+        }
+        self.port = other.port.or(self.port);
+        self.max_connections = other.max_connections.or(self.max_connections);
+        self.log_level = other.log_level.or(self.log_level);
+        self.max_created_rooms = other.max_created_rooms.or(self.max_created_rooms);
+        self.abandoned_room_timeout = other.abandoned_room_timeout.or(self.abandoned_room_timeout);
+        for room in &other.rooms {
+            match self.rooms.iter_mut().find(|r| r.name == room.name) {
+                Some(r) => *r = room.clone(),
+                None => self.rooms.push(room.clone()),
+            }
+        }
+    }
+
+    fn from_table(table: &toml::Table, room_defaults: &RoomConfig) -> ah::Result<ServerConfig> {
+        let mut config = ServerConfig::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("bind", toml::Value::String(addr)) => {
+                    config.bind = Some(vec![addr.clone()]);
+                }
+                ("bind", toml::Value::Array(addrs)) => {
+                    let addrs: Option<Vec<String>> = addrs
+                        .iter()
+                        .map(|addr| addr.as_str().map(|addr| addr.to_string()))
+                        .collect();
+                    match addrs {
+                        Some(addrs) if !addrs.is_empty() => config.bind = Some(addrs),
+                        Some(_) | None => {
+                            return Err(ah::format_err!(
+                                "'bind' must be an address or a list of addresses."
+                            ));
+                        }
+                    }
+                }
+                ("bind", value) => {
+                    return Err(ah::format_err!(
+                        "'bind' must be an address or a list of addresses, not {}.",
+                        value.type_str()
+                    ));
+                }
+                ("port", _) => {
+                    config.port = get_int_in(table, key, 1, u16::MAX as i64)?.map(|p| p as u16);
+                }
+                ("max-connections", _) => {
+                    config.max_connections =
+                        get_int_in(table, key, 1, u16::MAX as i64)?.map(|m| m as u16);
+                }
+                ("log-level", _) => {
+                    config.log_level = get_int_in(table, key, 0, 4)?.map(|l| l as u8);
+                }
+                ("max-created-rooms", _) => {
+                    config.max_created_rooms =
+                        get_int_in(table, key, 0, MAX_ROOMS as i64)?.map(|m| m as usize);
+                }
+                ("abandoned-room-timeout", _) => {
+                    config.abandoned_room_timeout = get_int_in(table, key, 1, u32::MAX as i64)?
+                        .map(|t| Duration::from_secs(t as u64));
+                }
+                ("room", toml::Value::Array(tables)) => {
+                    if tables.len() > MAX_ROOMS {
+                        return Err(ah::format_err!(
+                            "Maximum number of rooms ({}) exceeded.",
+                            MAX_ROOMS
+                        ));
+                    }
+                    for (index, table) in tables.iter().enumerate() {
+                        let room = match table {
+                            toml::Value::Table(table) => room_defaults.with_table(table),
+                            _ => Err(ah::format_err!("A room must be a [[room]] table.")),
+                        };
+                        let room =
+                            room.map_err(|e| ah::format_err!("room {}: {}", index + 1, e))?;
+                        if config.rooms.iter().any(|r| r.name == room.name) {
+                            return Err(ah::format_err!(
+                                "room {}: The room '{}' is defined more than once.",
+                                index + 1,
+                                room.name
+                            ));
+                        }
+                        config.rooms.push(room);
+                    }
+                }
+                ("room", _) => {
+                    return Err(ah::format_err!(
+                        "The rooms must be given as [[room]] tables."
+                    ));
+                }
+                (key, _) => {
+                    return Err(ah::format_err!("Unknown setting '{}'.", key));
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The config file of the server.
+pub struct ConfigFile {
+    path: PathBuf,
+    /// The settings of the rooms that are not given in the file.
+    room_defaults: RoomConfig,
+    /// The modification time of the file when it has been read last.
+    modified: Option<SystemTime>,
+}
+
+impl ConfigFile {
+    pub fn new(path: &Path, room_defaults: RoomConfig) -> ConfigFile {
+        ConfigFile {
+            path: path.to_path_buf(),
+            room_defaults,
+            modified: None,
+        }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    fn get_modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    /// Check if the file has been modified since it has been read last.
+    pub fn is_modified(&self) -> bool {
+        let modified = self.get_modified();
+        modified.is_some() && modified != self.modified
+    }
+
+    /// Read the settings from the file.
+    /// Each room is a [[room]] table. Missing room settings are taken from the room defaults.
+    pub fn read(&mut self) -> ah::Result<ServerConfig> {
+        let path = self.path.display();
+        self.modified = self.get_modified();
+        let text = fs::read_to_string(&self.path)
+            .map_err(|e| ah::format_err!("Failed to read config file '{}': {}", path, e))?;
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| ah::format_err!("Failed to parse config file '{}': {}", path, e))?;
+        ServerConfig::from_table(&table, &self.room_defaults)
+            .map_err(|e| ah::format_err!("Config file '{}': {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room_defaults() -> RoomConfig {
+        RoomConfig::new(RuleSet::from_name("standard").unwrap())
+    }
+
+    fn parse(text: &str) -> ah::Result<ServerConfig> {
+        let table: toml::Table = text.parse().unwrap();
+        ServerConfig::from_table(&table, &room_defaults())
+    }
+
+    #[test]
+    fn test_time_control() {
+        let time_control = TimeControl::from_name("5+3").unwrap();
+        assert_eq!(time_control.base, Duration::from_secs(300));
+        assert_eq!(time_control.increment, Duration::from_secs(3));
+        assert_eq!(time_control.to_string(), "5+3");
+        assert_eq!(TimeControl::from_name("10").unwrap().to_string(), "10+0");
+        for name in ["", "0+5", "x", "5+x", "-1+2"] {
+            assert!(TimeControl::from_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_server_settings() {
+        let config = parse(
+            "bind = [\"127.0.0.1\", \"::1\"]\n\
+             port = 5597\n\
+             max-connections = 10\n\
+             log-level = 3\n\
+             max-created-rooms = 0\n\
+             abandoned-room-timeout = 60\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.bind,
+            Some(vec!["127.0.0.1".to_string(), "::1".to_string()])
+        );
+        assert_eq!(config.port, Some(5597));
+        assert_eq!(config.max_connections, Some(10));
+        assert_eq!(config.log_level, Some(3));
+        assert_eq!(config.max_created_rooms, Some(0));
+        assert_eq!(config.abandoned_room_timeout, Some(Duration::from_secs(60)));
+        assert!(config.rooms.is_empty());
+
+        let config = parse("bind = \"0.0.0.0\"").unwrap();
+        assert_eq!(config.bind, Some(vec!["0.0.0.0".to_string()]));
+        assert_eq!(config.port, None);
+
+        for text in [
+            "bind = 1",
+            "bind = []",
+            "bind = [1]",
+            "port = 0",
+            "port = 65536",
+            "port = \"5596\"",
+            "max-connections = 0",
+            "log-level = 5",
+            "max-created-rooms = -1",
+            "max-created-rooms = 4097",
+            "abandoned-room-timeout = 0",
+            "abandoned-room-timeout = 4294967296",
+            "unknown = 1",
+        ] {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_rooms() {
+        let config = parse(
+            "[[room]]\n\
+             name = \"a\"\n\
+             [[room]]\n\
+             name = \" b \"\n\
+             rules = \"huffing,blocked=draw\"\n\
+             seat-policy = \"seats\"\n\
+             load-policy = \"agree\"\n\
+             reset-policy = \"timeout=10\"\n\
+             max-players = 4\n\
+             spectators = false\n\
+             password = \"secret\"\n\
+             unlisted = true\n\
+             time-control = \"5+3\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.rooms.len(), 2);
+        assert_eq!(
+            config.rooms[0],
+            RoomConfig {
+                name: "a".to_string(),
+                ..room_defaults()
+            }
+        );
+        let room = &config.rooms[1];
+        assert_eq!(room.name, "b");
+        assert_eq!(room.rules.name, "huffing,blocked=draw");
+        assert_eq!(room.seat_policy, SeatPolicy::Seats);
+        assert_eq!(room.load_policy, LoadPolicy::Agree);
+        assert_eq!(
+            room.reset_policy,
+            ResetPolicy::Timeout(Duration::from_secs(10))
+        );
+        assert_eq!(room.max_players, 4);
+        assert!(!room.spectators);
+        assert_eq!(room.password.as_deref(), Some("secret"));
+        assert!(room.unlisted);
+        assert_eq!(
+            room.time_control,
+            Some(TimeControl::from_name("5+3").unwrap())
+        );
+
+        let config = parse("[[room]]\nname = \"a\"\npassword = \"\"").unwrap();
+        assert_eq!(config.rooms[0].password, None);
+
+        let long_name = "x".repeat(MSG_MAXROOMNAME + 1);
+        let long_password = "x".repeat(MSG_MAXPASSWORD + 1);
+        for room in [
+            "rules = \"standard\"".to_string(),
+            "name = \"\"".to_string(),
+            "name = \"a:b\"".to_string(),
+            format!("name = \"{}\"", long_name),
+            "name = \"a\"\nunknown = 1".to_string(),
+            "name = \"a\"\nrules = \"chess\"".to_string(),
+            "name = \"a\"\nseat-policy = \"owner\"".to_string(),
+            "name = \"a\"\nreset-policy = \"timeout=0\"".to_string(),
+            "name = \"a\"\nmax-players = 0".to_string(),
+            "name = \"a\"\nmax-players = 1025".to_string(),
+            "name = \"a\"\nmax-players = \"4\"".to_string(),
+            "name = \"a\"\nspectators = \"no\"".to_string(),
+            format!("name = \"a\"\npassword = \"{}\"", long_password),
+            "name = \"a\"\ntime-control = \"fast\"".to_string(),
+        ] {
+            let text = format!("[[room]]\n{}", room);
+            assert!(parse(&text).is_err(), "{}", text);
+        }
+        assert!(parse("[[room]]\nname = \"a\"\n[[room]]\nname = \" a\"").is_err());
+        assert!(parse("room = 1").is_err());
+        assert!(parse("room = [1]").is_err());
+    }
+
+    #[test]
+    fn test_room_spec() {
+        let defaults = room_defaults();
+        let room = defaults.with_spec("a").unwrap();
+        assert_eq!(
+            room,
+            RoomConfig {
+                name: "a".to_string(),
+                ..room_defaults()
+            }
+        );
+
+        let room = defaults.with_spec("b:asalto::seats").unwrap();
+        assert_eq!(room.name, "b");
+        assert_eq!(room.rules.name, "asalto");
+        assert_eq!(room.reset_policy, defaults.reset_policy);
+        assert_eq!(room.seat_policy, SeatPolicy::Seats);
+
+        for spec in ["", ":standard", "a:chess", "a::never", "a:::none"] {
+            assert!(defaults.with_spec(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_override_with() {
+        let mut config = parse(
+            "port = 1\nlog-level = 1\n\
+             [[room]]\nname = \"a\"\n\
+             [[room]]\nname = \"b\"\n",
+        )
+        .unwrap();
+        let other = parse(
+            "port = 2\nmax-created-rooms = 3\n\
+             [[room]]\nname = \"b\"\nmax-players = 2\n\
+             [[room]]\nname = \"c\"\n",
+        )
+        .unwrap();
+        config.override_with(&other);
+        assert_eq!(config.port, Some(2));
+        assert_eq!(config.log_level, Some(1));
+        assert_eq!(config.max_created_rooms, Some(3));
+        assert_eq!(config.abandoned_room_timeout, None);
+        let names: Vec<&str> = config.rooms.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(config.rooms[1].max_players, 2);
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
@@ -5,34 +5,305 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //
 
-use crate::game_state::GameState;
-use crate::net::consts::MAX_PLAYERS;
+use crate::game_state::{
+    GameState, Turn, VoteSubject, WinReason, WinState, num_to_turn, vote_subject_to_num,
+};
+use crate::net::{
+    protocol::{
+        MSG_MAXPASSWORD, MSG_TAKEBACK_ACTION_REQUEST, MSG_VOTE_ACTION_FAILED,
+        MSG_VOTE_ACTION_PASSED, MSG_VOTE_ACTION_REQUEST, MsgGameState, MsgTakeback, MsgVote,
+    },
+    server::config::{RoomConfig, TimeControl},
+};
 use crate::player::{Player, PlayerList, PlayerMode};
+use crate::print::Print;
 use anyhow as ah;
 use std::cmp::{Eq, Ord, PartialEq, PartialOrd};
+use std::fmt;
+use std::hint::black_box;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Compare two passwords.
+/// Both passwords are padded to the maximum password length and compared over the full
+/// length, so the time of the comparison depends on neither their contents nor their lengths.
+fn password_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let len = MSG_MAXPASSWORD.max(a.len()).max(b.len());
+    let diff = (0..len).fold(a.len() ^ b.len(), |diff, i| {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        black_box(diff | usize::from(x ^ y))
+    });
+    diff == 0
+}
+
+/// The players that may load a game state into a room.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LoadPolicy {
+    /// Every seated player may load a game.
+    Open,
+    /// Only the room owner may load a game.
+    Owner,
+    /// All other seated players have to agree to load a game.
+    Agree,
+}
+
+impl LoadPolicy {
+    pub fn from_name(name: &str) -> ah::Result<LoadPolicy> {
+        match name.to_lowercase().trim() {
+            "open" => Ok(LoadPolicy::Open),
+            "owner" => Ok(LoadPolicy::Owner),
+            "agree" => Ok(LoadPolicy::Agree),
+            name => Err(ah::format_err!(
+                "Unknown load policy '{}'. May be \"open\", \"owner\" or \"agree\".",
+                name
+            )),
+        }
+    }
+}
+
+/// The way the players of a room take the wolf and the sheep side.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SeatPolicy {
+    /// Every player may play the sides of its player mode.
+    /// Several players may play the same side.
+    Open,
+    /// The room has one wolf seat and one sheep seat.
+    /// A player with the mode "both" takes both seats (hotseat).
+    /// Only the player in the seat of a side may move that side.
+    Seats,
+}
+
+impl SeatPolicy {
+    pub fn from_name(name: &str) -> ah::Result<SeatPolicy> {
+        match name.to_lowercase().trim() {
+            "open" => Ok(SeatPolicy::Open),
+            "seats" => Ok(SeatPolicy::Seats),
+            name => Err(ah::format_err!(
+                "Unknown seat policy '{}'. May be \"open\" or \"seats\".",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SeatPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SeatPolicy::Open => "open",
+                SeatPolicy::Seats => "seats",
+            }
+        )
+    }
+}
+
+/// The players in the seats of a room.
+#[derive(Clone, Default, Debug)]
+struct Seats {
+    wolf: Option<String>,
+    sheep: Option<String>,
+}
+
+impl Seats {
+    /// Get the player in the seat of one side.
+    fn get(&self, side: Turn) -> Option<&str> {
+        match side {
+            Turn::Wolf => self.wolf.as_deref(),
+            Turn::Sheep => self.sheep.as_deref(),
+        }
+    }
+
+    /// Take the seats of a player mode.
+    fn take(&mut self, player_name: &str, player_mode: PlayerMode) {
+        if matches!(player_mode, PlayerMode::Wolf | PlayerMode::Both) {
+            self.wolf = Some(player_name.to_string());
+        }
+        if matches!(player_mode, PlayerMode::Sheep | PlayerMode::Both) {
+            self.sheep = Some(player_name.to_string());
+        }
+    }
+
+    /// Free all seats of a player.
+    fn free(&mut self, player_name: &str) {
+        for seat in [&mut self.wolf, &mut self.sheep] {
+            if seat.as_deref() == Some(player_name) {
+                *seat = None;
+            }
+        }
+    }
+}
+
+/// The way a seated player resets the game in a room.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ResetPolicy {
+    /// The game is reset at once.
+    Open,
+    /// All other seated players have to agree to the reset.
+    Agree,
+    /// The game is reset, if no other seated player objects within the timeout.
+    Timeout(Duration),
+}
+
+impl ResetPolicy {
+    pub fn from_name(name: &str) -> ah::Result<ResetPolicy> {
+        let name = name.to_lowercase();
+        match name.trim() {
+            "open" => Ok(ResetPolicy::Open),
+            "agree" => Ok(ResetPolicy::Agree),
+            name => match name
+                .strip_prefix("timeout=")
+                .map(|secs| secs.parse::<u32>())
+            {
+                Some(Ok(secs)) if secs > 0 => {
+                    Ok(ResetPolicy::Timeout(Duration::from_secs(secs as u64)))
+                }
+                Some(_) => Err(ah::format_err!(
+                    "Invalid reset timeout '{}'. Must be a number of seconds greater than 0.",
+                    name
+                )),
+                None => Err(ah::format_err!(
+                    "Unknown reset policy '{}'. May be \"open\", \"agree\" or \"timeout=SECONDS\".",
+                    name
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ResetPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResetPolicy::Open => write!(f, "open"),
+            ResetPolicy::Agree => write!(f, "agree"),
+            ResetPolicy::Timeout(timeout) => write!(f, "timeout={}", timeout.as_secs()),
+        }
+    }
+}
+
+/// A pending vote of the seated players of a room.
+struct RoomVote {
+    subject: VoteSubject,
+    requester: String,
+    /// The players that have not answered the vote, yet.
+    voters: Vec<String>,
+    /// The time at which the vote passes without objection, if any.
+    deadline: Option<Instant>,
+    /// The game state that is loaded, if a load vote passes.
+    game_state: Option<MsgGameState>,
+}
+
+/// The remaining thinking time of both sides of a room with a time control.
+struct RoomClock {
+    wolf: Duration,
+    sheep: Duration,
+    /// The side to move.
+    turn: Turn,
+    /// The time at which the side to move has started thinking, if the clock runs.
+    running_since: Option<Instant>,
+}
+
+impl RoomClock {
+    fn new(time_control: TimeControl, turn: Turn) -> RoomClock {
+        RoomClock {
+            wolf: time_control.base,
+            sheep: time_control.base,
+            turn,
+            running_since: None,
+        }
+    }
+
+    fn remaining_mut(&mut self, side: Turn) -> &mut Duration {
+        match side {
+            Turn::Wolf => &mut self.wolf,
+            Turn::Sheep => &mut self.sheep,
+        }
+    }
+}
 
 pub struct ServerRoom {
-    name: String,
+    config: RoomConfig,
     game_state: GameState,
     player_list: PlayerList,
-    restrict_player_modes: bool,
+    seats: Seats,
+    vote: Option<RoomVote>,
+    /// The player that has requested the pending takeback, if any.
+    takeback_requester: Option<String>,
+    clock: Option<RoomClock>,
+    /// The address of the client that has created the room,
+    /// if the room has been created at runtime.
+    creator: Option<IpAddr>,
+    /// The time at which the last player has left the room, if the room is empty.
+    empty_since: Option<Instant>,
 }
 
 impl ServerRoom {
-    pub fn new(name: String, restrict_player_modes: bool) -> ah::Result<ServerRoom> {
-        let mut game_state = GameState::new(PlayerMode::Both, None)?; /* no player name */
+    pub fn new(config: RoomConfig, creator: Option<IpAddr>) -> ah::Result<ServerRoom> {
+        /* no player name */
+        let mut game_state = GameState::new(PlayerMode::Both, None, config.rules.clone())?;
         let player_list = PlayerList::new(vec![]);
         game_state.set_room_player_list(player_list.clone());
         Ok(ServerRoom {
-            name,
+            config,
             game_state,
             player_list,
-            restrict_player_modes,
+            seats: Seats::default(),
+            vote: None,
+            takeback_requester: None,
+            clock: None,
+            creator,
+            empty_since: Some(Instant::now()),
         })
     }
 
     pub fn get_name(&self) -> &str {
-        &self.name
+        &self.config.name
+    }
+
+    pub fn get_config(&self) -> &RoomConfig {
+        &self.config
+    }
+
+    /// Get the address of the client that has created the room at runtime.
+    pub fn get_creator(&self) -> Option<IpAddr> {
+        self.creator
+    }
+
+    /// Check if the room has been created at runtime and has been empty for the timeout.
+    pub fn is_abandoned(&self, timeout: Duration) -> bool {
+        self.creator.is_some()
+            && self
+                .empty_since
+                .is_some_and(|since| since.elapsed() >= timeout)
+    }
+
+    /// Change the settings of the room.
+    /// The rules and the seat policy can only be changed while the room is empty.
+    pub fn set_config(&mut self, config: RoomConfig) -> ah::Result<()> {
+        if config.rules != self.config.rules || config.seat_policy != self.config.seat_policy {
+            if self.player_list.count() > 0 {
+                return Err(ah::format_err!(
+                    "The rules and the seat policy of room '{}' \
+                     can't be changed while players are in the room.",
+                    self.config.name
+                ));
+            }
+            *self = ServerRoom::new(config, self.creator)?;
+            return Ok(());
+        }
+        if config.time_control != self.config.time_control {
+            self.clock = None;
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    /// Get the name of the rule variant played in this room.
+    pub fn get_rules_name(&self) -> String {
+        self.game_state.get_rules().name.clone()
     }
 
     pub fn get_game_state(&mut self, player_mode: PlayerMode) -> &mut GameState {
@@ -42,6 +313,26 @@ impl ServerRoom {
         &mut self.game_state
     }
 
+    /// Check the password that a player has given to join the room.
+    pub fn check_password(&self, password: &str) -> ah::Result<()> {
+        match self.config.password.as_deref() {
+            Some(room_password) if !password_eq(room_password, password) => {
+                if password.is_empty() {
+                    Err(ah::format_err!(
+                        "Room '{}' needs a password.",
+                        self.config.name
+                    ))
+                } else {
+                    Err(ah::format_err!(
+                        "Wrong password for room '{}'.",
+                        self.config.name
+                    ))
+                }
+            }
+            Some(_) | None => Ok(()),
+        }
+    }
+
     pub fn can_add_player(
         &self,
         player_name: &str,
@@ -53,34 +344,34 @@ impl ServerRoom {
             player_list.remove_player_by_name(ignore_name);
         }
 
-        if self.restrict_player_modes {
-            match player_mode {
-                PlayerMode::Spectator => (),
-                PlayerMode::Both => {
+        if player_mode == PlayerMode::Spectator && !self.config.spectators {
+            return Err(ah::format_err!(
+                "Room '{}' does not allow spectators.",
+                self.config.name
+            ));
+        }
+
+        if self.config.seat_policy == SeatPolicy::Seats {
+            let sides = match player_mode {
+                PlayerMode::Spectator => vec![],
+                PlayerMode::Both => vec![Turn::Wolf, Turn::Sheep],
+                PlayerMode::Wolf => vec![Turn::Wolf],
+                PlayerMode::Sheep => vec![Turn::Sheep],
+            };
+            for side in sides {
+                if let Some(seated_name) = self.seats.get(side)
+                    && Some(seated_name) != ignore_name
+                {
                     return Err(ah::format_err!(
-                        "PlayerMode::Both not supported in restricted mode."
+                        "The {} seat is already taken by '{}'.",
+                        side,
+                        seated_name
                     ));
                 }
-                PlayerMode::Wolf => {
-                    if !player_list
-                        .find_players_by_mode(PlayerMode::Wolf)
-                        .is_empty()
-                    {
-                        return Err(ah::format_err!("The game already has a Wolf player."));
-                    }
-                }
-                PlayerMode::Sheep => {
-                    if !player_list
-                        .find_players_by_mode(PlayerMode::Sheep)
-                        .is_empty()
-                    {
-                        return Err(ah::format_err!("The game already has a Sheep player."));
-                    }
-                }
             }
         }
 
-        if player_list.count() < MAX_PLAYERS {
+        if player_list.count() < self.config.max_players {
             if player_list.find_player_by_name(player_name).is_some() {
                 Err(ah::format_err!(
                     "Player name '{}' is already occupied.",
@@ -104,39 +395,830 @@ impl ServerRoom {
             .add_player(Player::new(player_name.to_string(), player_mode, false));
         self.game_state
             .set_room_player_list(self.player_list.clone());
+        if self.config.seat_policy == SeatPolicy::Seats {
+            self.seats.take(player_name, player_mode);
+        }
+        self.empty_since = None;
         Ok(())
     }
 
-    pub fn remove_player(&mut self, player_name: &str) {
+    /// Remove a player from the room.
+    /// Returns the outcome of the pending vote, if the player's leave has decided it.
+    pub fn remove_player(&mut self, player_name: &str) -> Option<MsgVote> {
         self.player_list.remove_player_by_name(player_name);
         self.game_state
             .set_room_player_list(self.player_list.clone());
+        self.seats.free(player_name);
+        if self.takeback_requester.as_deref() == Some(player_name) {
+            self.takeback_requester = None;
+        }
+        if self.player_list.count() == 0 {
+            self.empty_since = Some(Instant::now());
+        }
+
+        let passed = match self.vote.as_mut() {
+            Some(vote) if vote.requester == player_name => false,
+            Some(vote) if vote.voters.iter().any(|v| v == player_name) => {
+                vote.voters.retain(|v| v != player_name);
+                if !vote.voters.is_empty() {
+                    return None;
+                }
+                true
+            }
+            Some(_) | None => return None,
+        };
+        self.finish_vote_logged(passed)
     }
 
     pub fn get_player_list_ref(&self) -> &PlayerList {
         &self.player_list
     }
+
+    /// Check if a player may move the tokens of one side.
+    pub fn check_seat(
+        &self,
+        player_name: &str,
+        player_mode: PlayerMode,
+        side: Turn,
+    ) -> ah::Result<()> {
+        let seated = match self.config.seat_policy {
+            SeatPolicy::Open => matches!(
+                (player_mode, side),
+                (PlayerMode::Both, _)
+                    | (PlayerMode::Wolf, Turn::Wolf)
+                    | (PlayerMode::Sheep, Turn::Sheep)
+            ),
+            SeatPolicy::Seats => self.seats.get(side) == Some(player_name),
+        };
+        if seated {
+            Ok(())
+        } else {
+            Err(ah::format_err!(
+                "Player '{}' is not in the {} seat.",
+                player_name,
+                side
+            ))
+        }
+    }
+
+    /// Request a takeback or answer the pending takeback request.
+    /// The request has to be answered by another player than the requester.
+    pub fn handle_takeback(
+        &mut self,
+        player_name: &str,
+        player_mode: PlayerMode,
+        msg: &MsgTakeback,
+    ) -> ah::Result<()> {
+        let (action, turn) = msg.get_action();
+        self.check_seat(player_name, player_mode, num_to_turn(turn)?)?;
+        if action != MSG_TAKEBACK_ACTION_REQUEST
+            && self.game_state.get_takeback_request().is_some()
+            && self.takeback_requester.as_deref() == Some(player_name)
+        {
+            return Err(ah::format_err!(
+                "Player '{}' can't answer the own takeback request.",
+                player_name
+            ));
+        }
+        self.get_game_state(player_mode)
+            .server_handle_rx_msg_takeback(msg)?;
+        self.takeback_requester = if action == MSG_TAKEBACK_ACTION_REQUEST {
+            Some(player_name.to_string())
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    /// Get the pending draw offer and takeback request.
+    pub fn get_offers(&self) -> (Option<Turn>, Option<Turn>) {
+        (
+            self.game_state.get_draw_offer(),
+            self.game_state.get_takeback_request(),
+        )
+    }
+
+    /// Get the name of the room owner.
+    /// The owner is the seated player that has been in the room for the longest time.
+    pub fn get_owner(&self) -> Option<&str> {
+        self.player_list
+            .iter()
+            .find(|player| player.mode != PlayerMode::Spectator)
+            .map(|player| player.name.as_str())
+    }
+
+    /// Load a game state that has been uploaded by a player.
+    /// The game state is checked against the rules and the load policy of the room.
+    /// Returns a vote request, if the other seated players have to agree first.
+    pub fn load_game_state(
+        &mut self,
+        player_name: &str,
+        player_mode: PlayerMode,
+        msg: &MsgGameState,
+    ) -> ah::Result<Option<MsgVote>> {
+        if player_mode == PlayerMode::Spectator {
+            return Err(ah::format_err!(
+                "Player is spectator. Not allowed to load game state."
+            ));
+        }
+        self.game_state.server_check_state_message(msg)?;
+
+        match self.config.load_policy {
+            LoadPolicy::Open => (),
+            LoadPolicy::Owner => {
+                if self.get_owner() != Some(player_name) {
+                    return Err(ah::format_err!("Only the room owner may load a game."));
+                }
+            }
+            LoadPolicy::Agree => {
+                let request =
+                    self.start_vote(VoteSubject::Load, player_name, None, Some(msg.clone()))?;
+                if request.is_some() {
+                    return Ok(request);
+                }
+            }
+        }
+
+        self.get_game_state(player_mode)
+            .read_state_message(msg, false)?;
+        Ok(None)
+    }
+
+    /// Reset the game on request of a player.
+    /// Returns a vote request, if the other seated players have to agree first.
+    pub fn reset_game(
+        &mut self,
+        player_name: &str,
+        player_mode: PlayerMode,
+    ) -> ah::Result<Option<MsgVote>> {
+        if player_mode == PlayerMode::Spectator {
+            return Err(ah::format_err!(
+                "Player is spectator. Not allowed to reset the game."
+            ));
+        }
+
+        let timeout = match self.config.reset_policy {
+            ResetPolicy::Open => {
+                self.start_new_game(player_mode);
+                return Ok(None);
+            }
+            ResetPolicy::Agree => None,
+            ResetPolicy::Timeout(timeout) => Some(timeout),
+        };
+        let request = self.start_vote(VoteSubject::Reset, player_name, timeout, None)?;
+        if request.is_none() {
+            // There is nobody to ask.
+            self.start_new_game(player_mode);
+        }
+        Ok(request)
+    }
+
+    /// Reset the game and the clock.
+    fn start_new_game(&mut self, player_mode: PlayerMode) {
+        self.get_game_state(player_mode).reset_game(true);
+        self.clock = None;
+    }
+
+    /// Start a vote of the other seated players.
+    /// Returns the vote request, or None if there is no other seated player to ask.
+    fn start_vote(
+        &mut self,
+        subject: VoteSubject,
+        requester: &str,
+        timeout: Option<Duration>,
+        game_state: Option<MsgGameState>,
+    ) -> ah::Result<Option<MsgVote>> {
+        if self.vote.is_some() {
+            return Err(ah::format_err!("A vote is already in progress."));
+        }
+        let voters: Vec<String> = self
+            .player_list
+            .iter()
+            .filter(|p| p.mode != PlayerMode::Spectator && p.name != requester)
+            .map(|p| p.name.clone())
+            .collect();
+        if voters.is_empty() {
+            return Ok(None);
+        }
+
+        let request = MsgVote::new(
+            MSG_VOTE_ACTION_REQUEST,
+            vote_subject_to_num(subject),
+            timeout.map(|t| t.as_secs() as u32).unwrap_or(0),
+            requester,
+        )?;
+        self.vote = Some(RoomVote {
+            subject,
+            requester: requester.to_string(),
+            voters,
+            deadline: timeout.map(|t| Instant::now() + t),
+            game_state,
+        });
+        Ok(Some(request))
+    }
+
+    /// Agree to or object to the pending vote.
+    /// Returns the outcome of the vote, if this answer has decided it.
+    pub fn answer_vote(
+        &mut self,
+        player_name: &str,
+        subject: VoteSubject,
+        accept: bool,
+    ) -> ah::Result<Option<MsgVote>> {
+        let vote = match self.vote.as_mut() {
+            Some(vote) if vote.subject == subject => vote,
+            Some(_) | None => {
+                return Err(ah::format_err!("There is no vote to {}.", subject));
+            }
+        };
+        if !vote.voters.iter().any(|v| v == player_name) {
+            return Err(ah::format_err!(
+                "Player '{}' can't vote on this request.",
+                player_name
+            ));
+        }
+        vote.voters.retain(|v| v != player_name);
+
+        if !accept {
+            Ok(Some(self.finish_vote(false)?))
+        } else if vote.voters.is_empty() {
+            Ok(Some(self.finish_vote(true)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Let the pending vote pass, if its timeout has expired without objection.
+    /// Returns the outcome of the vote, if it has passed.
+    pub fn check_vote_timeout(&mut self) -> Option<MsgVote> {
+        match self.vote.as_ref() {
+            Some(RoomVote {
+                deadline: Some(deadline),
+                ..
+            }) if Instant::now() >= *deadline => self.finish_vote_logged(true),
+            Some(_) | None => None,
+        }
+    }
+
+    /// Update the clock of the room after the game state has changed.
+    /// The thinking time of a side that has completed its move is reduced
+    /// by the time it has taken and increased by the increment of the time control.
+    /// The clock starts with the first move of a new game and stops, if the game is decided.
+    pub fn update_clock(&mut self) {
+        let time_control = match self.config.time_control {
+            Some(time_control) => time_control,
+            None => return,
+        };
+        let turn = self.game_state.get_turn();
+        let clock = match self.clock.as_mut() {
+            Some(clock) => clock,
+            None => {
+                // A new game has been started. Start a new clock.
+                self.clock = Some(RoomClock::new(time_control, turn));
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        if clock.turn != turn {
+            let moved = clock.turn;
+            let elapsed = clock
+                .running_since
+                .map(|since| now.duration_since(since))
+                .unwrap_or_default();
+            let remaining = clock.remaining_mut(moved);
+            *remaining = remaining.saturating_sub(elapsed) + time_control.increment;
+            clock.turn = turn;
+            clock.running_since = Some(now);
+        }
+        if self.game_state.get_win_state() != WinState::Undecided {
+            clock.running_since = None;
+        }
+    }
+
+    /// Check if the side to move has run out of thinking time.
+    /// In that case the other side wins the game.
+    /// Returns true, if the game has been decided.
+    pub fn check_clock(&mut self) -> bool {
+        let clock = match self.clock.as_mut() {
+            Some(clock) => clock,
+            None => return false,
+        };
+        let since = match clock.running_since {
+            Some(since) => since,
+            None => return false,
+        };
+        let flagged = clock.turn;
+        if since.elapsed() < *clock.remaining_mut(flagged) {
+            return false;
+        }
+        *clock.remaining_mut(flagged) = Duration::ZERO;
+        clock.running_since = None;
+
+        let win_state = match flagged {
+            Turn::Wolf => WinState::Sheep,
+            Turn::Sheep => WinState::Wolf,
+        };
+        match self
+            .game_state
+            .declare_result(win_state, WinReason::Timeout)
+        {
+            Ok(()) => {
+                Print::info(&format!(
+                    "Room '{}': The {} side has run out of time.",
+                    self.config.name, flagged
+                ));
+                true
+            }
+            Err(e) => {
+                Print::error(&format!("Failed to end the game on time: {}", e));
+                false
+            }
+        }
+    }
+
+    /// End the pending vote and carry out its subject, if the vote has passed.
+    fn finish_vote(&mut self, passed: bool) -> ah::Result<MsgVote> {
+        let vote = match self.vote.take() {
+            Some(vote) => vote,
+            None => return Err(ah::format_err!("There is no vote in progress.")),
+        };
+        if passed {
+            match (vote.subject, vote.game_state.as_ref()) {
+                (VoteSubject::Load, Some(game_state)) => {
+                    self.game_state.read_state_message(game_state, true)?;
+                }
+                (VoteSubject::Load, None) => {
+                    return Err(ah::format_err!("The vote has no game state to load."));
+                }
+                (VoteSubject::Reset, _) => {
+                    self.start_new_game(PlayerMode::Both);
+                }
+            }
+        }
+        MsgVote::new(
+            if passed {
+                MSG_VOTE_ACTION_PASSED
+            } else {
+                MSG_VOTE_ACTION_FAILED
+            },
+            vote_subject_to_num(vote.subject),
+            0,
+            &vote.requester,
+        )
+    }
+
+    /// End the pending vote, if it has been decided without an answer.
+    fn finish_vote_logged(&mut self, passed: bool) -> Option<MsgVote> {
+        match self.finish_vote(passed) {
+            Ok(msg) => Some(msg),
+            Err(e) => {
+                Print::error(&format!("Failed to finish the vote: {}", e));
+                None
+            }
+        }
+    }
 }
 
 impl PartialEq for ServerRoom {
     fn eq(&self, other: &Self) -> bool {
-        self.name.eq(&other.name)
+        self.config.name.eq(&other.config.name)
     }
 }
 
 #[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for ServerRoom {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.name.partial_cmp(&other.name)
+        self.config.name.partial_cmp(&other.config.name)
     }
 }
 
 impl Ord for ServerRoom {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.cmp(&other.name)
+        self.config.name.cmp(&other.config.name)
     }
 }
 
 impl Eq for ServerRoom {}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::RuleSet;
+    use crate::net::protocol::{
+        MSG_TAKEBACK_ACTION_ACCEPT, MSG_TAKEBACK_ACTION_DECLINE, MSG_VOTE_SUBJECT_LOAD,
+        MSG_VOTE_SUBJECT_RESET,
+    };
+
+    const SHEEP: u32 = 0;
+    const WOLF: u32 = 1;
+
+    fn new_room(seat_policy: SeatPolicy) -> ServerRoom {
+        let config = RoomConfig {
+            seat_policy,
+            ..RoomConfig::new(RuleSet::from_name("standard").unwrap())
+        };
+        ServerRoom::new(config, None).unwrap()
+    }
+
+    /// Make the first legal move in the room.
+    fn make_move(room: &mut ServerRoom) {
+        let game = room.get_game_state(PlayerMode::Both);
+        let mov = game.legal_moves()[0];
+        game.make_move(&mov).unwrap();
+    }
+
+    /// Create a room with two seated players and a spectator.
+    fn new_player_room() -> ServerRoom {
+        let mut room = new_room(SeatPolicy::Open);
+        room.add_player("a", PlayerMode::Both).unwrap();
+        room.add_player("b", PlayerMode::Both).unwrap();
+        room.add_player("c", PlayerMode::Spectator).unwrap();
+        room
+    }
+
+    /// Create a room with the reset policy and two seated players and a spectator.
+    fn new_vote_room(reset_policy: ResetPolicy) -> ServerRoom {
+        let mut room = new_player_room();
+        room.config.reset_policy = reset_policy;
+        make_move(&mut room);
+        room
+    }
+
+    fn move_count(room: &mut ServerRoom) -> usize {
+        room.get_game_state(PlayerMode::Both)
+            .get_recorder()
+            .get_moves()
+            .len()
+    }
+
+    fn takeback(action: u32, turn: u32, player_name: &str) -> MsgTakeback {
+        MsgTakeback::new(action, turn, player_name).unwrap()
+    }
+
+    /// Get the state of the room game after its first legal move.
+    fn moved_state(room: &mut ServerRoom) -> MsgGameState {
+        let mut game = room.get_game_state(PlayerMode::Both).clone_local();
+        let mov = game.legal_moves()[0];
+        game.make_move(&mov).unwrap();
+        game.make_state_message()
+    }
+
+    fn position(room: &mut ServerRoom) -> String {
+        room.get_game_state(PlayerMode::Both)
+            .get_position_notation()
+    }
+
+    #[test]
+    fn test_seats() {
+        let mut room = new_room(SeatPolicy::Seats);
+        room.add_player("wolf", PlayerMode::Wolf).unwrap();
+        room.add_player("watcher", PlayerMode::Spectator).unwrap();
+        assert!(room.add_player("wolf2", PlayerMode::Wolf).is_err());
+        assert!(room.add_player("both", PlayerMode::Both).is_err());
+        assert!(room.add_player("wolf", PlayerMode::Sheep).is_err());
+        room.add_player("sheep", PlayerMode::Sheep).unwrap();
+
+        assert!(
+            room.check_seat("wolf", PlayerMode::Wolf, Turn::Wolf)
+                .is_ok()
+        );
+        assert!(
+            room.check_seat("wolf", PlayerMode::Wolf, Turn::Sheep)
+                .is_err()
+        );
+        assert!(
+            room.check_seat("sheep", PlayerMode::Sheep, Turn::Sheep)
+                .is_ok()
+        );
+        assert!(
+            room.check_seat("watcher", PlayerMode::Spectator, Turn::Wolf)
+                .is_err()
+        );
+
+        // The seat of a player that has left can be taken again.
+        room.remove_player("wolf");
+        assert!(
+            room.check_seat("wolf", PlayerMode::Wolf, Turn::Wolf)
+                .is_err()
+        );
+        room.add_player("wolf2", PlayerMode::Wolf).unwrap();
+        assert!(
+            room.check_seat("wolf2", PlayerMode::Wolf, Turn::Wolf)
+                .is_ok()
+        );
+
+        // Without seats the player mode decides.
+        let room = new_room(SeatPolicy::Open);
+        assert!(room.check_seat("a", PlayerMode::Both, Turn::Wolf).is_ok());
+        assert!(room.check_seat("a", PlayerMode::Both, Turn::Sheep).is_ok());
+        assert!(room.check_seat("a", PlayerMode::Sheep, Turn::Sheep).is_ok());
+        assert!(room.check_seat("a", PlayerMode::Sheep, Turn::Wolf).is_err());
+        assert!(
+            room.check_seat("a", PlayerMode::Spectator, Turn::Sheep)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_takeback() {
+        let mut room = new_room(SeatPolicy::Open);
+        room.add_player("a", PlayerMode::Both).unwrap();
+        room.add_player("b", PlayerMode::Both).unwrap();
+        make_move(&mut room);
+        room.handle_takeback(
+            "a",
+            PlayerMode::Both,
+            &takeback(MSG_TAKEBACK_ACTION_REQUEST, SHEEP, "a"),
+        )
+        .unwrap();
+        assert_eq!(room.get_offers(), (None, Some(Turn::Sheep)));
+
+        // The requester can't answer the own request.
+        let accept = takeback(MSG_TAKEBACK_ACTION_ACCEPT, WOLF, "a");
+        assert!(
+            room.handle_takeback("a", PlayerMode::Both, &accept)
+                .is_err()
+        );
+        assert_eq!(room.get_offers(), (None, Some(Turn::Sheep)));
+
+        room.handle_takeback("b", PlayerMode::Both, &accept)
+            .unwrap();
+        assert_eq!(room.get_offers(), (None, None));
+        let game = room.get_game_state(PlayerMode::Both);
+        assert!(game.get_recorder().get_moves().is_empty());
+        assert_eq!(game.get_turn(), Turn::Sheep);
+    }
+
+    #[test]
+    fn test_takeback_seats() {
+        let mut room = new_room(SeatPolicy::Seats);
+        room.add_player("wolf", PlayerMode::Wolf).unwrap();
+        room.add_player("sheep", PlayerMode::Sheep).unwrap();
+        make_move(&mut room);
+
+        let request = takeback(MSG_TAKEBACK_ACTION_REQUEST, SHEEP, "wolf");
+        assert!(
+            room.handle_takeback("wolf", PlayerMode::Wolf, &request)
+                .is_err()
+        );
+        room.handle_takeback("sheep", PlayerMode::Sheep, &request)
+            .unwrap();
+
+        let decline = takeback(MSG_TAKEBACK_ACTION_DECLINE, WOLF, "sheep");
+        assert!(
+            room.handle_takeback("sheep", PlayerMode::Sheep, &decline)
+                .is_err()
+        );
+        room.handle_takeback("wolf", PlayerMode::Wolf, &decline)
+            .unwrap();
+        assert_eq!(room.get_offers(), (None, None));
+        let game = room.get_game_state(PlayerMode::Both);
+        assert_eq!(game.get_recorder().get_moves().len(), 1);
+    }
+
+    #[test]
+    fn test_load_policy() {
+        assert_eq!(LoadPolicy::from_name(" Agree ").unwrap(), LoadPolicy::Agree);
+        assert!(LoadPolicy::from_name("never").is_err());
+
+        let mut room = new_player_room();
+        room.config.load_policy = LoadPolicy::Owner;
+        let start = position(&mut room);
+        let msg = moved_state(&mut room);
+        assert!(
+            room.load_game_state("c", PlayerMode::Spectator, &msg)
+                .is_err()
+        );
+        assert!(room.load_game_state("b", PlayerMode::Both, &msg).is_err());
+        assert_eq!(position(&mut room), start);
+        assert!(
+            room.load_game_state("a", PlayerMode::Both, &msg)
+                .unwrap()
+                .is_none()
+        );
+        assert_ne!(position(&mut room), start);
+    }
+
+    #[test]
+    fn test_load_vote() {
+        let mut room = new_player_room();
+        room.config.load_policy = LoadPolicy::Agree;
+        let start = position(&mut room);
+        let msg = moved_state(&mut room);
+        let request = room
+            .load_game_state("a", PlayerMode::Both, &msg)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            request.get_action(),
+            (MSG_VOTE_ACTION_REQUEST, MSG_VOTE_SUBJECT_LOAD)
+        );
+        assert!(room.load_game_state("b", PlayerMode::Both, &msg).is_err());
+        assert!(room.answer_vote("a", VoteSubject::Load, true).is_err());
+        assert!(room.answer_vote("c", VoteSubject::Load, true).is_err());
+        assert_eq!(position(&mut room), start);
+
+        let outcome = room.answer_vote("b", VoteSubject::Load, true).unwrap();
+        assert_eq!(
+            outcome.unwrap().get_action(),
+            (MSG_VOTE_ACTION_PASSED, MSG_VOTE_SUBJECT_LOAD)
+        );
+        assert_ne!(position(&mut room), start);
+
+        // A declined vote does not load the game.
+        let mut room = new_player_room();
+        room.config.load_policy = LoadPolicy::Agree;
+        room.load_game_state("a", PlayerMode::Both, &msg).unwrap();
+        let outcome = room.answer_vote("b", VoteSubject::Load, false).unwrap();
+        assert_eq!(
+            outcome.unwrap().get_action(),
+            (MSG_VOTE_ACTION_FAILED, MSG_VOTE_SUBJECT_LOAD)
+        );
+        assert_eq!(position(&mut room), start);
+    }
+
+    #[test]
+    fn test_reset_policy() {
+        for name in ["open", "agree", "timeout=30"] {
+            assert_eq!(ResetPolicy::from_name(name).unwrap().to_string(), name);
+        }
+        for name in ["", "never", "timeout=", "timeout=0", "timeout=x"] {
+            assert!(ResetPolicy::from_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_reset_vote() {
+        let mut room = new_vote_room(ResetPolicy::Agree);
+        assert!(room.reset_game("c", PlayerMode::Spectator).is_err());
+        let request = room.reset_game("a", PlayerMode::Both).unwrap().unwrap();
+        assert_eq!(
+            request.get_action(),
+            (MSG_VOTE_ACTION_REQUEST, MSG_VOTE_SUBJECT_RESET)
+        );
+        assert!(room.reset_game("b", PlayerMode::Both).is_err());
+        assert!(room.answer_vote("a", VoteSubject::Reset, true).is_err());
+        assert!(room.answer_vote("c", VoteSubject::Reset, true).is_err());
+        assert!(room.answer_vote("b", VoteSubject::Load, true).is_err());
+        assert_eq!(move_count(&mut room), 1);
+
+        let outcome = room.answer_vote("b", VoteSubject::Reset, true).unwrap();
+        assert_eq!(
+            outcome.unwrap().get_action(),
+            (MSG_VOTE_ACTION_PASSED, MSG_VOTE_SUBJECT_RESET)
+        );
+        assert_eq!(move_count(&mut room), 0);
+
+        // A declined vote does not reset the game.
+        let mut room = new_vote_room(ResetPolicy::Agree);
+        room.reset_game("a", PlayerMode::Both).unwrap().unwrap();
+        let outcome = room.answer_vote("b", VoteSubject::Reset, false).unwrap();
+        assert_eq!(
+            outcome.unwrap().get_action(),
+            (MSG_VOTE_ACTION_FAILED, MSG_VOTE_SUBJECT_RESET)
+        );
+        assert_eq!(move_count(&mut room), 1);
+
+        // Without a vote the game is reset immediately.
+        let mut room = new_vote_room(ResetPolicy::Open);
+        assert!(room.reset_game("a", PlayerMode::Both).unwrap().is_none());
+        assert_eq!(move_count(&mut room), 0);
+    }
+
+    #[test]
+    fn test_reset_vote_leave() {
+        // The vote fails, if the requester leaves.
+        let mut room = new_vote_room(ResetPolicy::Agree);
+        room.reset_game("a", PlayerMode::Both).unwrap().unwrap();
+        let outcome = room.remove_player("a").unwrap();
+        assert_eq!(outcome.get_action().0, MSG_VOTE_ACTION_FAILED);
+        assert_eq!(move_count(&mut room), 1);
+
+        // The vote passes, if the last voter leaves.
+        let mut room = new_vote_room(ResetPolicy::Agree);
+        room.reset_game("a", PlayerMode::Both).unwrap().unwrap();
+        assert!(room.remove_player("c").is_none());
+        let outcome = room.remove_player("b").unwrap();
+        assert_eq!(outcome.get_action().0, MSG_VOTE_ACTION_PASSED);
+        assert_eq!(move_count(&mut room), 0);
+    }
+
+    #[test]
+    fn test_reset_vote_timeout() {
+        let mut room = new_vote_room(ResetPolicy::Timeout(Duration::from_secs(3600)));
+        let request = room.reset_game("a", PlayerMode::Both).unwrap().unwrap();
+        assert_eq!(request.get_timeout(), 3600);
+        assert!(room.check_vote_timeout().is_none());
+
+        let mut room = new_vote_room(ResetPolicy::Timeout(Duration::ZERO));
+        room.reset_game("a", PlayerMode::Both).unwrap().unwrap();
+        let outcome = room.check_vote_timeout().unwrap();
+        assert_eq!(outcome.get_action().0, MSG_VOTE_ACTION_PASSED);
+        assert_eq!(move_count(&mut room), 0);
+        assert!(room.check_vote_timeout().is_none());
+    }
+
+    fn clock_running(room: &ServerRoom) -> Option<bool> {
+        room.clock
+            .as_ref()
+            .map(|clock| clock.running_since.is_some())
+    }
+
+    #[test]
+    fn test_clock() {
+        let mut room = new_room(SeatPolicy::Open);
+        room.config.time_control = Some(TimeControl::from_name("60").unwrap());
+        room.add_player("a", PlayerMode::Both).unwrap();
+        room.add_player("b", PlayerMode::Both).unwrap();
+
+        // The clock starts with the first move.
+        room.update_clock();
+        assert_eq!(clock_running(&room), Some(false));
+        make_move(&mut room);
+        room.update_clock();
+        assert_eq!(clock_running(&room), Some(true));
+        assert!(!room.check_clock());
+
+        // Taking back the first move does not start a new clock.
+        let request = takeback(MSG_TAKEBACK_ACTION_REQUEST, SHEEP, "a");
+        room.handle_takeback("a", PlayerMode::Both, &request)
+            .unwrap();
+        let accept = takeback(MSG_TAKEBACK_ACTION_ACCEPT, WOLF, "b");
+        room.handle_takeback("b", PlayerMode::Both, &accept)
+            .unwrap();
+        room.update_clock();
+        assert_eq!(clock_running(&room), Some(true));
+
+        // A new game starts a new clock.
+        room.config.reset_policy = ResetPolicy::Open;
+        room.reset_game("a", PlayerMode::Both).unwrap();
+        assert_eq!(clock_running(&room), None);
+        room.update_clock();
+        assert_eq!(clock_running(&room), Some(false));
+    }
+
+    #[test]
+    fn test_clock_timeout() {
+        let mut room = new_room(SeatPolicy::Open);
+        room.config.time_control = Some(TimeControl {
+            base: Duration::ZERO,
+            increment: Duration::ZERO,
+        });
+        room.update_clock();
+        make_move(&mut room);
+        room.update_clock();
+        assert!(room.check_clock());
+        assert_eq!(
+            room.get_game_state(PlayerMode::Both).get_result(),
+            (WinState::Sheep, Some(WinReason::Timeout))
+        );
+        assert!(!room.check_clock());
+    }
+
+    #[test]
+    fn test_password() {
+        let mut room = new_room(SeatPolicy::Open);
+        assert!(room.check_password("").is_ok());
+        assert!(room.check_password("anything").is_ok());
+        room.config.password = Some("secret".to_string());
+        assert!(room.check_password("secret").is_ok());
+        assert!(room.check_password("").is_err());
+        assert!(room.check_password("wrong").is_err());
+
+        assert!(password_eq("", ""));
+        assert!(password_eq("secret", "secret"));
+        assert!(!password_eq("secret", "secreT"));
+        assert!(!password_eq("secret", "secret2"));
+        assert!(!password_eq("secret", ""));
+        assert!(!password_eq("secret\0", "secret"));
+        let long = "x".repeat(MSG_MAXPASSWORD + 1);
+        assert!(password_eq(&long, &long));
+        assert!(!password_eq(&long, &long[..MSG_MAXPASSWORD]));
+    }
+
+    #[test]
+    fn test_abandoned() {
+        let hour = Duration::from_secs(3600);
+
+        // Rooms of the server config are never abandoned.
+        let room = new_room(SeatPolicy::Open);
+        assert_eq!(room.get_creator(), None);
+        assert!(!room.is_abandoned(Duration::ZERO));
+
+        let creator: IpAddr = "192.0.2.1".parse().unwrap();
+        let config = RoomConfig::new(RuleSet::from_name("standard").unwrap());
+        let mut room = ServerRoom::new(config, Some(creator)).unwrap();
+        assert_eq!(room.get_creator(), Some(creator));
+        assert!(room.is_abandoned(Duration::ZERO));
+        assert!(!room.is_abandoned(hour));
+
+        room.add_player("a", PlayerMode::Both).unwrap();
+        assert!(!room.is_abandoned(Duration::ZERO));
+        room.remove_player("a");
+        assert!(room.is_abandoned(Duration::ZERO));
+        assert!(!room.is_abandoned(hour));
+    }
+}
+
 // vim: ts=4 sw=4 expandtab
@@ -120,12 +120,6 @@ impl PlayerList {
         self.players.iter().find(|player| player.name == name)
     }
 
-    pub fn find_players_by_mode(&self, mode: PlayerMode) -> Vec<Player> {
-        let mut players = self.players.to_vec();
-        players.retain(|p| p.mode == mode);
-        players
-    }
-
     pub fn add_player(&mut self, player: Player) {
         self.players.push(player);
     }